@@ -0,0 +1,203 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Something the user can ask the app to do, whichever key or screen it comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    ToggleTypingMode,
+    ShowHelp,
+    ShowCommandPalette,
+    ShowDialPad,
+    Redial,
+    ShowRecentNumbers,
+    SwitchProfile,
+    HangUp,
+    SendTones,
+    AddContact,
+    EditContact,
+    DeleteContact,
+    CallContact,
+    SendSms,
+    EmailContact,
+    ShowTemplates,
+    CopyNumber,
+    CopyNameAndNumber,
+    CopyVCard,
+    CopyTemplate,
+    ToggleFavourite,
+    /// Calls the contact in a speed-dial slot, 1-9.
+    SpeedDial(u8),
+    PasteSignature,
+    ShowNotifications,
+    SelectNext,
+    SelectPrevious,
+}
+
+impl Action {
+    /// Every action that makes sense to run from the command palette.
+    pub const PALETTE: &'static [Action] = &[
+        Action::AddContact,
+        Action::EditContact,
+        Action::DeleteContact,
+        Action::CallContact,
+        Action::SendSms,
+        Action::EmailContact,
+        Action::ShowTemplates,
+        Action::CopyNumber,
+        Action::CopyNameAndNumber,
+        Action::CopyVCard,
+        Action::CopyTemplate,
+        Action::ShowDialPad,
+        Action::Redial,
+        Action::ShowRecentNumbers,
+        Action::SwitchProfile,
+        Action::HangUp,
+        Action::SendTones,
+        Action::ToggleFavourite,
+        Action::PasteSignature,
+        Action::ShowNotifications,
+        Action::ShowHelp,
+        Action::ToggleTypingMode,
+        Action::Quit,
+    ];
+
+    /// The keys bound to the action, e.g. `Ctrl+A`, in the order the binding tables list them.
+    pub fn key_labels(&self) -> Vec<String> {
+        GLOBAL_BINDINGS
+            .iter()
+            .chain(CONTACT_LIST_BINDINGS)
+            .filter(|binding| binding.action == *self)
+            .map(KeyBinding::label)
+            .collect()
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::ToggleTypingMode => "Toggle insert / overwrite typing",
+            Action::ShowHelp => "Show help",
+            Action::ShowCommandPalette => "Show the command palette",
+            Action::ShowDialPad => "Dial a number that isn't a contact",
+            Action::Redial => "Redial the last number",
+            Action::ShowRecentNumbers => "Call a recently dialled number",
+            Action::SwitchProfile => "Switch dialler profile",
+            Action::HangUp => "Hang up the current call",
+            Action::SendTones => "Send the rest of the tones after a wait",
+            Action::AddContact => "Add a contact",
+            Action::EditContact => "Edit the selected contact",
+            Action::DeleteContact => "Delete the selected contact",
+            Action::CallContact => "Call the selected contact",
+            Action::SendSms => "Send an SMS to the selected contact",
+            Action::EmailContact => "Email the selected contact",
+            Action::ShowTemplates => "Manage message templates",
+            Action::CopyNumber => "Copy the selected contact's number",
+            Action::CopyNameAndNumber => "Copy the selected contact's name and number",
+            Action::CopyVCard => "Copy the selected contact as a vCard",
+            Action::CopyTemplate => "Copy a template filled in for the selected contact",
+            Action::ToggleFavourite => "Add or remove the selected contact from favourites",
+            Action::SpeedDial(_) => "Call a speed-dial contact",
+            Action::PasteSignature => "Add a contact from a pasted signature",
+            Action::ShowNotifications => "Show the message log",
+            Action::SelectNext => "Select the next contact",
+            Action::SelectPrevious => "Select the previous contact",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyBinding {
+    pub modifiers: KeyModifiers,
+    pub code: KeyCode,
+    pub action: Action,
+}
+
+impl KeyBinding {
+    pub const fn new(modifiers: KeyModifiers, code: KeyCode, action: Action) -> Self {
+        Self { modifiers, code, action }
+    }
+
+    pub fn matches(&self, key_event: &KeyEvent) -> bool {
+        self.code == key_event.code && self.modifiers == key_event.modifiers
+    }
+
+    /// The key as shown to the user, e.g. `Ctrl+A`.
+    pub fn label(&self) -> String {
+        key_label(self.modifiers, self.code)
+    }
+}
+
+/// Keys that work on every screen.
+pub const GLOBAL_BINDINGS: &[KeyBinding] = &[
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('q'), Action::Quit),
+    KeyBinding::new(KeyModifiers::NONE, KeyCode::Insert, Action::ToggleTypingMode),
+    KeyBinding::new(KeyModifiers::NONE, KeyCode::F(1), Action::ShowHelp),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('p'), Action::ShowCommandPalette),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('r'), Action::Redial),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('r'), Action::ShowRecentNumbers),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('p'), Action::SwitchProfile),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('x'), Action::HangUp),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('g'), Action::SendTones),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('t'), Action::ShowTemplates),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('1'), Action::SpeedDial(1)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('2'), Action::SpeedDial(2)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('3'), Action::SpeedDial(3)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('4'), Action::SpeedDial(4)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('5'), Action::SpeedDial(5)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('6'), Action::SpeedDial(6)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('7'), Action::SpeedDial(7)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('8'), Action::SpeedDial(8)),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('9'), Action::SpeedDial(9)),
+];
+
+/// Keys on the contact list. Anything else edits the filter.
+pub const CONTACT_LIST_BINDINGS: &[KeyBinding] = &[
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('a'), Action::AddContact),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('e'), Action::EditContact),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('d'), Action::DeleteContact),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('c'), Action::CallContact),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('s'), Action::SendSms),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('e'), Action::EmailContact),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('c'), Action::CopyNumber),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('n'), Action::CopyNameAndNumber),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('v'), Action::CopyVCard),
+    KeyBinding::new(KeyModifiers::ALT, KeyCode::Char('m'), Action::CopyTemplate),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('f'), Action::ToggleFavourite),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('n'), Action::ShowDialPad),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('v'), Action::PasteSignature),
+    KeyBinding::new(KeyModifiers::CONTROL, KeyCode::Char('l'), Action::ShowNotifications),
+    KeyBinding::new(KeyModifiers::NONE, KeyCode::Down, Action::SelectNext),
+    KeyBinding::new(KeyModifiers::NONE, KeyCode::Up, Action::SelectPrevious),
+    KeyBinding::new(KeyModifiers::NONE, KeyCode::Esc, Action::Quit),
+];
+
+/// Finds the action bound to `key_event` in `bindings`.
+pub fn lookup(bindings: &[KeyBinding], key_event: &KeyEvent) -> Option<Action> {
+    bindings
+        .iter()
+        .find(|binding| binding.matches(key_event))
+        .map(|binding| binding.action)
+}
+
+pub fn key_label(modifiers: KeyModifiers, code: KeyCode) -> String {
+    let mut label = String::new();
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        label.push_str("Ctrl+");
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        label.push_str("Alt+");
+    }
+    if modifiers.contains(KeyModifiers::SHIFT) {
+        label.push_str("Shift+");
+    }
+
+    match code {
+        KeyCode::Char(' ') => label.push_str("Space"),
+        KeyCode::Char(c) => label.extend(c.to_uppercase()),
+        KeyCode::F(n) => label.push_str(&format!("F{n}")),
+        KeyCode::BackTab => label.push_str("Shift+Tab"),
+        KeyCode::PageUp => label.push_str("PgUp"),
+        KeyCode::PageDown => label.push_str("PgDn"),
+        code => label.push_str(&format!("{code:?}")),
+    }
+    label
+}
@@ -1,16 +1,49 @@
+use crate::action::{self, Action, GLOBAL_BINDINGS};
+use crate::clipboard::{self, ClipboardEvent, CopyStatus};
 use crate::config::Config;
+use crate::data_layer::call::{CallForInsert, CallStatus};
 use crate::data_layer::contact::{Contact, ContactForUpdate};
-use crate::dialog::modal::{DialogResult, Modal};
-use crate::error::AppResult;
+use crate::data_layer::message::{MessageForInsert, MessageStatus};
+use crate::data_layer::template::{Template, TemplateForUpdate};
+use crate::dialler::{self, ActiveCall, CallRequest, CallState, DiallerEvent};
+use crate::dialog::dialog_field::DialogField;
+use crate::dialog::modal::Modal;
+use crate::dialog::popup::{Popup, PopupResult};
+use crate::dialog::validator::{UniqueCheck, UniqueScope};
+use crate::editing::EditContext;
+use crate::error::{AppResult, Error};
 
 use crate::data_layer::db::Db;
 use crate::event::Event;
 use crate::line_buffer::LineBuffer;
+use crate::lookup::service::LookupService;
+use crate::lookup::Lookup;
+use crate::notification::Notifications;
+use crate::screen::command_palette::CommandPalette;
+use crate::screen::contact_form::ContactForm;
+use crate::screen::contact_list::ContactList;
+use crate::screen::dial_pad::{DialPad, MAX_MATCHES};
+use crate::screen::help::Help;
+use crate::screen::notification_log::NotificationLog;
+use crate::screen::paste_signature::PasteSignature;
+use crate::screen::sms_compose::SmsCompose;
+use crate::screen::template_list::TemplateList;
+use crate::screen::template_picker::TemplatePicker;
+use crate::screen::{Screen, ScreenAction, ScreenStack};
+use crate::sms::{self, SmsEvent, SmsRequest};
+use crate::uri::{self, DialNumber};
+use crate::vcard;
 use crossterm::cursor::SetCursorStyle;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent};
+use crossterm::event::{KeyCode, KeyEvent, MouseEvent};
 use rusqlite::Connection;
+use std::process::Stdio;
 use std::sync::{Arc, Mutex};
-use tracing::info;
+use std::time::Instant;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{info, warn};
+
+/// How many numbers the recent numbers popup offers, one per number key.
+const RECENT_NUMBERS: usize = 9;
 
 #[derive(Default, Debug, PartialEq, Clone)]
 pub enum TypingMode {
@@ -23,9 +56,11 @@ pub enum TypingMode {
 pub struct App {
     pub running: bool,
     type_mode: TypingMode,
-    pub mode: AppMode,
     pub state: AppState,
+    pub screens: ScreenStack,
     conn: Arc<Mutex<Option<Connection>>>,
+    events: Option<UnboundedSender<Event>>,
+    lookup_service: LookupService,
 }
 
 impl Default for App {
@@ -33,9 +68,11 @@ impl Default for App {
         Self {
             running: true,
             type_mode: TypingMode::Insert,
-            mode: AppMode::Filtering,
             state: AppState::default(),
+            screens: ScreenStack::default(),
             conn: Arc::default(),
+            events: None,
+            lookup_service: LookupService::default(),
         }
     }
 }
@@ -45,23 +82,31 @@ impl App {
 
         let conn = Arc::new(Mutex::new(Some(Config::create_db()?)));
 
-        let modal = Modal::add_contact();
+        let config = Config::new()?;
         let state = AppState {
             focus: AppFocus::Filter,
             selected_contact_index: 0,
             filter: Default::default(),
             contacts: vec![],
-            config: Config::new()?,
-            modal,
+            favourites: vec![],
+            edit_context: EditContext::new(&config),
+            config,
+            notifications: Notifications::default(),
+            active_calls: vec![],
+            templates: vec![],
         };
+        let mut screens = ScreenStack::default();
+        screens.push(ContactList);
         Ok(Self {
             conn,
             state,
+            screens,
             ..Default::default()
         })
     }
 
     pub fn tick(&mut self) -> AppResult<()> {
+        self.state.notifications.tick();
         Ok(())
     }
 
@@ -72,164 +117,997 @@ impl App {
     pub fn get_contacts(&mut self) -> AppResult<()> {
         let db = Db::new(self.conn.clone());
         self.state.contacts = db.list(self.state.filter.to_string())?;
+        self.state.favourites = db.favourites()?;
         self.state.selected_contact_index = 0;
 
         Ok(())
     }
 
-    pub fn insert_contact(&self) {
+    pub fn get_templates(&mut self) -> AppResult<()> {
+        let db = Db::new(self.conn.clone());
+        self.state.templates = db.templates()?;
+        Ok(())
+    }
+
+    /// Reloads the contacts, reporting a failure rather than returning it so the app keeps running
+    /// with the list it had.
+    pub fn refresh_contacts(&mut self) {
+        if let Err(err) = self.get_contacts() {
+            self.state.notifications.report("Could not load the contacts", &err);
+        }
+    }
+
+    /// Reloads the templates, reporting a failure rather than returning it.
+    pub fn refresh_templates(&mut self) {
+        if let Err(err) = self.get_templates() {
+            self.state.notifications.report("Could not load the templates", &err);
+        }
+    }
+
+    pub fn selected_contact(&self) -> Option<&Contact> {
+        self.state.contacts.get(self.state.selected_contact_index)
+    }
+
+    pub fn insert_contact(&self, contact: ContactForUpdate) -> AppResult<usize> {
         info!("Running insert_contact");
         let db = Db::new(self.conn.clone());
+        db.insert(contact)
+    }
 
-        let c = ContactForUpdate {
-            first_name: Some(self.state.modal.fields[0].to_string()),
-            last_name: Some(self.state.modal.fields[1].to_string()),
-            phone_number: self.state.modal.fields[2].to_string(),
-            company_name: Some(self.state.modal.fields[3].to_string()),
+    /// Whether `value` is already used by the unique `field` of another row in `scope`.
+    fn is_taken(db: &Db, scope: UniqueScope, field: &str, value: &str) -> AppResult<bool> {
+        match (scope, field) {
+            (UniqueScope::Contacts { excluding }, "phone") => db.phone_number_exists(value, excluding),
+            (UniqueScope::Contacts { excluding }, "speed_dial") => match value.parse() {
+                Ok(slot) => db.speed_dial_exists(slot, excluding),
+                // "None" can be shared.
+                Err(_) => Ok(false),
+            },
+            (UniqueScope::Templates { excluding }, "name") => db.template_name_exists(value, excluding),
+            (scope, field) => {
+                warn!("No uniqueness check for the {field} field in {scope:?}");
+                Ok(false)
+            }
+        }
+    }
+
+    /// Checks form values against the database on a blocking thread, sending each outcome back as
+    /// an [`Event::UniqueCheck`] so the form can flag a taken value before it is submitted.
+    pub fn check_unique(&self, scope: UniqueScope, values: Vec<(String, String)>) {
+        let Some(events) = self.events.clone().filter(|_| !values.is_empty()) else {
+            return;
         };
+        let db = Db::new(self.conn.clone());
+        tokio::task::spawn_blocking(move || {
+            for (field, value) in values {
+                match Self::is_taken(&db, scope, &field, &value) {
+                    Ok(taken) => {
+                        let check = UniqueCheck {
+                            scope,
+                            field,
+                            value,
+                            taken,
+                        };
+                        if events.send(Event::UniqueCheck(check)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => warn!("Could not check whether {value} is taken: {}", err.full_message()),
+                }
+            }
+        });
+    }
 
-        let _ = db.insert(c);
+    fn contact_from_modal(modal: &Modal) -> ContactForUpdate {
+        ContactForUpdate {
+            first_name: Some(modal.value("first").to_string()),
+            last_name: Some(modal.value("last").to_string()),
+            phone_number: modal.value("phone").to_string(),
+            company_name: Some(modal.value("company").to_string()),
+            email: Some(modal.value("email").to_string()),
+            favourite: modal.get_field("favourite").is_some_and(DialogField::is_checked),
+            dtmf: Some(modal.value("dtmf").trim().to_string()).filter(|dtmf| !dtmf.is_empty()),
+            speed_dial: modal
+                .get_field("speed_dial")
+                .and_then(DialogField::get_selected_index)
+                .filter(|slot| *slot > 0)
+                .map(|slot| slot as u8),
+        }
     }
 
-    pub fn delete_contact(&self) {
+    pub fn delete_contact(&mut self, id: u64, name: &str) {
         let db = Db::new(self.conn.clone());
-        let c = &self.state.contacts[self.state.selected_contact_index];
 
-        let _ = db.delete(c.id);
+        match db.delete(id) {
+            Ok(0) => self
+                .state
+                .notifications
+                .warning(format!("{name} was already deleted")),
+            Ok(_) => self.state.notifications.success(format!("Deleted {name}")),
+            Err(err) => self
+                .state
+                .notifications
+                .report(format!("Could not delete {name}"), &err),
+        }
     }
 
-    pub fn update_contact(&self) {
+    /// Asks for confirmation before deleting the selected contact.
+    pub fn confirm_delete_selected_contact(&mut self) {
+        let Some(c) = self.state.contacts.get(self.state.selected_contact_index) else {
+            self.state.selected_contact_index = 0;
+            return;
+        };
+
+        let (id, name) = (c.id, c.display_name());
+        let popup = Popup::confirm(
+            "Delete?",
+            format!("Are you sure you want to delete {name} ({})?", c.phone_number),
+        )
+        .danger()
+        .on_result(move |app, result| {
+            if result == PopupResult::Confirmed {
+                info!("Deleting contact");
+                app.delete_contact(id, &name);
+                app.refresh_contacts();
+            }
+            Ok(())
+        });
+        self.push_popup(popup);
+    }
+
+    pub fn push_popup(&mut self, popup: Popup) {
+        self.screens.push(popup);
+    }
+
+    pub fn update_contact(&self, id: u64, contact: ContactForUpdate) -> AppResult<usize> {
+        let db = Db::new(self.conn.clone());
+        db.update(id, contact)
+    }
+
+    /// Checks a contact modal against the database and saves it, inserting when `id` is `None`.
+    /// Returns `false`, with the problem shown in the modal, if the contact couldn't be saved.
+    pub fn save_contact(&mut self, modal: &mut Modal, id: Option<u64>) -> bool {
         let db = Db::new(self.conn.clone());
-        let c = &self.state.contacts[self.state.selected_contact_index];
+        let scope = UniqueScope::Contacts { excluding: id };
+        match modal.validate_unique(|field, value| Self::is_taken(&db, scope, field, value)) {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(err) => {
+                modal.error = Some(format!("Could not check the contact: {}", err.full_message()));
+                self.state.notifications.report("Could not check the contact", &err);
+                return false;
+            }
+        }
+
+        let contact = Self::contact_from_modal(modal);
+        let result = match id {
+            Some(id) => self.update_contact(id, contact),
+            None => self.insert_contact(contact),
+        };
+
+        let name = format!("{} {}", modal.value("first"), modal.value("last"))
+            .trim()
+            .to_string();
+        let name = if name.is_empty() { modal.value("phone").to_string() } else { name };
+        match result {
+            Ok(_) => {
+                let verb = if id.is_some() { "Updated" } else { "Added" };
+                self.state.notifications.success(format!("{verb} {name}"));
+                true
+            }
+            Err(err @ Error::DuplicatePhoneNumber(_)) => {
+                if let Some(index) = modal.fields.iter().position(|field| field.name == "phone") {
+                    modal.fields[index].error = Some(err.to_string());
+                    modal.focused_index = index;
+                }
+                false
+            }
+            Err(err) => {
+                modal.error = Some(format!("Could not save contact: {}", err.full_message()));
+                self.state.notifications.report(format!("Could not save {name}"), &err);
+                false
+            }
+        }
+    }
+
+    pub fn call_selected_contact(&mut self) {
+        let Some(c) = self.state.contacts.get(self.state.selected_contact_index) else {
+            self.state.notifications.warning("No contact selected");
+            return;
+        };
+        let call = CallForInsert::for_contact(c);
+        self.call(call);
+    }
+
+    /// Opens the SMS dialog for the selected contact, if texts can be sent.
+    pub fn compose_sms_to_selected_contact(&mut self) {
+        if self.state.config.sms.is_none() {
+            self.state
+                .notifications
+                .error("No SMS gateway is set up; add one in sms.toml");
+            return;
+        }
+        let Some(c) = self.selected_contact().cloned() else {
+            self.state.notifications.warning("No contact selected");
+            return;
+        };
+        self.pick_template("SMS", "Blank message", c, |app, c, template| {
+            let mut compose = SmsCompose::for_contact(&c);
+            if let Some(template) = template {
+                compose.modal.set_value("message", template.body);
+            }
+            app.screens.push(compose);
+            Ok(())
+        });
+    }
 
-        let _ = db.update(c.id, ContactForUpdate {
-            first_name: Some(self.state.modal.fields[0].to_string()),
-            last_name: Some(self.state.modal.fields[1].to_string()),
-            phone_number: self.state.modal.fields[2].to_string(),
-            company_name: Some(self.state.modal.fields[3].to_string()),
+    /// Opens an email to the selected contact in the mail program, starting from a template.
+    pub fn email_selected_contact(&mut self) {
+        let Some(c) = self.selected_contact().cloned() else {
+            self.state.notifications.warning("No contact selected");
+            return;
+        };
+        if c.email.as_deref().is_none_or(|email| email.trim().is_empty()) {
+            self.state
+                .notifications
+                .warning(format!("{} has no email address", c.display_name()));
+            return;
+        }
+        self.pick_template("Email", "Blank email", c, |app, c, template| {
+            let body = template.map(|template| template.render(Some(&c))).unwrap_or_default();
+            app.open_mail(&c, &body);
+            Ok(())
         });
     }
 
-    pub fn call_selected_contact(&self) {
-        let c = &self.state.contacts[self.state.selected_contact_index];
-        info!("Calling \"{}\"", c.phone_number);
-        let _ = std::process::Command::new(&self.state.config.dialler_program)
-            .arg(c.phone_number.replace(' ', ""))
+    /// Runs the mail program with a `mailto:` URI for `contact` with `body` filled in.
+    fn open_mail(&mut self, contact: &Contact, body: &str) {
+        let address = contact.email.as_deref().unwrap_or_default().trim();
+        let program = self.state.config.mail_program.clone();
+        let child = tokio::process::Command::new(&program)
+            .arg(uri::mailto(address, body))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
             .spawn();
+        match child {
+            Ok(mut child) => {
+                info!("Started mail program with pid {:?}", child.id());
+                tokio::spawn(async move {
+                    match child.wait().await {
+                        Ok(status) if !status.success() => warn!("Mail program \"{program}\" exited with {status}"),
+                        Ok(_) => {}
+                        Err(err) => warn!("Could not wait for mail program \"{program}\": {err}"),
+                    }
+                });
+                self.state
+                    .notifications
+                    .info(format!("Writing an email to {}", contact.display_name()));
+            }
+            Err(err) => self
+                .state
+                .notifications
+                .error(format!("Could not start mail program \"{program}\": {err}")),
+        }
+    }
+
+    /// Asks which template to start from when there are any, then runs `then` with it.
+    fn pick_template(
+        &mut self,
+        title: &str,
+        blank: &str,
+        contact: Contact,
+        then: impl FnOnce(&mut App, Contact, Option<Template>) -> AppResult<()> + 'static,
+    ) {
+        self.refresh_templates();
+        if self.state.templates.is_empty() {
+            if let Err(err) = then(self, contact, None) {
+                self.state.notifications.report("Could not use the template", &err);
+            }
+            return;
+        }
+
+        let title = format!("{title} to {}", contact.display_name());
+        let picker = TemplatePicker::new(title, Some(contact.clone()))
+            .blank(blank)
+            .on_pick(move |app, template| then(app, contact, template));
+        self.screens.push(picker);
+    }
+
+    /// Copies `text` to the clipboard, confirming with a toast that says it copied `what`. With a
+    /// clipboard command the toast waits for [`App::handle_clipboard_event`].
+    pub fn copy_to_clipboard(&mut self, what: &str, text: &str) {
+        match clipboard::copy(&self.state.config.clipboard, what, text, self.events.clone()) {
+            Ok(CopyStatus::Sent) => self.state.notifications.success(format!("Copied {what}")),
+            Ok(CopyStatus::Started) => {}
+            Err(err) => self
+                .state
+                .notifications
+                .report(format!("Could not copy {what}"), &err),
+        }
+    }
+
+    /// Says how the clipboard command went. If it failed after OSC 52 was sent, the text may
+    /// still have been copied, so that's only a warning.
+    pub fn handle_clipboard_event(&mut self, event: ClipboardEvent) {
+        match event {
+            ClipboardEvent::Copied { what } => self.state.notifications.success(format!("Copied {what}")),
+            ClipboardEvent::Failed { what, osc52: true, message } => self
+                .state
+                .notifications
+                .warning(format!("Copied {what} through the terminal only: {message}")),
+            ClipboardEvent::Failed { what, message, .. } => self
+                .state
+                .notifications
+                .report(format!("Could not copy {what}"), &Error::Clipboard(message)),
+        }
+    }
+
+    /// Copies something about the selected contact, as `text` formats it.
+    pub fn copy_selected_contact(&mut self, what: &str, text: impl Fn(&Contact) -> String) {
+        let Some(c) = self.selected_contact() else {
+            self.state.notifications.warning("No contact selected");
+            return;
+        };
+        let (what, text) = (format!("{}'s {what}", c.display_name()), text(c));
+        self.copy_to_clipboard(&what, &text);
+    }
+
+    /// Asks which template to copy, filled in for the selected contact.
+    pub fn copy_template_for_selected_contact(&mut self) {
+        let Some(c) = self.selected_contact().cloned() else {
+            self.state.notifications.warning("No contact selected");
+            return;
+        };
+        self.refresh_templates();
+        if self.state.templates.is_empty() {
+            self.state
+                .notifications
+                .warning("There are no templates yet; add one with Alt+T");
+            return;
+        }
+
+        let picker = TemplatePicker::new(format!("Copy for {}", c.display_name()), Some(c.clone())).on_pick(
+            move |app, template| {
+                if let Some(template) = template {
+                    let what = format!("\"{}\" for {}", template.name, c.display_name());
+                    app.copy_to_clipboard(&what, &template.render(Some(&c)));
+                }
+                Ok(())
+            },
+        );
+        self.screens.push(picker);
+    }
+
+    pub fn show_templates(&mut self) {
+        self.refresh_templates();
+        self.screens.push(TemplateList::default());
+    }
+
+    /// Checks a template modal against the database and saves it, inserting when `id` is `None`.
+    /// Returns `false`, with the problem shown in the modal, if the template couldn't be saved.
+    pub fn save_template(&mut self, modal: &mut Modal, id: Option<u64>) -> bool {
+        let db = Db::new(self.conn.clone());
+        let scope = UniqueScope::Templates { excluding: id };
+        match modal.validate_unique(|field, value| Self::is_taken(&db, scope, field, value)) {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(err) => {
+                modal.error = Some(format!("Could not check the template: {}", err.full_message()));
+                self.state.notifications.report("Could not check the template", &err);
+                return false;
+            }
+        }
+
+        let name = modal.value("name").trim().to_string();
+        let template = TemplateForUpdate {
+            name: name.clone(),
+            body: modal.value("body").trim().to_string(),
+        };
+        let result = match id {
+            Some(id) => db.update_template(id, template),
+            None => db.insert_template(template),
+        };
+        match result {
+            Ok(_) => {
+                let verb = if id.is_some() { "Updated" } else { "Added" };
+                self.state.notifications.success(format!("{verb} template {name}"));
+                true
+            }
+            Err(err) => {
+                modal.error = Some(format!("Could not save template: {}", err.full_message()));
+                self.state.notifications.report(format!("Could not save template {name}"), &err);
+                false
+            }
+        }
+    }
+
+    /// Asks for confirmation before deleting `template`.
+    pub fn confirm_delete_template(&mut self, template: Template) {
+        let popup = Popup::confirm("Delete?", format!("Are you sure you want to delete the template {}?", template.name))
+            .danger()
+            .on_result(move |app, result| {
+                if result != PopupResult::Confirmed {
+                    return Ok(());
+                }
+                let db = Db::new(app.conn.clone());
+                match db.delete_template(template.id) {
+                    Ok(_) => app
+                        .state
+                        .notifications
+                        .success(format!("Deleted template {}", template.name)),
+                    Err(err) => app
+                        .state
+                        .notifications
+                        .report(format!("Could not delete template {}", template.name), &err),
+                }
+                app.refresh_templates();
+                Ok(())
+            });
+        self.push_popup(popup);
+    }
+
+    /// Logs the message against its contact and hands it to the SMS gateway, which reports back
+    /// with an [`SmsEvent`].
+    pub fn send_sms(&mut self, message: MessageForInsert) {
+        let Some(gateway) = &self.state.config.sms else {
+            self.state.notifications.error("No SMS gateway is set up");
+            return;
+        };
+        let name = message.name.clone().unwrap_or_else(|| message.phone_number.clone());
+        let to = DialNumber::new(&message.phone_number).number;
+        let body = message.body.clone();
+        let sender = sms::for_config(gateway);
+        let db = Db::new(self.conn.clone());
+
+        // Without a row of its own the message has no id to be told apart by, so it isn't sent.
+        let message_id = match db.record_message(message, gateway.name()) {
+            Ok(id) => id,
+            Err(err) => {
+                self.state
+                    .notifications
+                    .report(format!("Could not log the SMS to {name}, so it wasn't sent"), &err);
+                return;
+            }
+        };
+
+        let request = SmsRequest {
+            message_id,
+            to,
+            name: name.clone(),
+            body,
+        };
+        match sender.send(request, self.events.clone()) {
+            Ok(()) => self.state.notifications.info(format!("Sending SMS to {name}")),
+            Err(err) => {
+                if let Err(err) = db.finish_message(message_id, MessageStatus::Failed, Some(err.full_message())) {
+                    warn!("Could not record the failed message: {}", err.full_message());
+                }
+                self.state
+                    .notifications
+                    .report(format!("Could not send SMS to {name}"), &err);
+            }
+        }
+    }
+
+    /// Records how a text went and tells the user.
+    pub fn handle_sms_event(&mut self, event: SmsEvent) {
+        let db = Db::new(self.conn.clone());
+        let recorded = match event {
+            SmsEvent::Sent { message_id, name } => {
+                self.state.notifications.success(format!("Sent SMS to {name}"));
+                db.finish_message(message_id, MessageStatus::Sent, None)
+            }
+            SmsEvent::Failed {
+                message_id,
+                name,
+                message,
+            } => {
+                self.state
+                    .notifications
+                    .report(format!("Could not send SMS to {name}"), &Error::SmsGateway(message.clone()));
+                db.finish_message(message_id, MessageStatus::Failed, Some(message))
+            }
+        };
+        if let Err(err) = recorded {
+            self.state.notifications.report("Could not save the message log", &err);
+        }
+    }
+
+    /// Calls whoever is in speed-dial `slot`.
+    pub fn speed_dial(&mut self, slot: u8) {
+        let db = Db::new(self.conn.clone());
+        match db.find_by_speed_dial(slot) {
+            Ok(Some(c)) => self.call(CallForInsert::for_contact(&c)),
+            Ok(None) => self
+                .state
+                .notifications
+                .warning(format!("Nobody is on speed dial {slot}")),
+            Err(err) => self
+                .state
+                .notifications
+                .report(format!("Could not look up speed dial {slot}"), &err),
+        }
+    }
+
+    /// Calls the last number dialled again.
+    pub fn redial(&mut self) {
+        let db = Db::new(self.conn.clone());
+        match db.recent_calls(1) {
+            Ok(calls) => match calls.first() {
+                Some(last) => self.call(CallForInsert::again(last)),
+                None => self.state.notifications.warning("Nothing to redial yet"),
+            },
+            Err(err) => self.state.notifications.report("Could not find the last call", &err),
+        }
+    }
+
+    /// Offers the last few numbers dialled, picked by their number key.
+    pub fn choose_recent_number(&mut self) {
+        let db = Db::new(self.conn.clone());
+        let recent = match db.recent_numbers(RECENT_NUMBERS) {
+            Ok(recent) => recent,
+            Err(err) => {
+                self.state.notifications.report("Could not load the recent numbers", &err);
+                return;
+            }
+        };
+        if recent.is_empty() {
+            self.state.notifications.warning("No calls yet");
+            return;
+        }
+
+        let options = recent
+            .iter()
+            .map(|call| {
+                let when = call.dialled_at().format("%a %H:%M");
+                if call.name.is_some() {
+                    format!("{} - {} ({when})", call.display_name(), call.dialled())
+                } else {
+                    format!("{} ({when})", call.dialled())
+                }
+            })
+            .collect();
+        let popup = Popup::choice("Recent Numbers", "Pick a number to call it again", options).on_result(
+            move |app, result| {
+                if let PopupResult::Choice(index) = result {
+                    app.call(CallForInsert::again(&recent[index]));
+                }
+                Ok(())
+            },
+        );
+        self.push_popup(popup);
+    }
+
+    /// Contacts whose number contains `digits`, for the dial pad's reverse lookup.
+    pub fn lookup_number(&self, digits: &str, limit: usize) -> AppResult<Vec<Contact>> {
+        let db = Db::new(self.conn.clone());
+        db.find_by_number(digits, limit)
+    }
+
+    /// Records the call in the history and hands it to the dialler for its profile, the active
+    /// one unless the call names another. [`App::handle_dialler_event`] hears how it goes.
+    pub fn call(&mut self, call: CallForInsert) {
+        let number = DialNumber::with_tones(&call.phone_number, call.dtmf.as_deref());
+        self.call_number(call, number);
+    }
+
+    /// Like [`App::call`], but dials `number`, which can carry an extension and post-dial digits.
+    pub fn call_number(&mut self, mut call: CallForInsert, number: DialNumber) {
+        info!("Calling \"{}\"", call.phone_number);
+        let name = call.name.clone().unwrap_or_else(|| call.phone_number.clone());
+        let phone_number = call.phone_number.clone();
+        let config = &self.state.config;
+        let Some(profile) = call
+            .profile
+            .as_deref()
+            .and_then(|name| config.get_profile(name))
+            .or_else(|| config.active_profile())
+        else {
+            self.state.notifications.error("No dialler profile is set up");
+            return;
+        };
+        let address = match profile.address(&number) {
+            Ok(address) => address,
+            Err(err) => {
+                self.state.notifications.report(format!("Could not call {name}"), &err);
+                return;
+            }
+        };
+        let dialler = dialler::for_profile(profile);
+        call.profile = Some(profile.name.clone());
+        call.dtmf = number.tones();
+        let db = Db::new(self.conn.clone());
+
+        // The history row's id is what the dialler's events name the call by, so without one
+        // the call couldn't be followed and isn't made.
+        let call_id = match db.record_call(call, CallStatus::Active, None) {
+            Ok(id) => id,
+            Err(err) => {
+                self.state
+                    .notifications
+                    .report(format!("Could not save the call to {name} in the history, so it wasn't made"), &err);
+                return;
+            }
+        };
+
+        let request = CallRequest {
+            call_id,
+            number,
+            address,
+            name: name.clone(),
+        };
+        match dialler.dial(request, self.events.clone()) {
+            Ok(control) => {
+                self.state
+                    .notifications
+                    .info(format!("Calling {name} on {phone_number}"));
+                self.state.active_calls.push(ActiveCall {
+                    call_id,
+                    name,
+                    phone_number,
+                    started: Instant::now(),
+                    state: None,
+                    control,
+                });
+            }
+            Err(err) => {
+                if let Err(err) = db.finish_call(call_id, CallStatus::Failed, None, Some(err.full_message())) {
+                    warn!("Could not record the failed call: {}", err.full_message());
+                }
+                self.state
+                    .notifications
+                    .report(format!("Could not call {name}"), &err);
+            }
+        }
     }
+
+    /// Calls a number read from a URI, such as one handed to `dialler-rs dial`, as the contact
+    /// with that number if there is one.
+    pub fn dial_number(&mut self, mut number: DialNumber) -> AppResult<()> {
+        let contact = self
+            .lookup_number(&number.number, MAX_MATCHES)?
+            .into_iter()
+            .find(|contact| DialNumber::new(&contact.phone_number).number == number.number);
+        let call = match contact {
+            Some(contact) => CallForInsert::for_contact(&contact),
+            None => CallForInsert::for_number(number.number.clone()),
+        };
+        // Tones in the URI win, but the contact's own are used if it has none.
+        if number.tones().is_none() {
+            number.post_dial = call.dtmf.clone();
+        }
+        self.call_number(call, number);
+        Ok(())
+    }
+
+    /// Tracks a call's progress, and records how it ended, reporting any failure.
+    pub fn handle_dialler_event(&mut self, event: DiallerEvent) {
+        let (call_id, outcome) = match event {
+            DiallerEvent::Progress { call_id, state } => {
+                if let Some(call) = self.state.active_calls.iter_mut().find(|call| call.call_id == call_id) {
+                    call.state = Some(state);
+                    if state == CallState::Waiting {
+                        let key = Action::SendTones.key_labels().join("/");
+                        self.state
+                            .notifications
+                            .info(format!("Press {key} when {} is ready for the rest of the tones", call.name));
+                    }
+                }
+                return;
+            }
+            DiallerEvent::Exited {
+                call_id,
+                program,
+                success,
+                code,
+                stderr,
+            } => {
+                let outcome = if success {
+                    Ok(code)
+                } else {
+                    let err = Error::DiallerExited {
+                        program,
+                        code,
+                        stderr: stderr.lines().last().unwrap_or_default().to_string(),
+                    };
+                    let detail = if stderr.is_empty() { err.to_string() } else { stderr };
+                    Err((err, code, detail))
+                };
+                (call_id, outcome)
+            }
+            DiallerEvent::Ended { call_id, reason } => {
+                if let Some(call) = self.state.active_calls.iter().find(|call| call.call_id == call_id) {
+                    let reason = reason.map(|reason| format!(" ({reason})")).unwrap_or_default();
+                    self.state
+                        .notifications
+                        .info(format!("Call with {} ended{reason}", call.name));
+                }
+                (call_id, Ok(None))
+            }
+            DiallerEvent::Failed {
+                call_id,
+                backend,
+                message,
+            } => {
+                let err = Error::DiallerBackend {
+                    backend,
+                    message: message.clone(),
+                };
+                (call_id, Err((err, None, message)))
+            }
+        };
+
+        let call = self
+            .state
+            .active_calls
+            .iter()
+            .position(|call| call.call_id == call_id)
+            .map(|index| self.state.active_calls.remove(index));
+        let name = call.map_or_else(|| "the last number".to_string(), |call| call.name);
+
+        let db = Db::new(self.conn.clone());
+        let recorded = match outcome {
+            Ok(code) => db.finish_call(call_id, CallStatus::Ended, code, None),
+            Err((err, code, detail)) => {
+                self.state.notifications.report(format!("Call to {name} failed"), &err);
+                db.finish_call(call_id, CallStatus::Failed, code, Some(detail))
+            }
+        };
+        if let Err(err) = recorded {
+            self.state
+                .notifications
+                .report(format!("Could not save the call to {name} in the history"), &err);
+        }
+    }
+
+    /// Asks the dialler to hang up the latest call, if its backend can.
+    pub fn hang_up(&mut self) {
+        let Some(call) = self.state.active_calls.last() else {
+            self.state.notifications.info("There's no call to hang up");
+            return;
+        };
+        if call.control.hang_up() {
+            self.state.notifications.info(format!("Hanging up on {}", call.name));
+        } else {
+            self.state
+                .notifications
+                .warning(format!("The call to {} can't be hung up from here; use your phone", call.name));
+        }
+    }
+
+    /// Lets a call waiting at a `;` in its tones go on.
+    pub fn send_tones(&mut self) {
+        let waiting = self
+            .state
+            .active_calls
+            .iter()
+            .rev()
+            .find(|call| call.state == Some(CallState::Waiting));
+        match waiting {
+            Some(call) if call.control.send_tones() => {}
+            _ => self.state.notifications.info("No call is waiting to send tones"),
+        }
+    }
+
+    /// Offers the dialler profiles and makes the chosen one the active profile.
+    pub fn choose_profile(&mut self) {
+        let profiles: Vec<String> = self.state.config.profiles.iter().map(|p| p.name.clone()).collect();
+        let options = self
+            .state
+            .config
+            .profiles
+            .iter()
+            .map(|p| {
+                let active = if p.name == self.state.config.profile { " (active)" } else { "" };
+                format!("{} - {}{active}", p.name, p.backend.name())
+            })
+            .collect();
+        let popup = Popup::choice("Dialler Profile", "Calls go through the chosen profile", options).on_result(
+            move |app, result| {
+                if let PopupResult::Choice(index) = result {
+                    app.state.config.profile = profiles[index].clone();
+                    app.state
+                        .notifications
+                        .success(format!("Calling through {}", profiles[index]));
+                }
+                Ok(())
+            },
+        );
+        self.push_popup(popup);
+    }
+
+    /// Lets background work such as dialler supervision send events to the loop.
+    pub fn set_event_sender(&mut self, events: UnboundedSender<Event>) {
+        self.events = Some(events);
+    }
+
+    /// Starts answering lookups from other programs, reporting it if that fails.
+    pub fn start_lookup_service(&mut self) {
+        match LookupService::start(&self.state.config.lookup, self.conn.clone(), self.events.clone()) {
+            Ok(service) => self.lookup_service = service,
+            Err(err) => self
+                .state
+                .notifications
+                .report("Could not start the lookup service", &err),
+        }
+    }
+
+    /// Pops up who a number another program looked up belongs to, if the user asked for that,
+    /// offering to add numbers that aren't in the contacts.
+    pub fn handle_lookup(&mut self, lookup: Lookup) {
+        if !self.state.config.lookup.popup {
+            return;
+        }
+
+        let number = lookup.number.clone();
+        let popup = match lookup.best() {
+            Some(found) => {
+                let others = match lookup.matches.len() - 1 {
+                    0 => String::new(),
+                    1 => ", or one other contact".to_string(),
+                    others => format!(", or one of {others} other contacts"),
+                };
+                Popup::alert("Caller ID", format!("{number} is {}{others}", found.label()))
+            }
+            None => Popup::confirm("Caller ID", format!("{number} isn't in your contacts. Add it?")).on_result(
+                move |app, result| {
+                    if result == PopupResult::Confirmed {
+                        app.screens.push(ContactForm::for_number(&number));
+                    }
+                    Ok(())
+                },
+            ),
+        };
+        self.push_popup(popup);
+    }
+
+    /// Flips the favourite flag on the selected contact.
+    pub fn toggle_favourite_selected_contact(&mut self) {
+        let Some(c) = self.state.contacts.get(self.state.selected_contact_index) else {
+            self.state.notifications.warning("No contact selected");
+            return;
+        };
+
+        let (id, name, favourite) = (c.id, c.display_name(), !c.favourite);
+        let db = Db::new(self.conn.clone());
+        match db.set_favourite(id, favourite) {
+            Ok(_) if favourite => self.state.notifications.success(format!("Added {name} to favourites")),
+            Ok(_) => self.state.notifications.success(format!("Removed {name} from favourites")),
+            Err(err) => self
+                .state
+                .notifications
+                .report(format!("Could not update {name}"), &err),
+        }
+
+        let selected = self.state.selected_contact_index;
+        self.refresh_contacts();
+        self.state.selected_contact_index = selected.min(self.state.contacts.len().saturating_sub(1));
+    }
+
     pub fn handle_event(&mut self, event: Event) -> AppResult<()> {
         match event {
             Event::Tick => self.tick()?,
-            Event::Key(key_event) => self.handle_key_event(key_event, self.type_mode.clone())?,
+            Event::Key(key_event) => self.handle_key_event(key_event)?,
             Event::Mouse(mouse_event) => self.handle_mouse_event(mouse_event)?,
             Event::Resize(_, _) => {}
+            Event::Paste(text) => self.handle_paste(&text)?,
+            Event::Dialler(event) => self.handle_dialler_event(event),
+            Event::Lookup(lookup) => self.handle_lookup(lookup),
+            Event::Sms(event) => self.handle_sms_event(event),
+            Event::UniqueCheck(check) => self.screens.handle_unique_check(&check),
+            Event::Clipboard(event) => self.handle_clipboard_event(event),
         }
 
         Ok(())
     }
 
-    pub fn handle_mouse_event(&mut self, _: MouseEvent) -> AppResult<()> {
-        Ok(())
-    }
-
-    pub fn load_edit_modal(&mut self) {
-        let c = &self.state.contacts[self.state.selected_contact_index];
-        let mut modal = Modal::add_contact();
-        modal.fields[0].set_value(c.first_name.clone().unwrap_or_default());
-        modal.fields[1].set_value(c.last_name.clone().unwrap_or_default());
-        modal.fields[2].set_value(c.phone_number.clone());
-        modal.fields[3].set_value(c.company_name.clone().unwrap_or_default());
-        self.state.modal = modal;
+    /// Gives a paste to the topmost screen.
+    pub fn handle_paste(&mut self, text: &str) -> AppResult<()> {
+        self.with_top_screen(|screen, app| screen.handle_paste(app, text))
     }
 
-    pub fn handle_key_event(
+    /// Takes the topmost screen off the stack while `f` runs, so it can change the app, then
+    /// puts it back according to the returned [`ScreenAction`].
+    fn with_top_screen(
         &mut self,
-        key_event: KeyEvent,
-        type_mode: TypingMode,
+        f: impl FnOnce(&mut dyn Screen, &mut App) -> AppResult<ScreenAction>,
     ) -> AppResult<()> {
+        let Some((index, mut screen)) = self.screens.take_top() else {
+            self.quit();
+            return Ok(());
+        };
+        match f(screen.as_mut(), self) {
+            Ok(action) => {
+                self.screens.restore(index, screen, action);
+                Ok(())
+            }
+            Err(err) => {
+                self.screens.restore(index, screen, ScreenAction::None);
+                Err(err)
+            }
+        }
+    }
 
-        match (key_event.modifiers, key_event.code) {
-            (_, KeyCode::Esc) if self.mode == AppMode::Filtering => self.quit(),
-            (KeyModifiers::CONTROL, KeyCode::Char(c)) => match c {
-                'q' => self.quit(),
-                'a' => {
-                    self.mode = AppMode::AddingContact;
-                }
-                'c' => self.call_selected_contact(),
-                'd' => {
-                    if (self.state.contacts.get(self.state.selected_contact_index)).is_some() {
-                        self.mode = AppMode::DeletingContact;
-                    } else {
-                        self.state.selected_contact_index = 0;
-                    }
+    pub fn handle_mouse_event(&mut self, _: MouseEvent) -> AppResult<()> {
+        Ok(())
+    }
+
+    /// Gives the key to the topmost screen if it captures it, then tries the app-wide bindings,
+    /// then the screen again. Esc closes the topmost screen unless it captures Esc.
+    pub fn handle_key_event(&mut self, key_event: KeyEvent) -> AppResult<()> {
+        self.with_top_screen(|screen, app| {
+            if !screen.captures(&key_event) {
+                if let Some(action) = action::lookup(GLOBAL_BINDINGS, &key_event) {
+                    app.perform(action);
+                    return Ok(ScreenAction::None);
                 }
-                'e' => {
-                    self.load_edit_modal();
-                    self.mode = AppMode::EditingContact;
+                if key_event.code == KeyCode::Esc {
+                    return Ok(ScreenAction::Close);
                 }
-                _ => {}
-            },
-            (_, KeyCode::Insert) => {
+            }
+            screen.handle_key_event(app, key_event)
+        })
+    }
+
+    /// Carries out an [`Action`], whichever key or screen asked for it. Failures are reported as
+    /// notifications, so none of them ends the app.
+    pub fn perform(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.quit(),
+            Action::ToggleTypingMode => {
                 self.type_mode = match self.type_mode {
                     TypingMode::Insert => TypingMode::Overwrite,
                     TypingMode::Overwrite => TypingMode::Insert,
                 };
             }
-            (_, KeyCode::Down) => {
+            Action::ShowHelp => self.screens.push(Help::new()),
+            Action::ShowDialPad => self.screens.push(DialPad::default()),
+            Action::ShowCommandPalette => self.screens.push(CommandPalette::default()),
+            Action::AddContact => self.screens.push(ContactForm::add()),
+            Action::EditContact => {
+                if let Some(contact) = self.state.contacts.get(self.state.selected_contact_index) {
+                    let form = ContactForm::edit(contact);
+                    self.screens.push(form);
+                }
+            }
+            Action::DeleteContact => self.confirm_delete_selected_contact(),
+            Action::CallContact => self.call_selected_contact(),
+            Action::SendSms => self.compose_sms_to_selected_contact(),
+            Action::EmailContact => self.email_selected_contact(),
+            Action::ShowTemplates => self.show_templates(),
+            Action::CopyNumber => self.copy_selected_contact("number", |c| c.phone_number.clone()),
+            Action::CopyNameAndNumber => self.copy_selected_contact("name and number", Contact::name_and_number),
+            Action::CopyVCard => self.copy_selected_contact("vCard", vcard::format),
+            Action::CopyTemplate => self.copy_template_for_selected_contact(),
+            Action::Redial => self.redial(),
+            Action::SwitchProfile => self.choose_profile(),
+            Action::HangUp => self.hang_up(),
+            Action::SendTones => self.send_tones(),
+            Action::ShowRecentNumbers => self.choose_recent_number(),
+            Action::ToggleFavourite => self.toggle_favourite_selected_contact(),
+            Action::SpeedDial(slot) => self.speed_dial(slot),
+            Action::PasteSignature => self.screens.push(PasteSignature),
+            Action::ShowNotifications => {
+                self.state.notifications.dismiss_all();
+                self.screens.push(NotificationLog::default());
+            }
+            Action::SelectNext => {
                 // If there is more contacts go down
-                if self.state.selected_contact_index < self.state.contacts.len() - 1 {
+                if self.state.selected_contact_index + 1 < self.state.contacts.len() {
                     self.state.selected_contact_index += 1;
                 }
             }
-            (_, KeyCode::Up) => {
+            Action::SelectPrevious => {
                 if self.state.selected_contact_index > 0 {
                     self.state.selected_contact_index -= 1;
                 }
             }
-            _ => match self.mode {
-                AppMode::Filtering => {
-                    self.state.filter.handle_key_events(key_event, type_mode)?;
-                    if self.state.filter.updated {
-                        self.state.selected_contact_index = 0;
-                        self.get_contacts()?
-                    }
-                }
-                AppMode::AddingContact => {
-                    match self.state.modal.handle_key_events(key_event, type_mode)? {
-                        DialogResult::Ok => {
-                            self.insert_contact();
-                            self.get_contacts()?;
-                            self.mode = AppMode::Filtering;
-                            self.state.modal.reset();
-                        }
-                        DialogResult::Cancel => self.mode = AppMode::Filtering,
-                        _ => {}
-                    }
-                }
-                AppMode::EditingContact => {
-                    match self.state.modal.handle_key_events(key_event, type_mode)? {
-                        DialogResult::Ok => {
-                            self.update_contact();
-                            self.get_contacts()?;
-                            self.mode = AppMode::Filtering;
-                            self.state.modal.reset();
-                        }
-                        DialogResult::Cancel => self.mode = AppMode::Filtering,
-                        _ => {}
-                    }
-                }
-                AppMode::DeletingContact => match key_event.code {
-                    KeyCode::Char('y') => {
-                        info!("Deleting contact");
-                        self.delete_contact();
-                        self.get_contacts()?;
-                        self.mode = AppMode::Filtering;
-                    }
-                    _ => self.mode = AppMode::Filtering,
-                },
-            },
         }
-        Ok(())
+    }
+
+    pub fn type_mode(&self) -> TypingMode {
+        self.type_mode.clone()
     }
 
     pub fn get_cursor_style(&self) -> SetCursorStyle {
@@ -247,8 +1125,15 @@ pub struct AppState {
     pub selected_contact_index: usize,
     pub filter: LineBuffer,
     pub contacts: Vec<Contact>,
+    /// Favourites and speed-dial contacts, pinned above the contact list.
+    pub favourites: Vec<Contact>,
+    pub edit_context: EditContext,
     pub config: Config,
-    pub modal: Modal,
+    pub notifications: Notifications,
+    /// Calls whose dialler process is still running.
+    pub active_calls: Vec<ActiveCall>,
+    /// The saved message templates, by name.
+    pub templates: Vec<Template>,
 }
 
 #[derive(Debug, Default)]
@@ -257,11 +1142,3 @@ pub enum AppFocus {
     Filter,
     Contacts,
 }
-
-#[derive(Debug, Clone, PartialEq)]
-pub enum AppMode {
-    Filtering,
-    AddingContact,
-    EditingContact,
-    DeletingContact,
-}
@@ -0,0 +1,261 @@
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use std::io::Write;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+/// The longest OSC 52 sequence sent, in base64 characters. Terminals drop longer ones silently
+/// (xterm's limit is about this), so longer text only goes to the clipboard command.
+const OSC52_MAX: usize = 100_000;
+
+/// How long the clipboard command gets to take the text before it's assumed to be holding on to
+/// the selection, as `xclip` does until something else is copied.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How text is copied, from the `DIALLER_OSC52` and `DIALLER_CLIPBOARD_COMMAND` environment
+/// variables.
+#[derive(Debug, Clone)]
+pub struct ClipboardConfig {
+    /// Whether to ask the terminal to copy with an OSC 52 escape sequence, which works over SSH.
+    pub osc52: bool,
+    /// A program that copies what it reads on stdin, e.g. `wl-copy` or `xclip -selection clipboard`.
+    /// It's run as well as OSC 52, since a terminal that ignores OSC 52 can't be told apart from
+    /// one that honours it.
+    pub command: Option<String>,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            osc52: true,
+            command: None,
+        }
+    }
+}
+
+/// How far a copy got straight away.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyStatus {
+    /// The terminal was sent the text, which is as much as OSC 52 can tell.
+    Sent,
+    /// The clipboard command is running, and says how it went with a [`ClipboardEvent`].
+    Started,
+}
+
+/// How the clipboard command went, for a copy of `what`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipboardEvent {
+    Copied {
+        what: String,
+    },
+    Failed {
+        what: String,
+        /// Whether the text went to the terminal with OSC 52 too, so it may have been copied anyway.
+        osc52: bool,
+        message: String,
+    },
+}
+
+/// Copies `text` with OSC 52 and, if there is one, the clipboard command. The command runs in the
+/// background so a slow one can't hold up the UI; how it went arrives on `events`.
+pub fn copy(
+    config: &ClipboardConfig,
+    what: &str,
+    text: &str,
+    events: Option<UnboundedSender<Event>>,
+) -> AppResult<CopyStatus> {
+    let osc52 = config.osc52.then(|| copy_osc52(text));
+    if let Some(Err(err)) = &osc52 {
+        warn!("Could not copy with OSC 52: {}", err.full_message());
+    }
+
+    let Some(command) = &config.command else {
+        return match osc52 {
+            Some(Ok(())) => Ok(CopyStatus::Sent),
+            Some(Err(err)) => Err(err),
+            None => Err(clipboard_error("OSC 52 is off and there's no clipboard command")),
+        };
+    };
+
+    let osc52 = matches!(osc52, Some(Ok(())));
+    let (command, what, text) = (command.clone(), what.to_string(), text.to_string());
+    tokio::spawn(async move {
+        let event = match copy_command(&command, &text).await {
+            Ok(()) => ClipboardEvent::Copied { what },
+            Err(Error::Clipboard(message)) => ClipboardEvent::Failed { what, osc52, message },
+            Err(err) => ClipboardEvent::Failed {
+                what,
+                osc52,
+                message: err.full_message(),
+            },
+        };
+        if let Some(events) = events {
+            if events.send(Event::Clipboard(event)).is_err() {
+                warn!("Clipboard event arrived after the app closed");
+            }
+        }
+    });
+    Ok(CopyStatus::Started)
+}
+
+/// Writes the OSC 52 sequence to the terminal, which sets the system clipboard if it allows it.
+/// Terminals don't say whether they did, so this only fails if the sequence can't be sent.
+fn copy_osc52(text: &str) -> AppResult<()> {
+    let encoded = base64(text.as_bytes());
+    if encoded.len() > OSC52_MAX {
+        return Err(clipboard_error(format!(
+            "{} bytes is too long to copy through the terminal",
+            text.len()
+        )));
+    }
+
+    // ratatui draws to stderr, so that's where the terminal is.
+    let mut terminal = std::io::stderr().lock();
+    terminal
+        .write_all(format!("\x1b]52;c;{encoded}\x07").as_bytes())
+        .and_then(|_| terminal.flush())
+        .map_err(|err| clipboard_error(format!("could not write to the terminal: {err}")))?;
+    info!("Copied {} bytes with OSC 52", text.len());
+    Ok(())
+}
+
+/// Runs the clipboard command with `text` on its stdin. Its arguments are split on whitespace.
+async fn copy_command(command: &str, text: &str) -> AppResult<()> {
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return Err(clipboard_error("the clipboard command is empty"));
+    };
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| clipboard_error(format!("could not start \"{program}\": {err}")))?;
+
+    // A program that gave up without reading it all says why in its exit code, so that comes first.
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()).await,
+        None => Ok(()),
+    };
+    let written = written.map_err(|err| clipboard_error(format!("could not write to \"{program}\": {err}")));
+
+    let Ok(status) = timeout(COMMAND_TIMEOUT, child.wait()).await else {
+        written?;
+        info!("\"{program}\" is still running; assuming it's holding the selection");
+        tokio::spawn(async move { child.wait().await });
+        return Ok(());
+    };
+    match status?.code() {
+        Some(0) => {
+            written?;
+            info!("Copied {} bytes with \"{program}\"", text.len());
+            Ok(())
+        }
+        Some(code) => Err(clipboard_error(format!("\"{program}\" exited with code {code}"))),
+        None => Err(clipboard_error(format!("\"{program}\" was killed"))),
+    }
+}
+
+fn clipboard_error(message: impl Into<String>) -> Error {
+    Error::Clipboard(message.into())
+}
+
+/// Standard base64 with padding, as OSC 52 expects.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |triple, (index, &byte)| triple | (byte as u32) << (16 - 8 * index));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn base64_pads_to_a_multiple_of_four() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64("Zoë +/?".as_bytes()), "Wm/DqyArLz8=");
+    }
+
+    /// Copies with only the clipboard `command`, returning how it went.
+    async fn copy_with(command: &str) -> ClipboardEvent {
+        let config = ClipboardConfig {
+            osc52: false,
+            command: Some(command.to_string()),
+        };
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        assert_eq!(copy(&config, "the number", "0299991234", Some(sender)).unwrap(), CopyStatus::Started);
+        match receiver.recv().await {
+            Some(Event::Clipboard(event)) => event,
+            event => panic!("expected how the copy went, got {event:?}"),
+        }
+    }
+
+    fn failed(message: &str) -> ClipboardEvent {
+        ClipboardEvent::Failed {
+            what: "the number".to_string(),
+            osc52: false,
+            message: message.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn the_command_reports_when_it_has_copied() {
+        let copied = ClipboardEvent::Copied {
+            what: "the number".to_string(),
+        };
+        assert_eq!(copy_with("cat").await, copied);
+    }
+
+    #[tokio::test]
+    async fn a_failing_command_says_why() {
+        assert_eq!(copy_with("false").await, failed("\"false\" exited with code 1"));
+        let missing = copy_with("dialler-rs-no-such-program").await;
+        assert!(
+            matches!(&missing, ClipboardEvent::Failed { message, .. } if message.starts_with("could not start")),
+            "{missing:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn a_command_still_running_is_holding_the_selection() {
+        tokio::time::pause();
+        let copied = ClipboardEvent::Copied {
+            what: "the number".to_string(),
+        };
+        assert_eq!(copy_with("sleep 30").await, copied);
+    }
+
+    #[test]
+    fn without_osc52_or_a_command_nothing_can_copy() {
+        let config = ClipboardConfig {
+            osc52: false,
+            command: None,
+        };
+        assert!(matches!(copy(&config, "the number", "0299991234", None), Err(Error::Clipboard(_))));
+    }
+}
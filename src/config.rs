@@ -1,27 +1,135 @@
+use crate::clipboard::ClipboardConfig;
+use crate::data_layer::migrations;
+use crate::dialler::process::ProcessConfig;
+use crate::dialler::profile::{Backend, DiallerProfile, Profiles};
 use crate::error::{AppResult, Error};
+use crate::lookup::service::LookupConfig;
+use crate::sms::GatewayConfig;
 use directories::ProjectDirs;
 use rusqlite::Connection;
 use std::path::PathBuf;
 
 #[derive(Debug, Default)]
 pub struct Config {
-    pub dialler_program: String
+    pub dialler_program: String,
+    pub word_delimiters: String,
+    /// The program emails are opened in, given a `mailto:` URI.
+    pub mail_program: String,
+    pub clipboard: ClipboardConfig,
+    /// The dialler profiles from `profiles.toml`, or a single `default` one running
+    /// `dialler_program` if there is no such file.
+    pub profiles: Vec<DiallerProfile>,
+    /// The name of the profile new calls go through.
+    pub profile: String,
+    pub lookup: LookupConfig,
+    /// How texts are sent, from `sms.toml`, if they can be.
+    pub sms: Option<GatewayConfig>,
 }
 
 impl Config {
+    pub const DEFAULT_WORD_DELIMITERS: &'static str = "-_.,;:/\\@()[]+#*";
+
     pub fn new() -> AppResult<Self> {
         let dialler_program = std::env::var("DIALLER_PROGRAM").unwrap_or_else(|_| "dialler".to_string());
-        Ok(Self { dialler_program })
+        let word_delimiters = std::env::var("DIALLER_WORD_DELIMITERS")
+            .unwrap_or_else(|_| Self::DEFAULT_WORD_DELIMITERS.to_string());
+        let mail_program = std::env::var("DIALLER_MAIL_PROGRAM").unwrap_or_else(|_| "xdg-open".to_string());
+        let Profiles { mut profiles, default } = Profiles::load(&Self::get_config_file("profiles.toml")?)?;
+        if profiles.is_empty() {
+            profiles.push(DiallerProfile {
+                name: "default".to_string(),
+                backend: Backend::Process(ProcessConfig {
+                    program: dialler_program.clone(),
+                }),
+                uri: None,
+            });
+        }
+
+        let profile = std::env::var("DIALLER_PROFILE")
+            .ok()
+            .or(default)
+            .unwrap_or_else(|| profiles[0].name.clone());
+        if !profiles.iter().any(|p| p.name == profile) {
+            return Err(Error::ConfigError(format!("there is no dialler profile called \"{profile}\"")));
+        }
+
+        Ok(Self {
+            dialler_program,
+            word_delimiters,
+            mail_program,
+            profiles,
+            profile,
+            lookup: Self::lookup_config()?,
+            clipboard: Self::clipboard_config(),
+            sms: GatewayConfig::load(&Self::get_config_file("sms.toml")?)?,
+        })
+    }
+
+    /// The lookup service answers on a socket in the runtime directory unless
+    /// `DIALLER_LOOKUP_SOCKET` says otherwise, and over HTTP only if `DIALLER_LOOKUP_PORT` is set.
+    /// Either can be `off`. HTTP clients must send `DIALLER_LOOKUP_TOKEN` if it's set.
+    fn lookup_config() -> AppResult<LookupConfig> {
+        let setting = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let off = |value: &String| value.eq_ignore_ascii_case("off");
+
+        let socket = match setting("DIALLER_LOOKUP_SOCKET") {
+            Some(path) if off(&path) => None,
+            Some(path) => Some(PathBuf::from(path)),
+            None => Some(Self::get_runtime_file("lookup.sock")?),
+        };
+        let http_port = match setting("DIALLER_LOOKUP_PORT") {
+            Some(port) if off(&port) => None,
+            Some(port) => Some(port.parse().map_err(|_| {
+                Error::ConfigError(format!("DIALLER_LOOKUP_PORT must be a port number, not \"{port}\""))
+            })?),
+            None => None,
+        };
+        let http_token = setting("DIALLER_LOOKUP_TOKEN");
+        let popup = setting("DIALLER_LOOKUP_POPUP")
+            .is_some_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"));
+
+        Ok(LookupConfig {
+            socket,
+            http_port,
+            http_token,
+            popup,
+        })
+    }
+
+    /// Text is copied with OSC 52 unless `DIALLER_OSC52` is off, and with
+    /// `DIALLER_CLIPBOARD_COMMAND` too if it's set.
+    fn clipboard_config() -> ClipboardConfig {
+        let setting = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+        let osc52 = setting("DIALLER_OSC52")
+            .is_none_or(|value| !matches!(value.to_ascii_lowercase().as_str(), "0" | "false" | "no" | "off"));
+        ClipboardConfig {
+            osc52,
+            command: setting("DIALLER_CLIPBOARD_COMMAND"),
+        }
+    }
+
+    pub fn get_profile(&self, name: &str) -> Option<&DiallerProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn active_profile(&self) -> Option<&DiallerProfile> {
+        self.get_profile(&self.profile)
+    }
+
+    fn project_dirs() -> AppResult<ProjectDirs> {
+        ProjectDirs::from("com", "Shane Poppleton", "Dialler").ok_or(Error::ConfigError(
+            "No valid home directory path could be retrieved from the operating system.".to_string(),
+        ))
     }
 
     fn get_config_file(file: &str) -> AppResult<PathBuf> {
-        Ok(ProjectDirs::from("com", "Shane Poppleton", "Dialler")
-            .ok_or(Error::ConfigError(
-                "No valid home directory path could be retrieved from the operating system."
-                    .to_string(),
-            ))?
-            .config_dir()
-            .join(file))
+        Ok(Self::project_dirs()?.config_dir().join(file))
+    }
+
+    /// A file for sockets and the like, in the config folder if the system has no runtime folder.
+    fn get_runtime_file(file: &str) -> AppResult<PathBuf> {
+        let dirs = Self::project_dirs()?;
+        Ok(dirs.runtime_dir().unwrap_or(dirs.config_dir()).join(file))
     }
 
     pub fn create_db() -> AppResult<Connection> {
@@ -33,19 +141,13 @@ impl Config {
         std::fs::create_dir_all(parent).map_err(|_| {
             Error::ConfigError("Unable to create parent folders of config folder".to_string())
         })?;
-        let conn = Connection::open(db_file)?;
+        let mut conn = Connection::open(db_file)?;
+        // So call history keeps its rows, unlinked, when a contact is deleted.
+        conn.pragma_update(None, "foreign_keys", true)?;
 
         // Check if table exists
-        conn.execute(
-            "create table if not exists contacts (
-                id INTEGER PRIMARY KEY,
-                first_name TEXT,
-                last_name TEXT,
-                phone_number TEXT NOT NULL UNIQUE,
-                company_name TEXT
-            )",
-            [],
-        )?;
+        conn.execute(migrations::BASELINE, [])?;
+        migrations::run(&mut conn)?;
 
         Ok(conn)
     }
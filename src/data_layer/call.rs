@@ -0,0 +1,117 @@
+use crate::data_layer::contact::Contact;
+use chrono::{DateTime, Local, TimeZone};
+
+/// A number dialled from the app, whether or not it belongs to a contact.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub id: u64,
+    pub phone_number: String,
+    pub contact_id: Option<u64>,
+    /// Who was called, as shown at the time.
+    pub name: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub dialled_at: i64,
+    pub status: CallStatus,
+    /// The dialler's exit code, once it has exited.
+    pub exit_code: Option<i32>,
+    /// Why the call failed, e.g. the dialler's stderr.
+    pub error: Option<String>,
+    /// The dialler profile the call went through.
+    pub profile: Option<String>,
+    /// The tones sent after the number, kept apart from it.
+    pub dtmf: Option<String>,
+}
+
+/// Where a call got to, as far as the dialler process tells us.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallStatus {
+    /// The dialler is still running.
+    Active,
+    /// The dialler exited successfully.
+    Ended,
+    /// The dialler couldn't be started or exited with an error.
+    Failed,
+}
+
+impl CallStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CallStatus::Active => "active",
+            CallStatus::Ended => "ended",
+            CallStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "active" => CallStatus::Active,
+            "failed" => CallStatus::Failed,
+            _ => CallStatus::Ended,
+        }
+    }
+}
+
+impl Call {
+    pub fn dialled_at(&self) -> DateTime<Local> {
+        Local
+            .timestamp_opt(self.dialled_at, 0)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+
+    /// The number with any tones sent after it, e.g. `02 9999 1234,,2`.
+    pub fn dialled(&self) -> String {
+        format!("{}{}", self.phone_number, self.dtmf.as_deref().unwrap_or_default())
+    }
+
+    /// The name, falling back to the number for ad-hoc calls.
+    pub fn display_name(&self) -> &str {
+        self.name
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&self.phone_number)
+    }
+}
+
+pub struct CallForInsert {
+    pub phone_number: String,
+    pub contact_id: Option<u64>,
+    pub name: Option<String>,
+    /// The dialler profile to use, or the active one if `None`.
+    pub profile: Option<String>,
+    pub dtmf: Option<String>,
+}
+
+impl CallForInsert {
+    pub fn for_contact(contact: &Contact) -> Self {
+        Self {
+            phone_number: contact.phone_number.clone(),
+            contact_id: Some(contact.id),
+            name: Some(contact.display_name()),
+            profile: None,
+            dtmf: contact.dtmf.clone(),
+        }
+    }
+
+    /// Another call to the same number and contact as `call`, through the same profile.
+    pub fn again(call: &Call) -> Self {
+        Self {
+            phone_number: call.phone_number.clone(),
+            contact_id: call.contact_id,
+            name: call.name.clone(),
+            profile: call.profile.clone(),
+            dtmf: call.dtmf.clone(),
+        }
+    }
+
+    /// A call to a number that isn't saved as a contact.
+    pub fn for_number(phone_number: impl Into<String>) -> Self {
+        Self {
+            phone_number: phone_number.into(),
+            contact_id: None,
+            name: None,
+            profile: None,
+            dtmf: None,
+        }
+    }
+}
@@ -1,10 +1,51 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Contact {
     pub id: u64,
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub company_name: Option<String>,
     pub phone_number: String,
+    pub email: Option<String>,
+    pub favourite: bool,
+    /// The 1-9 slot the contact can be dialled from with Alt+digit.
+    pub speed_dial: Option<u8>,
+    /// Tones sent once the call connects, e.g. `,,2,345`. See [`crate::dialler::dtmf`].
+    pub dtmf: Option<String>,
+}
+
+impl Contact {
+    /// The contact's name, falling back to the company or phone number.
+    pub fn display_name(&self) -> String {
+        let name = [self.first_name.as_deref(), self.last_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if !name.is_empty() {
+            name
+        } else if let Some(company) = self.company_name.as_deref().filter(|c| !c.is_empty()) {
+            company.to_string()
+        } else {
+            self.phone_number.clone()
+        }
+    }
+
+    /// The name, company and number on one line for pasting elsewhere, e.g.
+    /// `Jane Smith (Acme): (02) 9999-1234`.
+    pub fn name_and_number(&self) -> String {
+        let name = self.display_name();
+        if name == self.phone_number {
+            return name;
+        }
+        match self.company_name.as_deref().map(str::trim) {
+            Some(company) if !company.is_empty() && company != name => {
+                format!("{name} ({company}): {}", self.phone_number)
+            }
+            _ => format!("{name}: {}", self.phone_number),
+        }
+    }
 }
 
 impl std::fmt::Display for Contact {
@@ -31,4 +72,8 @@ pub struct ContactForUpdate {
     pub last_name: Option<String>,
     pub company_name: Option<String>,
     pub phone_number: String,
+    pub email: Option<String>,
+    pub favourite: bool,
+    pub speed_dial: Option<u8>,
+    pub dtmf: Option<String>,
 }
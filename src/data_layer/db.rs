@@ -1,9 +1,46 @@
+use crate::data_layer::call::{Call, CallForInsert, CallStatus};
 use crate::data_layer::contact::{Contact, ContactForUpdate};
-use crate::error::AppResult;
-use rusqlite::{params, Connection};
+use crate::data_layer::message::{MessageForInsert, MessageStatus};
+use crate::data_layer::template::{Template, TemplateForUpdate};
+use crate::error::{AppResult, Error};
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension, Row};
 use std::sync::{Arc, Mutex};
 use tracing::info;
 
+const CONTACT_COLUMNS: &str = "id, first_name, last_name, phone_number, company_name, email, favourite, speed_dial, dtmf";
+
+fn contact_from_row(row: &Row) -> rusqlite::Result<Contact> {
+    Ok(Contact {
+        id: row.get(0)?,
+        first_name: row.get(1)?,
+        last_name: row.get(2)?,
+        phone_number: row.get(3)?,
+        company_name: row.get(4)?,
+        email: row.get(5)?,
+        favourite: row.get(6)?,
+        speed_dial: row.get(7)?,
+        dtmf: row.get(8)?,
+    })
+}
+
+const CALL_COLUMNS: &str = "id, phone_number, contact_id, name, dialled_at, status, exit_code, error, profile, dtmf";
+
+fn call_from_row(row: &Row) -> rusqlite::Result<Call> {
+    Ok(Call {
+        id: row.get(0)?,
+        phone_number: row.get(1)?,
+        contact_id: row.get(2)?,
+        name: row.get(3)?,
+        dialled_at: row.get(4)?,
+        status: CallStatus::parse(&row.get::<_, String>(5)?),
+        exit_code: row.get(6)?,
+        error: row.get(7)?,
+        profile: row.get(8)?,
+        dtmf: row.get(9)?,
+    })
+}
+
 pub struct Db {
     conn: Arc<Mutex<Option<Connection>>>,
 }
@@ -16,18 +53,21 @@ impl Db {
     pub fn insert(&self, contact: ContactForUpdate) -> AppResult<usize> {
         let mut guard = self.conn.lock().unwrap();
         if let Some(ref mut conn) = *guard {
-            let sql = "INSERT INTO contacts (first_name, last_name, phone_number, company_name) VALUES (?, ?, ?, ?)";
+            let sql = "INSERT INTO contacts (first_name, last_name, phone_number, company_name, email, favourite, speed_dial, dtmf) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
             let params = params![
                     contact.first_name,
                     contact.last_name,
                     contact.phone_number,
-                    contact.company_name
+                    contact.company_name,
+                    contact.email,
+                    contact.favourite,
+                    contact.speed_dial,
+                    contact.dtmf
             ];
 
-            let affected_rows = conn.execute(
-                sql,
-                params,
-            )?;
+            let affected_rows = conn
+                .execute(sql, params)
+                .map_err(|err| Error::from_contact_write(err, &contact.phone_number))?;
 
             return Ok(affected_rows);
         }
@@ -38,19 +78,22 @@ impl Db {
     pub fn update(&self, id: u64, contact: ContactForUpdate) -> AppResult<usize> {
         let mut guard = self.conn.lock().unwrap();
         if let Some(ref mut conn) = *guard {
-            let sql = "UPDATE contacts SET first_name = ?, last_name = ?, phone_number = ?, company_name = ? WHERE id = ?";
+            let sql = "UPDATE contacts SET first_name = ?, last_name = ?, phone_number = ?, company_name = ?, email = ?, favourite = ?, speed_dial = ?, dtmf = ? WHERE id = ?";
             let params = params![
                 contact.first_name,
                 contact.last_name,
                 contact.phone_number,
                 contact.company_name,
+                contact.email,
+                contact.favourite,
+                contact.speed_dial,
+                contact.dtmf,
                 id
             ];
 
-            let affected_rows = conn.execute(
-                sql,
-                params,
-            )?;
+            let affected_rows = conn
+                .execute(sql, params)
+                .map_err(|err| Error::from_contact_write(err, &contact.phone_number))?;
 
             return Ok(affected_rows);
         }
@@ -68,32 +111,307 @@ impl Db {
         Ok(0)
     }
     pub fn get() {}
+
+    pub fn set_favourite(&self, id: u64, favourite: bool) -> AppResult<usize> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "UPDATE contacts SET favourite = ? WHERE id = ?";
+            let affected_rows = conn.execute(sql, params![favourite, id])?;
+            return Ok(affected_rows);
+        }
+
+        Ok(0)
+    }
+
+    /// Whether another contact already has this speed-dial slot.
+    pub fn speed_dial_exists(&self, slot: u8, excluding: Option<u64>) -> AppResult<bool> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "SELECT COUNT(*) FROM contacts WHERE speed_dial = ?1 AND id IS NOT ?2";
+            let count: u64 = conn.query_row(sql, params![slot, excluding], |row| row.get(0))?;
+            return Ok(count > 0);
+        }
+
+        Ok(false)
+    }
+
+    pub fn find_by_speed_dial(&self, slot: u8) -> AppResult<Option<Contact>> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = format!("SELECT {CONTACT_COLUMNS} FROM contacts WHERE speed_dial = ?");
+            let contact = conn
+                .query_row(&sql, params![slot], contact_from_row)
+                .optional()?;
+            return Ok(contact);
+        }
+
+        Ok(None)
+    }
+
+    /// Contacts whose phone number contains `digits`, ignoring spaces, for reverse lookup.
+    pub fn find_by_number(&self, digits: &str, limit: usize) -> AppResult<Vec<Contact>> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = format!(
+                "SELECT {CONTACT_COLUMNS} FROM contacts
+                WHERE REPLACE(phone_number, ' ', '') LIKE '%' || REPLACE(?1, ' ', '') || '%'
+                ORDER BY LENGTH(REPLACE(phone_number, ' ', '')) = LENGTH(REPLACE(?1, ' ', '')) DESC, first_name, last_name
+                LIMIT ?2"
+            );
+            let mut sql = conn.prepare(&sql)?;
+            let contacts = sql
+                .query_map(params![digits.trim(), limit], contact_from_row)?
+                .filter_map(Result::ok)
+                .collect();
+            return Ok(contacts);
+        }
+
+        Ok(vec![])
+    }
+
+    /// Every contact whose phone number contains `digits` once its separators and `+` are dropped,
+    /// for caller ID to rank.
+    pub fn find_by_digits(&self, digits: &str) -> AppResult<Vec<Contact>> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = format!(
+                "SELECT {CONTACT_COLUMNS} FROM contacts
+                WHERE REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(phone_number, ' ', ''), '-', ''), '.', ''), '(', ''), ')', ''), '+', '')
+                    LIKE '%' || ?1 || '%'
+                ORDER BY first_name, last_name"
+            );
+            let mut sql = conn.prepare(&sql)?;
+            let contacts = sql
+                .query_map(params![digits], contact_from_row)?
+                .filter_map(Result::ok)
+                .collect();
+            return Ok(contacts);
+        }
+
+        Ok(vec![])
+    }
+
+    /// Adds a call to the history, returning its id.
+    pub fn record_call(&self, call: CallForInsert, status: CallStatus, error: Option<String>) -> AppResult<u64> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "INSERT INTO calls (phone_number, contact_id, name, dialled_at, status, error, profile, dtmf) VALUES (?, ?, ?, ?, ?, ?, ?, ?)";
+            let params = params![
+                call.phone_number,
+                call.contact_id,
+                call.name,
+                Local::now().timestamp(),
+                status.as_str(),
+                error,
+                call.profile,
+                call.dtmf
+            ];
+            conn.execute(sql, params)?;
+            return Ok(conn.last_insert_rowid() as u64);
+        }
+
+        Ok(0)
+    }
+
+    /// Records how a call in the history ended.
+    pub fn finish_call(&self, id: u64, status: CallStatus, exit_code: Option<i32>, error: Option<String>) -> AppResult<usize> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "UPDATE calls SET status = ?, exit_code = ?, error = ?, ended_at = ? WHERE id = ?";
+            let params = params![status.as_str(), exit_code, error, Local::now().timestamp(), id];
+            let affected_rows = conn.execute(sql, params)?;
+            return Ok(affected_rows);
+        }
+
+        Ok(0)
+    }
+
+    /// Adds a text to the message log, returning its id.
+    pub fn record_message(&self, message: MessageForInsert, gateway: &str) -> AppResult<u64> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "INSERT INTO messages (phone_number, contact_id, name, body, gateway, sent_at, status) VALUES (?, ?, ?, ?, ?, ?, ?)";
+            let params = params![
+                message.phone_number,
+                message.contact_id,
+                message.name,
+                message.body,
+                gateway,
+                Local::now().timestamp(),
+                MessageStatus::Sending.as_str()
+            ];
+            conn.execute(sql, params)?;
+            return Ok(conn.last_insert_rowid() as u64);
+        }
+
+        Ok(0)
+    }
+
+    pub fn finish_message(&self, id: u64, status: MessageStatus, error: Option<String>) -> AppResult<usize> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "UPDATE messages SET status = ?, error = ? WHERE id = ?";
+            let affected_rows = conn.execute(sql, params![status.as_str(), error, id])?;
+            return Ok(affected_rows);
+        }
+
+        Ok(0)
+    }
+
+    /// Every template, by name.
+    pub fn templates(&self) -> AppResult<Vec<Template>> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let mut sql = conn.prepare("SELECT id, name, body FROM templates ORDER BY name COLLATE NOCASE")?;
+            let templates = sql
+                .query_map([], |row| {
+                    Ok(Template {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        body: row.get(2)?,
+                    })
+                })?
+                .filter_map(Result::ok)
+                .collect();
+            return Ok(templates);
+        }
+
+        Ok(vec![])
+    }
+
+    pub fn insert_template(&self, template: TemplateForUpdate) -> AppResult<usize> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "INSERT INTO templates (name, body) VALUES (?, ?)";
+            let affected_rows = conn.execute(sql, params![template.name, template.body])?;
+            return Ok(affected_rows);
+        }
+
+        Ok(0)
+    }
+
+    pub fn update_template(&self, id: u64, template: TemplateForUpdate) -> AppResult<usize> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "UPDATE templates SET name = ?, body = ? WHERE id = ?";
+            let affected_rows = conn.execute(sql, params![template.name, template.body, id])?;
+            return Ok(affected_rows);
+        }
+
+        Ok(0)
+    }
+
+    pub fn delete_template(&self, id: u64) -> AppResult<usize> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let affected_rows = conn.execute("DELETE FROM templates WHERE id = ?", params![id])?;
+            return Ok(affected_rows);
+        }
+
+        Ok(0)
+    }
+
+    /// Whether another template already has this name.
+    pub fn template_name_exists(&self, name: &str, excluding: Option<u64>) -> AppResult<bool> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "SELECT COUNT(*) FROM templates WHERE name = ?1 AND id IS NOT ?2";
+            let count: u64 = conn.query_row(sql, params![name.trim(), excluding], |row| row.get(0))?;
+            return Ok(count > 0);
+        }
+
+        Ok(false)
+    }
+
+    /// The most recent calls, newest first.
+    pub fn recent_calls(&self, limit: usize) -> AppResult<Vec<Call>> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = format!("SELECT {CALL_COLUMNS} FROM calls ORDER BY dialled_at DESC, id DESC LIMIT ?");
+            let mut sql = conn.prepare(&sql)?;
+            let calls = sql
+                .query_map(params![limit], call_from_row)?
+                .filter_map(Result::ok)
+                .collect();
+            return Ok(calls);
+        }
+
+        Ok(vec![])
+    }
+
+    /// The most recently dialled distinct numbers, each with its latest call, newest first.
+    pub fn recent_numbers(&self, limit: usize) -> AppResult<Vec<Call>> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = format!(
+                "
+                SELECT {CALL_COLUMNS} FROM calls
+                WHERE id IN (SELECT MAX(id) FROM calls GROUP BY REPLACE(phone_number, ' ', ''), IFNULL(dtmf, ''))
+                ORDER BY dialled_at DESC, id DESC
+                LIMIT ?"
+            );
+            let mut sql = conn.prepare(&sql)?;
+            let calls = sql
+                .query_map(params![limit], call_from_row)?
+                .filter_map(Result::ok)
+                .collect();
+            return Ok(calls);
+        }
+
+        Ok(vec![])
+    }
+
+    /// Favourites and contacts with a speed-dial slot, in slot order then by name.
+    pub fn favourites(&self) -> AppResult<Vec<Contact>> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = format!(
+                "SELECT {CONTACT_COLUMNS} FROM contacts
+                WHERE favourite = 1 OR speed_dial IS NOT NULL
+                ORDER BY speed_dial IS NULL, speed_dial, first_name, last_name"
+            );
+            let mut sql = conn.prepare(&sql)?;
+            let contacts = sql
+                .query_map([], contact_from_row)?
+                .filter_map(Result::ok)
+                .collect();
+            return Ok(contacts);
+        }
+
+        Ok(vec![])
+    }
+
+    /// Whether another contact already has this phone number, ignoring spaces.
+    pub fn phone_number_exists(&self, phone_number: &str, excluding: Option<u64>) -> AppResult<bool> {
+        let mut guard = self.conn.lock().unwrap();
+        if let Some(ref mut conn) = *guard {
+            let sql = "SELECT COUNT(*) FROM contacts WHERE REPLACE(phone_number, ' ', '') = REPLACE(?1, ' ', '') AND id IS NOT ?2";
+            let count: u64 = conn.query_row(sql, params![phone_number.trim(), excluding], |row| row.get(0))?;
+            return Ok(count > 0);
+        }
+
+        Ok(false)
+    }
     pub fn list(&self, filter: impl Into<String>) -> AppResult<Vec<Contact>> {
         info!("Listing contacts");
         let mut guard = self.conn.lock().unwrap();
 
         if let Some(ref mut conn) = *guard {
-            let mut sql = conn.prepare(
+            let sql = format!(
                 "
-                SELECT * FROM contacts
+                SELECT {CONTACT_COLUMNS} FROM contacts
                 WHERE CAST(id AS TEXT) LIKE '%' || ?1 || '%'
                 OR first_name LIKE '%' || ?1 || '%'
                 OR last_name LIKE '%' || ?1 || '%' 
                 OR company_name LIKE '%' || ?1 || '%' 
                 OR phone_number LIKE '%' || ?1 || '%'
-            ",
-            )?;
+                OR email LIKE '%' || ?1 || '%'
+            "
+            );
+            let mut sql = conn.prepare(&sql)?;
 
             let contact_iter = sql
-                .query_map(params![filter.into()], |row| {
-                    Ok(Contact {
-                        id: row.get(0)?,
-                        first_name: row.get(1)?,
-                        last_name: row.get(2)?,
-                        phone_number: row.get(3)?,
-                        company_name: row.get(4)?,
-                    })
-                })?
+                .query_map(params![filter.into()], contact_from_row)?
                 .filter_map(Result::ok)
                 .collect();
 
@@ -0,0 +1,44 @@
+use crate::data_layer::contact::Contact;
+
+/// Where a text got to, as far as the gateway tells us.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageStatus {
+    /// Handed to the gateway, which hasn't answered yet.
+    Sending,
+    Sent,
+    Failed,
+}
+
+impl MessageStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageStatus::Sending => "sending",
+            MessageStatus::Sent => "sent",
+            MessageStatus::Failed => "failed",
+        }
+    }
+}
+
+/// A text for the message log, which keeps it against the contact it went to.
+pub struct MessageForInsert {
+    pub phone_number: String,
+    pub contact_id: Option<u64>,
+    /// Who it went to, as shown at the time.
+    pub name: Option<String>,
+    /// The message as sent, with its placeholders filled in.
+    pub body: String,
+}
+
+impl MessageForInsert {
+    /// A message to `phone_number`, logged against `contact` if that's the contact's number.
+    pub fn new(phone_number: impl Into<String>, contact: Option<&Contact>, body: impl Into<String>) -> Self {
+        let phone_number = phone_number.into();
+        let contact = contact.filter(|contact| contact.phone_number == phone_number);
+        Self {
+            phone_number,
+            contact_id: contact.map(|contact| contact.id),
+            name: contact.map(Contact::display_name),
+            body: body.into(),
+        }
+    }
+}
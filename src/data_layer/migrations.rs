@@ -0,0 +1,149 @@
+use crate::error::AppResult;
+use rusqlite::Connection;
+use tracing::info;
+
+/// The original `contacts` table, from before migrations were tracked.
+pub const BASELINE: &str = "create table if not exists contacts (
+    id INTEGER PRIMARY KEY,
+    first_name TEXT,
+    last_name TEXT,
+    phone_number TEXT NOT NULL UNIQUE,
+    company_name TEXT
+)";
+
+/// Schema changes applied in order on top of the original `contacts` table.
+/// The index of the last applied step is tracked in `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    "ALTER TABLE contacts ADD COLUMN email TEXT",
+    "ALTER TABLE contacts ADD COLUMN favourite INTEGER NOT NULL DEFAULT 0;
+     ALTER TABLE contacts ADD COLUMN speed_dial INTEGER CHECK (speed_dial BETWEEN 1 AND 9);
+     CREATE UNIQUE INDEX contacts_speed_dial ON contacts (speed_dial);",
+    "CREATE TABLE calls (
+        id INTEGER PRIMARY KEY,
+        phone_number TEXT NOT NULL,
+        contact_id INTEGER REFERENCES contacts (id) ON DELETE SET NULL,
+        name TEXT,
+        dialled_at INTEGER NOT NULL
+     );
+     CREATE INDEX calls_dialled_at ON calls (dialled_at);",
+    "ALTER TABLE calls ADD COLUMN status TEXT NOT NULL DEFAULT 'ended';
+     ALTER TABLE calls ADD COLUMN exit_code INTEGER;
+     ALTER TABLE calls ADD COLUMN error TEXT;
+     ALTER TABLE calls ADD COLUMN ended_at INTEGER;",
+    "ALTER TABLE calls ADD COLUMN profile TEXT",
+    "ALTER TABLE contacts ADD COLUMN dtmf TEXT;
+     ALTER TABLE calls ADD COLUMN dtmf TEXT;",
+    "CREATE TABLE messages (
+        id INTEGER PRIMARY KEY,
+        phone_number TEXT NOT NULL,
+        contact_id INTEGER REFERENCES contacts (id) ON DELETE SET NULL,
+        name TEXT,
+        body TEXT NOT NULL,
+        gateway TEXT NOT NULL,
+        sent_at INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        error TEXT
+     );
+     CREATE INDEX messages_contact_id ON messages (contact_id);",
+    "CREATE TABLE templates (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE,
+        body TEXT NOT NULL
+     );",
+];
+
+/// Applies the steps not yet recorded in `user_version`. Each step commits together with its
+/// version bump, so a failed step is rolled back and retried on the next start.
+pub fn run(conn: &mut Connection) -> AppResult<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("Applying migration {}", index + 1);
+        let transaction = conn.transaction()?;
+        transaction.execute_batch(sql)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(conn: &Connection) -> usize {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut statement = conn.prepare(&format!("PRAGMA table_info({table})")).unwrap();
+        let names = statement.query_map([], |row| row.get(1)).unwrap();
+        names.collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn a_fresh_database_is_migrated_to_the_latest_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(BASELINE, []).unwrap();
+        run(&mut conn).unwrap();
+
+        assert_eq!(version(&conn), MIGRATIONS.len());
+        for table in ["calls", "messages", "templates"] {
+            assert!(!columns(&conn, table).is_empty(), "{table} is missing");
+        }
+
+        // Running again is a no-op.
+        run(&mut conn).unwrap();
+        assert_eq!(version(&conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn an_old_database_keeps_its_contacts() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(BASELINE, []).unwrap();
+        conn.execute(
+            "INSERT INTO contacts (first_name, last_name, phone_number, company_name)
+             VALUES ('Jane', 'Smith', '+61 400 000 000', 'Acme')",
+            [],
+        )
+        .unwrap();
+        run(&mut conn).unwrap();
+
+        let contact: (String, Option<String>, bool, Option<u8>) = conn
+            .query_row(
+                "SELECT phone_number, email, favourite, speed_dial FROM contacts",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(contact, ("+61 400 000 000".to_string(), None, false, None));
+        assert!(columns(&conn, "contacts").contains(&"dtmf".to_string()));
+    }
+
+    #[test]
+    fn a_partly_migrated_database_continues_from_its_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(BASELINE, []).unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        run(&mut conn).unwrap();
+
+        assert_eq!(version(&conn), MIGRATIONS.len());
+        assert!(columns(&conn, "contacts").contains(&"favourite".to_string()));
+    }
+
+    #[test]
+    fn a_failed_step_is_rolled_back() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(BASELINE, []).unwrap();
+        // Migration 3 creates `calls`, so make it fail part way through.
+        conn.execute_batch(&MIGRATIONS[..2].join(";")).unwrap();
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        conn.execute("CREATE INDEX calls_dialled_at ON contacts (id)", []).unwrap();
+
+        assert!(run(&mut conn).is_err());
+        assert_eq!(version(&conn), 2);
+        assert!(columns(&conn, "calls").is_empty());
+    }
+}
@@ -1,2 +1,6 @@
+pub mod call;
 pub mod db;
-pub mod contact;
\ No newline at end of file
+pub mod contact;
+pub mod message;
+pub mod migrations;
+pub mod template;
@@ -0,0 +1,22 @@
+use crate::data_layer::contact::Contact;
+use crate::template;
+
+/// Saved wording for texts, emails and the clipboard, with placeholders filled in per contact.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub id: u64,
+    pub name: String,
+    pub body: String,
+}
+
+impl Template {
+    /// The body with its placeholders filled in from `contact`.
+    pub fn render(&self, contact: Option<&Contact>) -> String {
+        template::render(&self.body, contact)
+    }
+}
+
+pub struct TemplateForUpdate {
+    pub name: String,
+    pub body: String,
+}
@@ -0,0 +1,612 @@
+use crate::dialler::dtmf::{self, ToneSender};
+use crate::dialler::{send, CallCommand, CallControl, CallRequest, CallState, Dialler, DiallerEvent};
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::time::{timeout, timeout_at, Instant};
+use tracing::{debug, info, warn};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much longer than the profile's `timeout` Asterisk gets to report how the `Originate` went,
+/// since it only gives up on our phone once that has passed.
+const ORIGINATE_MARGIN: Duration = Duration::from_secs(5);
+
+/// How long each post-dial tone plays for, in milliseconds.
+const TONE_LENGTH: u32 = 250;
+
+fn default_port() -> u16 {
+    5038
+}
+
+fn default_priority() -> u32 {
+    1
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+/// Settings for an Asterisk Manager Interface profile.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AmiConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub username: String,
+    pub secret: String,
+    /// The channel rung first, usually your own phone, e.g. `PJSIP/1001`.
+    pub channel: String,
+    /// The dialplan context the number is dialled in once you answer.
+    pub context: String,
+    #[serde(default = "default_priority")]
+    pub priority: u32,
+    /// Shown on your phone while it rings, e.g. `Dialler <100>`.
+    pub caller_id: Option<String>,
+    /// Seconds to wait for your phone to be answered.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+/// Places calls server-side with an AMI `Originate`: Asterisk rings your phone, then dials the
+/// number once you answer.
+#[derive(Debug)]
+pub struct AmiDialler {
+    config: AmiConfig,
+}
+
+impl AmiDialler {
+    pub fn new(config: AmiConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Dialler for AmiDialler {
+    fn dial(&self, request: CallRequest, events: Option<UnboundedSender<Event>>) -> AppResult<CallControl> {
+        let config = self.config.clone();
+        let (control, commands) = CallControl::channel(false);
+        tokio::spawn(async move {
+            let call_id = request.call_id;
+            let event = match place_call(&config, &request, &events, commands).await {
+                Ok(()) => DiallerEvent::Ended { call_id, reason: None },
+                Err(err) => DiallerEvent::failed(call_id, "AMI", err),
+            };
+            send(&events, event);
+        });
+
+        Ok(control)
+    }
+}
+
+/// Logs in, originates the call and follows its events until it hangs up.
+async fn place_call(
+    config: &AmiConfig,
+    request: &CallRequest,
+    events: &Option<UnboundedSender<Event>>,
+    commands: UnboundedReceiver<CallCommand>,
+) -> AppResult<()> {
+    let call_id = request.call_id;
+    let mut conn = AmiConnection::connect(&config.host, config.port).await?;
+    conn.login(&config.username, &config.secret).await?;
+
+    let mut originate = AmiMessage::action("Originate")
+        .header("Channel", &config.channel)
+        .header("Context", &config.context)
+        .header("Exten", &request.number.number)
+        .header("Priority", config.priority.to_string())
+        .header("Timeout", (config.timeout * 1000).to_string())
+        .header("Async", "true");
+    if let Some(caller_id) = &config.caller_id {
+        originate = originate.header("CallerID", caller_id);
+    }
+    let action_id = conn.send(originate).await?;
+    send(events, DiallerEvent::Progress { call_id, state: CallState::Originating });
+    let originated_by = Instant::now() + Duration::from_secs(config.timeout) + ORIGINATE_MARGIN;
+
+    // The unique id of the channel to our phone, known once it has answered.
+    let mut channel: Option<String> = None;
+    let mut commands = Some(commands);
+    let mut tones = None;
+    loop {
+        let message = match channel {
+            Some(_) => conn.read_message().await?,
+            // Until the OriginateResponse arrives nothing else will tell us the call went nowhere.
+            None => timeout_at(originated_by, conn.read_message())
+                .await
+                .map_err(|_| ami_error("Asterisk didn't say whether the call was placed"))??,
+        };
+        if message.get("ActionID") == Some(action_id.as_str()) {
+            if message.get("Event").is_none() && message.get("Response") != Some("Success") {
+                return Err(ami_error(message.get("Message").unwrap_or("Originate was refused")));
+            }
+            if message.is_event("OriginateResponse") {
+                if message.get("Response") != Some("Success") {
+                    return Err(ami_error(originate_failure(message.get("Reason"))));
+                }
+                channel = message.get("Uniqueid").map(str::to_string);
+            }
+            continue;
+        }
+
+        let Some(id) = channel.as_deref() else {
+            continue;
+        };
+        if message.get("Uniqueid") != Some(id) && message.get("Linkedid") != Some(id) {
+            continue;
+        }
+
+        match message.get("Event") {
+            Some("DialBegin") => send(events, DiallerEvent::Progress { call_id, state: CallState::Ringing }),
+            Some("DialEnd") => match message.get("DialStatus") {
+                Some("ANSWER") => {
+                    send(events, DiallerEvent::Progress { call_id, state: CallState::Established });
+                    // The tones go out on the channel to the number, not the one to our phone.
+                    let dialled = message.get("DestChannel");
+                    if let (Some(sequence), Some(dialled), Some(commands)) = (request.number.tones(), dialled, commands.take()) {
+                        let task = play_tones(config.clone(), dialled.to_string(), sequence, call_id, events.clone(), commands);
+                        tones = Some(tokio::spawn(task));
+                    }
+                }
+                status => {
+                    let status = status.unwrap_or("unknown").to_lowercase();
+                    return Err(ami_error(format!("the call was not answered ({status})")));
+                }
+            },
+            Some("Hangup") if message.get("Uniqueid") == Some(id) => break,
+            _ => {}
+        }
+    }
+
+    info!("Call {call_id} hung up");
+    if let Some(task) = tones {
+        task.abort();
+    }
+    conn.send(AmiMessage::action("Logoff")).await?;
+    Ok(())
+}
+
+/// Plays the post-dial tones over a manager connection of their own, so the call's events keep
+/// flowing.
+async fn play_tones(
+    config: AmiConfig,
+    channel: String,
+    sequence: String,
+    call_id: u64,
+    events: Option<UnboundedSender<Event>>,
+    mut commands: UnboundedReceiver<CallCommand>,
+) {
+    let played = async {
+        let mut conn = AmiConnection::connect(&config.host, config.port).await?;
+        conn.login(&config.username, &config.secret).await?;
+        let mut sender = AmiTones { conn, channel };
+        dtmf::play(&mut sender, &sequence, call_id, &events, &mut commands).await?;
+        sender.conn.send(AmiMessage::action("Logoff")).await.map(|_| ())
+    };
+    if let Err(err) = played.await {
+        warn!("Could not send the tones for call {call_id}: {}", err.full_message());
+    }
+}
+
+/// Sends tones down a channel with `PlayDTMF`, which takes one digit at a time.
+struct AmiTones {
+    conn: AmiConnection,
+    channel: String,
+}
+
+impl ToneSender for AmiTones {
+    async fn send_tones(&mut self, tones: &str) -> AppResult<()> {
+        for digit in tones.chars() {
+            let play = AmiMessage::action("PlayDTMF")
+                .header("Channel", &self.channel)
+                .header("Digit", digit.to_string())
+                .header("Duration", TONE_LENGTH.to_string());
+            self.conn.request(play).await?;
+        }
+        Ok(())
+    }
+}
+
+/// What an `OriginateResponse` failure `Reason` code means for the caller.
+fn originate_failure(reason: Option<&str>) -> String {
+    match reason {
+        Some("1") => "your phone hung up".to_string(),
+        Some("3") => "your phone wasn't answered".to_string(),
+        Some("5") => "your phone is busy".to_string(),
+        Some("8") => "your phone is unavailable".to_string(),
+        Some(reason) => format!("the call could not be placed (reason {reason})"),
+        None => "the call could not be placed".to_string(),
+    }
+}
+
+fn ami_error(message: impl Into<String>) -> Error {
+    Error::DiallerBackend {
+        backend: "AMI",
+        message: message.into(),
+    }
+}
+
+/// One AMI packet: `Key: Value` lines ending with a blank line.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AmiMessage {
+    pub headers: Vec<(String, String)>,
+}
+
+impl AmiMessage {
+    pub fn action(name: &str) -> Self {
+        Self::default().header("Action", name)
+    }
+
+    pub fn header(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.headers.push((key.to_string(), value.into()));
+        self
+    }
+
+    /// The first value for `key`, ignoring case.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_event(&self, name: &str) -> bool {
+        self.get("Event").is_some_and(|event| event.eq_ignore_ascii_case(name))
+    }
+
+    pub fn to_wire(&self) -> String {
+        let mut wire: String = self
+            .headers
+            .iter()
+            .map(|(key, value)| format!("{key}: {value}\r\n"))
+            .collect();
+        wire.push_str("\r\n");
+        wire
+    }
+
+    /// Parses the lines of one packet. Lines without a `:` are ignored.
+    pub fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Self {
+        let headers = lines
+            .into_iter()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        Self { headers }
+    }
+}
+
+/// A logged-in connection to the Asterisk Manager Interface.
+pub struct AmiConnection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    next_action_id: u64,
+}
+
+impl AmiConnection {
+    /// Connects and checks the `Asterisk Call Manager` greeting.
+    pub async fn connect(host: &str, port: u16) -> AppResult<Self> {
+        let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| ami_error(format!("timed out connecting to {host}:{port}")))??;
+        let (reader, writer) = stream.into_split();
+        let mut conn = Self {
+            reader: BufReader::new(reader),
+            writer,
+            next_action_id: 1,
+        };
+
+        let greeting = conn.read_line().await?;
+        if !greeting.starts_with("Asterisk Call Manager") {
+            return Err(ami_error(format!("{host}:{port} is not an AMI server (it said {greeting:?})")));
+        }
+        Ok(conn)
+    }
+
+    pub async fn login(&mut self, username: &str, secret: &str) -> AppResult<()> {
+        let login = AmiMessage::action("Login")
+            .header("Username", username)
+            .header("Secret", secret)
+            .header("Events", "call");
+        self.request(login).await.map(|_| ())
+    }
+
+    /// Sends `message` with a fresh `ActionID`, which is returned. Values with line breaks are
+    /// refused, since they would end the header and let the rest be read as headers of its own.
+    pub async fn send(&mut self, message: AmiMessage) -> AppResult<String> {
+        if let Some((key, _)) = message.headers.iter().find(|(_, value)| value.contains(['\r', '\n'])) {
+            return Err(ami_error(format!("the {key} header can't contain a line break")));
+        }
+        let action_id = self.next_action_id.to_string();
+        self.next_action_id += 1;
+        let message = message.header("ActionID", action_id.clone());
+        debug!("AMI > {message:?}");
+        self.writer.write_all(message.to_wire().as_bytes()).await?;
+        self.writer.flush().await?;
+        Ok(action_id)
+    }
+
+    /// Sends `message` and waits for its response, skipping any events in between.
+    pub async fn request(&mut self, message: AmiMessage) -> AppResult<AmiMessage> {
+        let action_id = self.send(message).await?;
+        loop {
+            let reply = self.read_message().await?;
+            if reply.get("ActionID") != Some(action_id.as_str()) || reply.get("Response").is_none() {
+                continue;
+            }
+            return match reply.get("Response") {
+                Some("Success") => Ok(reply),
+                _ => Err(ami_error(reply.get("Message").unwrap_or("the request failed"))),
+            };
+        }
+    }
+
+    pub async fn read_message(&mut self) -> AppResult<AmiMessage> {
+        let mut lines = vec![];
+        loop {
+            let line = self.read_line().await?;
+            if line.is_empty() {
+                if lines.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            lines.push(line);
+        }
+
+        let message = AmiMessage::parse(lines.iter().map(String::as_str));
+        debug!("AMI < {message:?}");
+        Ok(message)
+    }
+
+    async fn read_line(&mut self) -> AppResult<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).await? == 0 {
+            return Err(ami_error("the server closed the connection"));
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialler::tests::{call_events, request};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// The server end of a manager connection, answering as a test scripts it.
+    struct MockAmi {
+        reader: BufReader<OwnedReadHalf>,
+        writer: OwnedWriteHalf,
+    }
+
+    impl MockAmi {
+        async fn accept(listener: &TcpListener) -> Self {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, writer) = stream.into_split();
+            let mut mock = Self {
+                reader: BufReader::new(reader),
+                writer,
+            };
+            mock.write("Asterisk Call Manager/7.0.3\r\n").await;
+            mock
+        }
+
+        /// Reads the next action, or `None` once the client has hung up.
+        async fn read(&mut self) -> Option<AmiMessage> {
+            let mut lines = vec![];
+            loop {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line).await.unwrap() == 0 {
+                    return None;
+                }
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    return Some(AmiMessage::parse(lines.iter().map(String::as_str)));
+                }
+                lines.push(line);
+            }
+        }
+
+        async fn expect(&mut self, action: &str) -> AmiMessage {
+            let message = self.read().await.expect("the client should send an action");
+            assert_eq!(message.get("Action"), Some(action), "{message:?}");
+            message
+        }
+
+        /// Answers `action` with `headers`, echoing its `ActionID`.
+        async fn reply(&mut self, action: &AmiMessage, headers: &[(&str, &str)]) {
+            let mut reply = AmiMessage::default();
+            for (key, value) in headers {
+                reply = reply.header(key, *value);
+            }
+            reply = reply.header("ActionID", action.get("ActionID").unwrap());
+            self.write(&reply.to_wire()).await;
+        }
+
+        /// Says how the `Originate` went, with the `ActionID` it was sent with.
+        async fn originate_response(&mut self, originate: &AmiMessage, headers: &[(&str, &str)]) {
+            let action_id = originate.get("ActionID").unwrap();
+            let headers = [&[("Event", "OriginateResponse"), ("ActionID", action_id)], headers].concat();
+            self.event(&headers).await;
+        }
+
+        async fn event(&mut self, headers: &[(&str, &str)]) {
+            let event = headers
+                .iter()
+                .fold(AmiMessage::default(), |event, (key, value)| event.header(key, *value));
+            self.write(&event.to_wire()).await;
+        }
+
+        async fn write(&mut self, text: &str) {
+            self.writer.write_all(text.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn config(port: u16) -> AmiConfig {
+        AmiConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: "dialler".to_string(),
+            secret: "s3cret".to_string(),
+            channel: "PJSIP/1001".to_string(),
+            context: "from-internal".to_string(),
+            priority: 1,
+            caller_id: Some("Dialler <100>".to_string()),
+            timeout: 30,
+        }
+    }
+
+    /// Dials `number` against a mock run by `script`, returning the events the call produced.
+    async fn call<F>(
+        config: impl FnOnce(u16) -> AmiConfig,
+        number: &str,
+        script: impl FnOnce(MockAmi) -> F,
+    ) -> Vec<DiallerEvent>
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dialler = AmiDialler::new(config(listener.local_addr().unwrap().port()));
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        dialler.dial(request(number), Some(sender)).unwrap();
+
+        let server = tokio::spawn(script(MockAmi::accept(&listener).await));
+        let events = call_events(&mut receiver).await;
+        server.await.unwrap();
+        events
+    }
+
+    async fn logged_in(mut ami: MockAmi) -> MockAmi {
+        let login = ami.expect("Login").await;
+        assert_eq!(login.get("Username"), Some("dialler"));
+        assert_eq!(login.get("Secret"), Some("s3cret"));
+        ami.reply(&login, &[("Response", "Success"), ("Message", "Authentication accepted")]).await;
+        ami
+    }
+
+    fn progress(state: CallState) -> DiallerEvent {
+        DiallerEvent::Progress { call_id: 7, state }
+    }
+
+    fn failed(message: &str) -> DiallerEvent {
+        DiallerEvent::Failed {
+            call_id: 7,
+            backend: "AMI",
+            message: message.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn an_answered_call_is_followed_until_it_hangs_up() {
+        let events = call(config, "02 9999 1234", |ami| async move {
+            let mut ami = logged_in(ami).await;
+            let originate = ami.expect("Originate").await;
+            assert_eq!(originate.get("Channel"), Some("PJSIP/1001"));
+            assert_eq!(originate.get("Context"), Some("from-internal"));
+            assert_eq!(originate.get("Exten"), Some("0299991234"));
+            assert_eq!(originate.get("CallerID"), Some("Dialler <100>"));
+            assert_eq!(originate.get("Timeout"), Some("30000"));
+            ami.reply(&originate, &[("Response", "Success"), ("Message", "Originate queued")]).await;
+
+            // Someone else's call is ignored.
+            ami.event(&[("Event", "Hangup"), ("Uniqueid", "99.1")]).await;
+            ami.originate_response(&originate, &[("Response", "Success"), ("Uniqueid", "100.1")]).await;
+            ami.event(&[("Event", "DialBegin"), ("Uniqueid", "100.1")]).await;
+            let answered = [("DialStatus", "ANSWER"), ("DestChannel", "PJSIP/trunk-01")];
+            ami.event(&[&[("Event", "DialEnd"), ("Linkedid", "100.1")], &answered[..]].concat()).await;
+            ami.event(&[("Event", "Hangup"), ("Uniqueid", "100.1")]).await;
+            ami.expect("Logoff").await;
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [
+                progress(CallState::Originating),
+                progress(CallState::Ringing),
+                progress(CallState::Established),
+                DiallerEvent::Ended { call_id: 7, reason: None },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_refused_login_fails_the_call() {
+        let events = call(config, "0299991234", |mut ami| async move {
+            let login = ami.expect("Login").await;
+            ami.reply(&login, &[("Response", "Error"), ("Message", "Authentication failed")]).await;
+        })
+        .await;
+
+        assert_eq!(events, [failed("Authentication failed")]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_originate_says_why() {
+        let events = call(config, "0299991234", |ami| async move {
+            let mut ami = logged_in(ami).await;
+            let originate = ami.expect("Originate").await;
+            ami.reply(&originate, &[("Response", "Success")]).await;
+            ami.originate_response(&originate, &[("Response", "Failure"), ("Reason", "5")]).await;
+        })
+        .await;
+
+        assert_eq!(events, [progress(CallState::Originating), failed("your phone is busy")]);
+    }
+
+    #[tokio::test]
+    async fn no_originate_response_times_out() {
+        let quick = |port| AmiConfig { timeout: 1, ..config(port) };
+        let events = call(quick, "0299991234", |ami| async move {
+            let mut ami = logged_in(ami).await;
+            let originate = ami.expect("Originate").await;
+            ami.reply(&originate, &[("Response", "Success")]).await;
+            // Let the clock run on to the deadline while Asterisk says nothing more.
+            tokio::time::pause();
+            assert_eq!(ami.read().await, None);
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [progress(CallState::Originating), failed("Asterisk didn't say whether the call was placed")]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_busy_number_fails_the_call() {
+        let events = call(config, "0299991234", |ami| async move {
+            let mut ami = logged_in(ami).await;
+            let originate = ami.expect("Originate").await;
+            ami.reply(&originate, &[("Response", "Success")]).await;
+            ami.originate_response(&originate, &[("Response", "Success"), ("Uniqueid", "100.1")]).await;
+            ami.event(&[("Event", "DialEnd"), ("Uniqueid", "100.1"), ("DialStatus", "BUSY")]).await;
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [progress(CallState::Originating), failed("the call was not answered (busy)")]
+        );
+    }
+
+    #[tokio::test]
+    async fn line_breaks_cannot_smuggle_in_headers() {
+        let with_bad_caller_id = |port| AmiConfig {
+            caller_id: Some("Dialler <100>\r\nAction: Command".to_string()),
+            ..config(port)
+        };
+        let events = call(with_bad_caller_id, "0299991234", |ami| async move {
+            let mut ami = logged_in(ami).await;
+            assert_eq!(ami.read().await, None, "nothing more should be sent");
+        })
+        .await;
+
+        assert_eq!(events, [failed("the CallerID header can't contain a line break")]);
+    }
+}
@@ -0,0 +1,523 @@
+use crate::dialler::dtmf::{self, ToneSender};
+use crate::dialler::{send, CallCommand, CallControl, CallRequest, CallState, Dialler, DiallerEvent};
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest netstring accepted from baresip, so a confused peer can't make us allocate freely.
+const MAX_MESSAGE: usize = 64 * 1024;
+
+fn default_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    4444
+}
+
+/// Settings for a profile that drives a running baresip through its `ctrl_tcp` module.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BaresipConfig {
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+/// Places calls from a baresip softphone, which dials the number itself and reports the call's
+/// progress, and can be told to hang up.
+#[derive(Debug)]
+pub struct BaresipDialler {
+    config: BaresipConfig,
+}
+
+impl BaresipDialler {
+    pub fn new(config: BaresipConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Dialler for BaresipDialler {
+    fn dial(&self, request: CallRequest, events: Option<UnboundedSender<Event>>) -> AppResult<CallControl> {
+        let config = self.config.clone();
+        let (control, commands) = CallControl::channel(true);
+        tokio::spawn(async move {
+            let call_id = request.call_id;
+            let event = match place_call(&config, &request, &events, commands).await {
+                Ok(reason) => DiallerEvent::Ended { call_id, reason },
+                Err(err) => DiallerEvent::failed(call_id, "baresip", err),
+            };
+            send(&events, event);
+        });
+
+        Ok(control)
+    }
+}
+
+/// Dials the number and follows the call baresip starts for it until it closes, returning why.
+async fn place_call(
+    config: &BaresipConfig,
+    request: &CallRequest,
+    events: &Option<UnboundedSender<Event>>,
+    mut commands: UnboundedReceiver<CallCommand>,
+) -> AppResult<Option<String>> {
+    let call_id = request.call_id;
+    let mut conn = BaresipConnection::connect(&config.host, config.port).await?;
+    let token = call_id.to_string();
+    conn.send(&BaresipCommand::new("dial", request.address.clone(), &token)).await?;
+
+    // baresip's id for our call, from the dial's response. baresip reports the new call before it
+    // answers the command, so call events are held back until the response has said which is ours.
+    let mut call: Option<String> = None;
+    let mut dialled = false;
+    let mut early: VecDeque<BaresipMessage> = VecDeque::new();
+    let mut established = false;
+    let mut hung_up = false;
+    // Where go-aheads are passed on to, once the tones are playing.
+    let mut tones: Option<(UnboundedSender<CallCommand>, JoinHandle<()>)> = None;
+    loop {
+        let held_back = if dialled { early.pop_front() } else { None };
+        let message = match held_back {
+            Some(message) => message,
+            None => tokio::select! {
+                message = conn.read_message() => message?,
+                Some(command) = commands.recv() => {
+                    match (command, &call) {
+                        (CallCommand::HangUp, Some(id)) => {
+                            hung_up = true;
+                            conn.send(&BaresipCommand::new("hangup", id.clone(), &token)).await?;
+                        }
+                        // Nothing to hang up yet; it's done once the call is known.
+                        (CallCommand::HangUp, None) => {
+                            hung_up = true;
+                            info!("Hang up for call {call_id} requested before it started");
+                        }
+                        (CallCommand::SendTones, _) => {
+                            if let Some((sender, _)) = &tones {
+                                sender.send(command).ok();
+                            }
+                        }
+                    }
+                    continue;
+                }
+            },
+        };
+
+        if message.response {
+            if message.token.as_deref() != Some(token.as_str()) {
+                continue;
+            }
+            if !message.ok {
+                let reason = message.data.trim();
+                return Err(baresip_error(if reason.is_empty() { "the dial was refused" } else { reason }));
+            }
+            if !dialled {
+                dialled = true;
+                call = dialled_call_id(&message.data);
+                match &call {
+                    Some(id) if hung_up => conn.send(&BaresipCommand::new("hangup", id.clone(), &token)).await?,
+                    Some(_) => {}
+                    None => warn!("baresip didn't give the id of call {call_id}; following its next outgoing call"),
+                }
+            }
+            continue;
+        }
+        if !message.event || message.class != "call" {
+            continue;
+        }
+        if !dialled {
+            early.push_back(message);
+            continue;
+        }
+
+        match &call {
+            Some(id) if *id != message.id => continue,
+            Some(_) => {}
+            // A baresip too old to answer a dial with the call's id.
+            None if message.direction == "outgoing" => {
+                call = Some(message.id.clone());
+                if hung_up {
+                    conn.send(&BaresipCommand::new("hangup", message.id.clone(), &token)).await?;
+                }
+            }
+            None => continue,
+        }
+
+        let state = match message.kind.as_str() {
+            "CALL_OUTGOING" => CallState::Dialling,
+            "CALL_RINGING" | "CALL_PROGRESS" => CallState::Ringing,
+            "CALL_ESTABLISHED" => {
+                established = true;
+                if let (Some(sequence), None) = (request.number.tones(), &tones) {
+                    let (sender, receiver) = mpsc::unbounded_channel();
+                    let (call, events) = (message.id.clone(), events.clone());
+                    let task = tokio::spawn(play_tones(config.clone(), call, sequence, call_id, events, receiver));
+                    tones = Some((sender, task));
+                }
+                CallState::Established
+            }
+            "CALL_CLOSED" => {
+                if let Some((_, task)) = &tones {
+                    task.abort();
+                }
+                let reason = message.param.trim();
+                if !established && !hung_up && !reason.is_empty() {
+                    return Err(baresip_error(reason));
+                }
+                info!("Call {call_id} closed: {reason}");
+                return Ok(Some(reason.to_string()).filter(|reason| !reason.is_empty()));
+            }
+            _ => continue,
+        };
+        send(events, DiallerEvent::Progress { call_id, state });
+    }
+}
+
+/// Plays the post-dial tones over a connection of their own, so the call's events keep flowing.
+async fn play_tones(
+    config: BaresipConfig,
+    call: String,
+    sequence: String,
+    call_id: u64,
+    events: Option<UnboundedSender<Event>>,
+    mut commands: UnboundedReceiver<CallCommand>,
+) {
+    let played = async {
+        let conn = BaresipConnection::connect(&config.host, config.port).await?;
+        let mut sender = BaresipTones { conn, call, call_id };
+        dtmf::play(&mut sender, &sequence, call_id, &events, &mut commands).await
+    };
+    if let Err(err) = played.await {
+        warn!("Could not send the tones for call {call_id}: {}", err.full_message());
+    }
+}
+
+/// Sends tones into a call with `sndcode`. That only ever plays into baresip's current call, so
+/// `callfind` makes ours current first; a call switched to from elsewhere in between would still
+/// get them.
+struct BaresipTones {
+    conn: BaresipConnection,
+    /// baresip's id for the call.
+    call: String,
+    call_id: u64,
+}
+
+impl ToneSender for BaresipTones {
+    async fn send_tones(&mut self, tones: &str) -> AppResult<()> {
+        let token = self.call_id.to_string();
+        self.conn.send(&BaresipCommand::new("callfind", self.call.clone(), &token)).await?;
+        self.conn.send(&BaresipCommand::new("sndcode", tones.to_string(), &token)).await
+    }
+}
+
+/// baresip's id for the call a `dial` started, from the `call id: <id>` line of its response.
+fn dialled_call_id(data: &str) -> Option<String> {
+    data.lines()
+        .find_map(|line| line.trim().strip_prefix("call id:"))
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+fn baresip_error(message: impl Into<String>) -> Error {
+    Error::DiallerBackend {
+        backend: "baresip",
+        message: message.into(),
+    }
+}
+
+/// A command for `ctrl_tcp`, whose response echoes `token`.
+#[derive(Debug, Serialize)]
+pub struct BaresipCommand {
+    pub command: String,
+    pub params: String,
+    pub token: String,
+}
+
+impl BaresipCommand {
+    pub fn new(command: &str, params: String, token: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            params,
+            token: token.to_string(),
+        }
+    }
+}
+
+/// A response or event from `ctrl_tcp`. Only the fields this backend reads are kept.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BaresipMessage {
+    pub response: bool,
+    pub ok: bool,
+    pub data: String,
+    pub token: Option<String>,
+    pub event: bool,
+    pub class: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+    pub direction: String,
+    pub param: String,
+}
+
+/// A connection to baresip's `ctrl_tcp` module, which speaks JSON in netstrings (`len:json,`).
+/// Messages are read on their own task so waiting for one can be raced against other work.
+pub struct BaresipConnection {
+    writer: OwnedWriteHalf,
+    messages: UnboundedReceiver<AppResult<BaresipMessage>>,
+}
+
+impl BaresipConnection {
+    pub async fn connect(host: &str, port: u16) -> AppResult<Self> {
+        let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| baresip_error(format!("timed out connecting to {host}:{port}")))?
+            .map_err(|err| baresip_error(format!("could not connect to {host}:{port}: {err}; is ctrl_tcp loaded?")))?;
+        let (reader, writer) = stream.into_split();
+        let (sender, messages) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            loop {
+                let message = read_message(&mut reader).await;
+                let failed = message.is_err();
+                if sender.send(message).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(Self { writer, messages })
+    }
+
+    pub async fn send(&mut self, command: &BaresipCommand) -> AppResult<()> {
+        let json = serde_json::to_string(command).map_err(|err| baresip_error(err.to_string()))?;
+        debug!("baresip > {json}");
+        self.writer
+            .write_all(format!("{}:{json},", json.len()).as_bytes())
+            .await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    /// The next message. Cancel safe, so it can be used in `tokio::select!`.
+    pub async fn read_message(&mut self) -> AppResult<BaresipMessage> {
+        self.messages
+            .recv()
+            .await
+            .unwrap_or_else(|| Err(baresip_error("the server closed the connection")))
+    }
+}
+
+async fn read_message(reader: &mut BufReader<OwnedReadHalf>) -> AppResult<BaresipMessage> {
+    let mut len = 0usize;
+    loop {
+        let byte = read_byte(reader).await?;
+        match byte {
+            b'0'..=b'9' => len = len * 10 + usize::from(byte - b'0'),
+            b':' => break,
+            _ => return Err(baresip_error("the server sent a malformed netstring")),
+        }
+        if len > MAX_MESSAGE {
+            return Err(baresip_error("the server sent an oversized message"));
+        }
+    }
+
+    let mut json = vec![0; len];
+    reader.read_exact(&mut json).await?;
+    if read_byte(reader).await? != b',' {
+        return Err(baresip_error("the server sent a malformed netstring"));
+    }
+
+    debug!("baresip < {}", String::from_utf8_lossy(&json));
+    serde_json::from_slice(&json).map_err(|err| baresip_error(format!("could not read a message: {err}")))
+}
+
+async fn read_byte(reader: &mut BufReader<OwnedReadHalf>) -> AppResult<u8> {
+    match reader.read_u8().await {
+        Ok(byte) => Ok(byte),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Err(baresip_error("the server closed the connection")),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialler::tests::{call_events, request};
+    use crate::uri::DialNumber;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    /// The baresip end of a `ctrl_tcp` connection, answering as a test scripts it.
+    struct MockBaresip {
+        reader: BufReader<OwnedReadHalf>,
+        writer: OwnedWriteHalf,
+    }
+
+    impl MockBaresip {
+        async fn accept(listener: &TcpListener) -> Self {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, writer) = stream.into_split();
+            Self {
+                reader: BufReader::new(reader),
+                writer,
+            }
+        }
+
+        /// Reads a command, checking it's `command` with `params`.
+        async fn expect(&mut self, command: &str, params: &str) -> Value {
+            let mut len = String::new();
+            loop {
+                match self.reader.read_u8().await.unwrap() {
+                    b':' => break,
+                    byte => len.push(char::from(byte)),
+                }
+            }
+            let mut json = vec![0; len.parse().unwrap()];
+            self.reader.read_exact(&mut json).await.unwrap();
+            assert_eq!(self.reader.read_u8().await.unwrap(), b',');
+
+            let sent: Value = serde_json::from_slice(&json).unwrap();
+            assert_eq!(sent["command"], command, "{sent}");
+            assert_eq!(sent["params"], params, "{sent}");
+            assert_eq!(sent["token"], "7", "{sent}");
+            sent
+        }
+
+        async fn respond(&mut self, ok: bool, data: &str) {
+            self.write(json!({ "response": true, "ok": ok, "data": data, "token": "7" })).await;
+        }
+
+        async fn event(&mut self, kind: &str, id: &str, param: &str) {
+            self.write(json!({
+                "event": true,
+                "class": "call",
+                "type": kind,
+                "id": id,
+                "direction": "outgoing",
+                "param": param,
+            }))
+            .await;
+        }
+
+        async fn write(&mut self, message: Value) {
+            let json = message.to_string();
+            self.writer.write_all(format!("{}:{json},", json.len()).as_bytes()).await.unwrap();
+        }
+    }
+
+    /// Places `request` against a mock run by `script`, returning the events the call produced.
+    async fn call<F>(request: CallRequest, script: impl FnOnce(TcpListener) -> F) -> Vec<DiallerEvent>
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let dialler = BaresipDialler::new(BaresipConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+        });
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        dialler.dial(request, Some(sender)).unwrap();
+
+        let server = tokio::spawn(script(listener));
+        let events = call_events(&mut receiver).await;
+        server.await.unwrap();
+        events
+    }
+
+    fn progress(state: CallState) -> DiallerEvent {
+        DiallerEvent::Progress { call_id: 7, state }
+    }
+
+    #[tokio::test]
+    async fn the_call_is_the_one_the_dial_started() {
+        let events = call(request("0299991234"), |listener| async move {
+            let mut baresip = MockBaresip::accept(&listener).await;
+            baresip.expect("dial", "0299991234").await;
+            // Another outgoing call is reported first, as if it was dialled from baresip meanwhile.
+            baresip.event("CALL_OUTGOING", "other", "").await;
+            baresip.event("CALL_OUTGOING", "ours", "").await;
+            baresip.respond(true, "call id: ours").await;
+            baresip.event("CALL_CLOSED", "other", "Rejected").await;
+            baresip.event("CALL_RINGING", "ours", "").await;
+            baresip.event("CALL_ESTABLISHED", "ours", "").await;
+            baresip.event("CALL_CLOSED", "ours", "Connection reset by user").await;
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [
+                progress(CallState::Dialling),
+                progress(CallState::Ringing),
+                progress(CallState::Established),
+                DiallerEvent::Ended {
+                    call_id: 7,
+                    reason: Some("Connection reset by user".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tones_go_to_the_call_the_dial_started() {
+        let request = CallRequest {
+            number: DialNumber::with_tones("0299991234", Some("5")),
+            ..request("0299991234")
+        };
+        let events = call(request, |listener| async move {
+            let mut baresip = MockBaresip::accept(&listener).await;
+            baresip.expect("dial", "0299991234").await;
+            baresip.respond(true, "call id: ours").await;
+            baresip.event("CALL_ESTABLISHED", "ours", "").await;
+
+            let mut tones = MockBaresip::accept(&listener).await;
+            tones.expect("callfind", "ours").await;
+            tones.expect("sndcode", "5").await;
+            baresip.event("CALL_CLOSED", "ours", "").await;
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [progress(CallState::Established), DiallerEvent::Ended { call_id: 7, reason: None }]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_refused_dial_says_why() {
+        let events = call(request("0299991234"), |listener| async move {
+            let mut baresip = MockBaresip::accept(&listener).await;
+            baresip.expect("dial", "0299991234").await;
+            baresip.respond(false, "invalid URI").await;
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [DiallerEvent::Failed {
+                call_id: 7,
+                backend: "baresip",
+                message: "invalid URI".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn the_call_id_is_read_from_the_dial_response() {
+        assert_eq!(dialled_call_id("call id: 2f1c7a\n"), Some("2f1c7a".to_string()));
+        assert_eq!(dialled_call_id(""), None);
+        assert_eq!(dialled_call_id("call id: "), None);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::dialler::{send, CallCommand, CallState, DiallerEvent};
+use crate::error::AppResult;
+use crate::event::Event;
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tracing::info;
+
+/// How long a `,` waits, as on most phones.
+pub const PAUSE: Duration = Duration::from_secs(2);
+
+pub const MAX_LENGTH: usize = 50;
+
+/// One step of a post-dial sequence such as `,,2;345#`: `,` pauses and `;` waits until the user
+/// says to go on, e.g. once an IVR has finished talking.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Tones(String),
+    Pause,
+    Wait,
+}
+
+/// Whether `c` can be in a post-dial sequence: a tone, a pause or a wait.
+pub fn is_valid_char(c: char) -> bool {
+    c.is_ascii_digit() || matches!(c.to_ascii_uppercase(), '*' | '#' | 'A'..='D' | ',' | ';')
+}
+
+pub fn validate(sequence: &str) -> Result<(), String> {
+    if !sequence.chars().all(is_valid_char) {
+        return Err("Only 0-9 * # A-D, with , to pause and ; to wait".to_string());
+    }
+    if sequence.chars().count() > MAX_LENGTH {
+        return Err(format!("Must be at most {MAX_LENGTH} characters"));
+    }
+    Ok(())
+}
+
+pub fn steps(sequence: &str) -> Vec<Step> {
+    let mut steps = vec![];
+    let mut tones = String::new();
+    for c in sequence.chars() {
+        let step = match c {
+            ',' => Step::Pause,
+            ';' => Step::Wait,
+            c => {
+                tones.push(c.to_ascii_uppercase());
+                continue;
+            }
+        };
+        if !tones.is_empty() {
+            steps.push(Step::Tones(std::mem::take(&mut tones)));
+        }
+        steps.push(step);
+    }
+    if !tones.is_empty() {
+        steps.push(Step::Tones(tones));
+    }
+    steps
+}
+
+/// The sequence as a `tel:`/`sip:` `postd` parameter, which writes pauses as `p` and waits as `w`.
+pub fn to_postd(sequence: &str) -> String {
+    sequence.replace(',', "p").replace(';', "w")
+}
+
+pub fn from_postd(postd: &str) -> String {
+    postd
+        .chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            'p' => ',',
+            'w' => ';',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Sends tones into an established call, however the backend does it.
+pub(crate) trait ToneSender {
+    async fn send_tones(&mut self, tones: &str) -> AppResult<()>;
+}
+
+/// Plays `sequence` into call `call_id`, pausing for `,` and, for `;`, waiting for a
+/// [`CallCommand::SendTones`]. Stops quietly if the call ends while it's waiting.
+pub(crate) async fn play(
+    sender: &mut impl ToneSender,
+    sequence: &str,
+    call_id: u64,
+    events: &Option<UnboundedSender<Event>>,
+    commands: &mut UnboundedReceiver<CallCommand>,
+) -> AppResult<()> {
+    for step in steps(sequence) {
+        match step {
+            Step::Tones(tones) => {
+                info!("Sending tones for call {call_id}");
+                sender.send_tones(&tones).await?;
+            }
+            Step::Pause => tokio::time::sleep(PAUSE).await,
+            Step::Wait => {
+                // Only a go-ahead given during this wait counts.
+                while commands.try_recv().is_ok() {}
+                send(events, DiallerEvent::Progress { call_id, state: CallState::Waiting });
+                loop {
+                    match commands.recv().await {
+                        Some(CallCommand::SendTones) => break,
+                        Some(_) => {}
+                        None => return Ok(()),
+                    }
+                }
+                send(events, DiallerEvent::Progress { call_id, state: CallState::Established });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tones(tones: &str) -> Step {
+        Step::Tones(tones.to_string())
+    }
+
+    #[test]
+    fn pauses_and_waits_split_the_tones() {
+        assert_eq!(
+            steps(",,2;345#"),
+            [Step::Pause, Step::Pause, tones("2"), Step::Wait, tones("345#")]
+        );
+        assert_eq!(steps("12;"), [tones("12"), Step::Wait]);
+        assert_eq!(steps(""), []);
+    }
+
+    #[test]
+    fn letter_tones_are_sent_in_upper_case() {
+        assert_eq!(steps("a,d*"), [tones("A"), Step::Pause, tones("D*")]);
+    }
+
+    #[test]
+    fn only_tones_pauses_and_waits_are_valid() {
+        assert!(validate(",,2;345#*ABCDabcd").is_ok());
+        for sequence in ["12x", "1 2", "p", "1-2", "+1"] {
+            assert!(validate(sequence).is_err(), "{sequence}");
+        }
+        assert!(validate(&"1".repeat(MAX_LENGTH)).is_ok());
+        assert!(validate(&"1".repeat(MAX_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn postd_writes_pauses_as_p_and_waits_as_w() {
+        assert_eq!(to_postd(",,2;345#"), "pp2w345#");
+        assert_eq!(from_postd("PP2W345#"), ",,2;345#");
+        assert_eq!(from_postd(&to_postd("1,2;3")), "1,2;3");
+    }
+}
@@ -0,0 +1,673 @@
+use crate::dialler::dtmf::{self, ToneSender};
+use crate::dialler::{send, CallCommand, CallControl, CallRequest, CallState, Dialler, DiallerEvent};
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use crate::uri::unescape;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The channel events followed for a call, besides the `BACKGROUND_JOB` with the originate result.
+const EVENTS: &str = "BACKGROUND_JOB CHANNEL_ANSWER CHANNEL_BRIDGE CHANNEL_HANGUP_COMPLETE";
+
+fn default_port() -> u16 {
+    8021
+}
+
+fn default_context() -> String {
+    "default".to_string()
+}
+
+fn default_dialplan() -> String {
+    "XML".to_string()
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+/// Settings for a FreeSWITCH Event Socket profile.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EslConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub password: String,
+    /// The endpoint rung first, usually your own phone, e.g. `user/1001`.
+    pub endpoint: String,
+    /// The dialplan context the number is dialled in once you answer.
+    #[serde(default = "default_context")]
+    pub context: String,
+    #[serde(default = "default_dialplan")]
+    pub dialplan: String,
+    /// Shown on your phone while it rings.
+    pub caller_id_name: Option<String>,
+    pub caller_id_number: Option<String>,
+    /// Seconds to wait for your phone to be answered.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl EslConfig {
+    /// Checks the settings that go into commands as they are. A line break would end the command
+    /// and start another, and `'`, `,` or a brace in the caller ID would break up the `{…}` block of
+    /// channel variables it goes in.
+    pub fn validate(&self) -> Result<(), String> {
+        let settings = [
+            ("password", Some(&self.password)),
+            ("endpoint", Some(&self.endpoint)),
+            ("context", Some(&self.context)),
+            ("dialplan", Some(&self.dialplan)),
+            ("caller_id_name", self.caller_id_name.as_ref()),
+            ("caller_id_number", self.caller_id_number.as_ref()),
+        ];
+        for (key, value) in settings {
+            match value {
+                Some(value) if value.contains(['\r', '\n']) => return Err(format!("{key} can't contain a line break")),
+                Some(value) if key.starts_with("caller_id") && value.contains(['\'', ',', '{', '}']) => {
+                    return Err(format!("{key} can't contain ', commas or braces"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Places calls server-side with a `bgapi originate`: FreeSWITCH rings your phone, then runs the
+/// number through the dialplan once you answer.
+#[derive(Debug)]
+pub struct EslDialler {
+    config: EslConfig,
+}
+
+impl EslDialler {
+    pub fn new(config: EslConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Dialler for EslDialler {
+    fn dial(&self, request: CallRequest, events: Option<UnboundedSender<Event>>) -> AppResult<CallControl> {
+        let config = self.config.clone();
+        let (control, commands) = CallControl::channel(false);
+        tokio::spawn(async move {
+            let call_id = request.call_id;
+            let event = match place_call(&config, &request, &events, commands).await {
+                Ok(()) => DiallerEvent::Ended { call_id, reason: None },
+                Err(err) => DiallerEvent::failed(call_id, "ESL", err),
+            };
+            send(&events, event);
+        });
+
+        Ok(control)
+    }
+}
+
+/// Authenticates, originates the call and follows its channel until it hangs up.
+async fn place_call(
+    config: &EslConfig,
+    request: &CallRequest,
+    events: &Option<UnboundedSender<Event>>,
+    commands: UnboundedReceiver<CallCommand>,
+) -> AppResult<()> {
+    let call_id = request.call_id;
+    let mut conn = EslConnection::connect(&config.host, config.port).await?;
+    conn.auth(&config.password).await?;
+
+    // Naming the channel and the job up front means their events can be filtered for before the
+    // originate is even sent.
+    let channel = Uuid::new_v4().to_string();
+    let job = Uuid::new_v4().to_string();
+    conn.command(&format!("event plain {EVENTS}"), &[]).await?;
+    conn.command(&format!("filter Unique-ID {channel}"), &[]).await?;
+    conn.command(&format!("filter Job-UUID {job}"), &[]).await?;
+
+    let originate = originate_command(config, &channel, &request.number.number);
+    conn.command(&format!("bgapi {originate}"), &[("Job-UUID", &job)]).await?;
+    send(events, DiallerEvent::Progress { call_id, state: CallState::Originating });
+
+    let mut bridged = false;
+    let mut commands = Some(commands);
+    let mut tones = None;
+    loop {
+        let event = conn.read_event().await?;
+        match event.get("Event-Name") {
+            Some("BACKGROUND_JOB") if event.get("Job-UUID") == Some(job.as_str()) => {
+                let result = event.body.trim();
+                if let Some(cause) = result.strip_prefix("-ERR") {
+                    return Err(esl_error(originate_failure(cause.trim())));
+                }
+            }
+            Some(_) if event.get("Unique-ID") != Some(channel.as_str()) => {}
+            Some("CHANNEL_ANSWER") => send(events, DiallerEvent::Progress { call_id, state: CallState::Ringing }),
+            Some("CHANNEL_BRIDGE") => {
+                bridged = true;
+                send(events, DiallerEvent::Progress { call_id, state: CallState::Established });
+                // The tones go to the leg to the number, not the one to our phone.
+                let other_leg = event.get("Other-Leg-Unique-ID");
+                if let (Some(sequence), Some(other_leg), Some(commands)) = (request.number.tones(), other_leg, commands.take()) {
+                    let task = play_tones(config.clone(), other_leg.to_string(), sequence, call_id, events.clone(), commands);
+                    tones = Some(tokio::spawn(task));
+                }
+            }
+            Some("CHANNEL_HANGUP_COMPLETE") => {
+                let cause = event.get("Hangup-Cause").unwrap_or("NORMAL_CLEARING");
+                if !bridged && cause != "NORMAL_CLEARING" {
+                    return Err(esl_error(originate_failure(cause)));
+                }
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    info!("Call {call_id} hung up");
+    if let Some(task) = tones {
+        task.abort();
+    }
+    // The call is over either way, so a server that drops the connection first is no failure.
+    conn.command("exit", &[]).await.ok();
+    Ok(())
+}
+
+/// Plays the post-dial tones over a connection of their own, so the call's events keep flowing.
+async fn play_tones(
+    config: EslConfig,
+    leg: String,
+    sequence: String,
+    call_id: u64,
+    events: Option<UnboundedSender<Event>>,
+    mut commands: UnboundedReceiver<CallCommand>,
+) {
+    let played = async {
+        let mut conn = EslConnection::connect(&config.host, config.port).await?;
+        conn.auth(&config.password).await?;
+        let mut sender = EslTones { conn, leg };
+        dtmf::play(&mut sender, &sequence, call_id, &events, &mut commands).await?;
+        sender.conn.command("exit", &[]).await.ok();
+        Ok::<_, Error>(())
+    };
+    if let Err(err) = played.await {
+        warn!("Could not send the tones for call {call_id}: {}", err.full_message());
+    }
+}
+
+/// Sends tones to a channel with `uuid_send_dtmf`.
+struct EslTones {
+    conn: EslConnection,
+    leg: String,
+}
+
+impl ToneSender for EslTones {
+    async fn send_tones(&mut self, tones: &str) -> AppResult<()> {
+        let command = format!("api uuid_send_dtmf {} {tones}", self.leg);
+        self.conn.command(&command, &[]).await.map(|_| ())
+    }
+}
+
+/// `originate {vars}<endpoint> <number> <dialplan> <context>`.
+fn originate_command(config: &EslConfig, channel: &str, phone_number: &str) -> String {
+    let mut vars = vec![
+        format!("origination_uuid={channel}"),
+        format!("originate_timeout={}", config.timeout),
+    ];
+    if let Some(name) = &config.caller_id_name {
+        vars.push(format!("origination_caller_id_name='{name}'"));
+    }
+    if let Some(number) = &config.caller_id_number {
+        vars.push(format!("origination_caller_id_number={number}"));
+    }
+
+    format!(
+        "originate {{{}}}{} {} {} {}",
+        vars.join(","),
+        config.endpoint,
+        phone_number,
+        config.dialplan,
+        config.context
+    )
+}
+
+/// What a hangup cause means for the caller.
+fn originate_failure(cause: &str) -> String {
+    match cause {
+        "NO_ANSWER" | "NO_USER_RESPONSE" | "ORIGINATOR_CANCEL" => "your phone wasn't answered".to_string(),
+        "USER_BUSY" => "your phone is busy".to_string(),
+        "CALL_REJECTED" => "your phone rejected the call".to_string(),
+        "USER_NOT_REGISTERED" | "SUBSCRIBER_ABSENT" => "your phone isn't registered".to_string(),
+        "" => "the call could not be placed".to_string(),
+        cause => format!("the call failed ({})", cause.to_lowercase().replace('_', " ")),
+    }
+}
+
+fn esl_error(message: impl Into<String>) -> Error {
+    Error::DiallerBackend {
+        backend: "ESL",
+        message: message.into(),
+    }
+}
+
+/// One Event Socket message: `Key: Value` lines ending with a blank line, then `Content-Length`
+/// bytes of body if there's any.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EslMessage {
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl EslMessage {
+    /// The first value for `key`, ignoring case.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn content_length(&self) -> usize {
+        self.get("Content-Length").and_then(|len| len.parse().ok()).unwrap_or(0)
+    }
+
+    /// Parses header lines, decoding `%xx` escapes in values when `encoded`, as in plain events.
+    pub fn parse_headers<'a>(lines: impl IntoIterator<Item = &'a str>, encoded: bool) -> Vec<(String, String)> {
+        lines
+            .into_iter()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| {
+                let value = value.trim();
+                let value = if encoded { unescape(value) } else { value.to_string() };
+                (key.trim().to_string(), value)
+            })
+            .collect()
+    }
+
+    /// Parses the body of a `text/event-plain` message, which is an event in the same format.
+    pub fn parse_event(text: &str) -> Self {
+        let (head, body) = text.split_once("\n\n").unwrap_or((text, ""));
+        let headers = Self::parse_headers(head.lines(), true);
+        let mut event = Self {
+            headers,
+            body: String::new(),
+        };
+        let len = event.content_length().min(body.len());
+        event.body = body.get(..len).unwrap_or(body).to_string();
+        event
+    }
+
+    /// Whether this is a command's `+OK` reply, or the reason it was refused. `api` commands
+    /// answer in the body rather than a `Reply-Text` header.
+    fn reply_result(&self) -> Result<(), String> {
+        let reply = self.get("Reply-Text").unwrap_or(self.body.trim());
+        match reply.strip_prefix("-ERR") {
+            Some(reason) => Err(reason.trim().to_string()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An authenticated connection to the FreeSWITCH Event Socket.
+pub struct EslConnection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    /// Events that arrived while waiting for a command's reply.
+    pending: VecDeque<EslMessage>,
+}
+
+impl EslConnection {
+    /// Connects and waits for the `auth/request` greeting.
+    pub async fn connect(host: &str, port: u16) -> AppResult<Self> {
+        let stream = timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port)))
+            .await
+            .map_err(|_| esl_error(format!("timed out connecting to {host}:{port}")))??;
+        let (reader, writer) = stream.into_split();
+        let mut conn = Self {
+            reader: BufReader::new(reader),
+            writer,
+            pending: VecDeque::new(),
+        };
+
+        let greeting = conn.read_message().await?;
+        if greeting.get("Content-Type") != Some("auth/request") {
+            return Err(esl_error(format!("{host}:{port} is not an Event Socket server")));
+        }
+        Ok(conn)
+    }
+
+    pub async fn auth(&mut self, password: &str) -> AppResult<()> {
+        if password.contains(['\r', '\n']) {
+            return Err(esl_error("the password can't contain a line break"));
+        }
+        self.command(&format!("auth {password}"), &[])
+            .await
+            .map(|_| ())
+            .map_err(|_| esl_error("the password was refused"))
+    }
+
+    /// Sends a command with any extra headers and waits for its reply, keeping any events that
+    /// arrive first for [`EslConnection::read_event`]. Line breaks are refused, since they would
+    /// end the command and let the rest be read as commands of their own.
+    pub async fn command(&mut self, command: &str, headers: &[(&str, &str)]) -> AppResult<EslMessage> {
+        let name = command.split(' ').next().unwrap_or_default();
+        if command.contains(['\r', '\n']) {
+            return Err(esl_error(format!("the {name} command can't contain a line break")));
+        }
+        if let Some((key, _)) = headers.iter().find(|(_, value)| value.contains(['\r', '\n'])) {
+            return Err(esl_error(format!("the {key} header can't contain a line break")));
+        }
+        let mut wire = format!("{command}\n");
+        for (key, value) in headers {
+            wire.push_str(&format!("{key}: {value}\n"));
+        }
+        wire.push('\n');
+        debug!("ESL > {name}");
+        self.writer.write_all(wire.as_bytes()).await?;
+        self.writer.flush().await?;
+
+        loop {
+            let message = self.read_message().await?;
+            match message.get("Content-Type") {
+                Some("command/reply" | "api/response") => {
+                    message.reply_result().map_err(esl_error)?;
+                    return Ok(message);
+                }
+                Some("text/event-plain") => self.pending.push_back(EslMessage::parse_event(&message.body)),
+                Some("text/disconnect-notice") => return Err(esl_error("the server closed the connection")),
+                _ => {}
+            }
+        }
+    }
+
+    /// The next event, skipping anything else.
+    pub async fn read_event(&mut self) -> AppResult<EslMessage> {
+        if let Some(event) = self.pending.pop_front() {
+            return Ok(event);
+        }
+        loop {
+            let message = self.read_message().await?;
+            match message.get("Content-Type") {
+                Some("text/event-plain") => return Ok(EslMessage::parse_event(&message.body)),
+                Some("text/disconnect-notice") => return Err(esl_error("the server closed the connection")),
+                _ => {}
+            }
+        }
+    }
+
+    pub async fn read_message(&mut self) -> AppResult<EslMessage> {
+        let mut lines = vec![];
+        loop {
+            let line = self.read_line().await?;
+            if line.is_empty() {
+                if lines.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            lines.push(line);
+        }
+
+        let mut message = EslMessage {
+            headers: EslMessage::parse_headers(lines.iter().map(String::as_str), false),
+            body: String::new(),
+        };
+        let len = message.content_length();
+        if len > 0 {
+            let mut body = vec![0; len];
+            self.reader.read_exact(&mut body).await?;
+            message.body = String::from_utf8_lossy(&body).into_owned();
+        }
+        debug!("ESL < {:?}", message.get("Content-Type"));
+        Ok(message)
+    }
+
+    async fn read_line(&mut self) -> AppResult<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).await? == 0 {
+            return Err(esl_error("the server closed the connection"));
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialler::tests::{call_events, request};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// The server end of an Event Socket connection, answering as a test scripts it.
+    struct MockEsl {
+        reader: BufReader<OwnedReadHalf>,
+        writer: OwnedWriteHalf,
+    }
+
+    impl MockEsl {
+        async fn accept(listener: &TcpListener) -> Self {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, writer) = stream.into_split();
+            let mut mock = Self {
+                reader: BufReader::new(reader),
+                writer,
+            };
+            mock.write("Content-Type: auth/request\n\n").await;
+            mock
+        }
+
+        /// Reads the next command line and its headers, or `None` once the client has hung up.
+        async fn read(&mut self) -> Option<(String, EslMessage)> {
+            let mut lines = vec![];
+            loop {
+                let mut line = String::new();
+                if self.reader.read_line(&mut line).await.unwrap() == 0 {
+                    return None;
+                }
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    let command = lines.remove(0);
+                    let headers = EslMessage::parse_headers(lines.iter().map(String::as_str), false);
+                    return Some((command, EslMessage { headers, body: String::new() }));
+                }
+                lines.push(line);
+            }
+        }
+
+        /// Reads a command, checks it starts with `prefix` and answers it `+OK`.
+        async fn expect(&mut self, prefix: &str) -> (String, EslMessage) {
+            let (command, headers) = self.read().await.expect("the client should send a command");
+            assert!(command.starts_with(prefix), "expected {prefix}, got {command}");
+            self.write("Content-Type: command/reply\nReply-Text: +OK\n\n").await;
+            (command, headers)
+        }
+
+        /// Sends a plain event with `headers`, and `body` if it isn't empty.
+        async fn event(&mut self, headers: &[(&str, &str)], body: &str) {
+            let mut event: String = headers.iter().map(|(key, value)| format!("{key}: {value}\n")).collect();
+            if !body.is_empty() {
+                event.push_str(&format!("Content-Length: {}\n", body.len()));
+            }
+            event.push('\n');
+            event.push_str(body);
+            self.write(&format!("Content-Type: text/event-plain\nContent-Length: {}\n\n{event}", event.len()))
+                .await;
+        }
+
+        async fn write(&mut self, text: &str) {
+            self.writer.write_all(text.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn config(port: u16) -> EslConfig {
+        EslConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            password: "ClueCon".to_string(),
+            endpoint: "user/1001".to_string(),
+            context: "default".to_string(),
+            dialplan: "XML".to_string(),
+            caller_id_name: Some("Dialler".to_string()),
+            caller_id_number: Some("100".to_string()),
+            timeout: 30,
+        }
+    }
+
+    /// Dials `number` against a mock run by `script`, returning the events the call produced.
+    async fn call<F>(number: &str, script: impl FnOnce(MockEsl) -> F) -> Vec<DiallerEvent>
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        call_with(config, number, script).await
+    }
+
+    /// Like [`call`], with the profile `config` makes for the mock's port.
+    async fn call_with<F>(
+        config: impl FnOnce(u16) -> EslConfig,
+        number: &str,
+        script: impl FnOnce(MockEsl) -> F,
+    ) -> Vec<DiallerEvent>
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dialler = EslDialler::new(config(listener.local_addr().unwrap().port()));
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        dialler.dial(request(number), Some(sender)).unwrap();
+
+        let server = tokio::spawn(script(MockEsl::accept(&listener).await));
+        let events = call_events(&mut receiver).await;
+        server.await.unwrap();
+        events
+    }
+
+    /// Takes the client through to its `bgapi originate`, returning the channel and job ids.
+    async fn originated(esl: &mut MockEsl) -> (String, String) {
+        let (auth, _) = esl.expect("auth ").await;
+        assert_eq!(auth, "auth ClueCon");
+        let (events, _) = esl.expect("event ").await;
+        assert_eq!(events, format!("event plain {EVENTS}"));
+        let (filter, _) = esl.expect("filter Unique-ID ").await;
+        let channel = filter.trim_start_matches("filter Unique-ID ").to_string();
+        let (filter, _) = esl.expect("filter Job-UUID ").await;
+        let job = filter.trim_start_matches("filter Job-UUID ").to_string();
+        for id in [&channel, &job] {
+            assert_eq!(Uuid::parse_str(id).unwrap().get_version_num(), 4, "{id} should be a random UUID");
+        }
+
+        let (bgapi, headers) = esl.expect("bgapi ").await;
+        assert_eq!(
+            bgapi,
+            format!(
+                "bgapi originate {{origination_uuid={channel},originate_timeout=30,\
+                 origination_caller_id_name='Dialler',origination_caller_id_number=100}}\
+                 user/1001 0299991234 XML default"
+            )
+        );
+        assert_eq!(headers.get("Job-UUID"), Some(job.as_str()));
+        (channel, job)
+    }
+
+    fn progress(state: CallState) -> DiallerEvent {
+        DiallerEvent::Progress { call_id: 7, state }
+    }
+
+    #[tokio::test]
+    async fn a_bridged_call_is_followed_until_it_hangs_up() {
+        let events = call("02 9999 1234", |mut esl| async move {
+            let (channel, job) = originated(&mut esl).await;
+            esl.event(&[("Event-Name", "BACKGROUND_JOB"), ("Job-UUID", &job)], &format!("+OK {channel}\n")).await;
+            esl.event(&[("Event-Name", "CHANNEL_ANSWER"), ("Unique-ID", &channel)], "").await;
+            // Someone else's call is ignored.
+            esl.event(&[("Event-Name", "CHANNEL_HANGUP_COMPLETE"), ("Unique-ID", "another-call")], "").await;
+            let bridge = [("Event-Name", "CHANNEL_BRIDGE"), ("Unique-ID", &channel), ("Other-Leg-Unique-ID", "leg-b")];
+            esl.event(&bridge, "").await;
+            let hangup = [("Event-Name", "CHANNEL_HANGUP_COMPLETE"), ("Unique-ID", &channel)];
+            esl.event(&[&hangup[..], &[("Hangup-Cause", "NORMAL_CLEARING")]].concat(), "").await;
+            esl.expect("exit").await;
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [
+                progress(CallState::Originating),
+                progress(CallState::Ringing),
+                progress(CallState::Established),
+                DiallerEvent::Ended { call_id: 7, reason: None },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_failed_originate_says_why() {
+        let events = call("0299991234", |mut esl| async move {
+            let (_, job) = originated(&mut esl).await;
+            esl.event(&[("Event-Name", "BACKGROUND_JOB"), ("Job-UUID", &job)], "-ERR USER_BUSY\n").await;
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [
+                progress(CallState::Originating),
+                DiallerEvent::Failed {
+                    call_id: 7,
+                    backend: "ESL",
+                    message: "your phone is busy".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn line_breaks_cannot_smuggle_in_commands() {
+        let with_bad_caller_id = |port| EslConfig {
+            caller_id_name: Some("Dialler'}\n\nbgapi originate user/1002 &park".to_string()),
+            ..config(port)
+        };
+        let events = call_with(with_bad_caller_id, "0299991234", |mut esl| async move {
+            esl.expect("auth ").await;
+            esl.expect("event ").await;
+            esl.expect("filter Unique-ID ").await;
+            esl.expect("filter Job-UUID ").await;
+            assert_eq!(esl.read().await, None, "nothing more should be sent");
+        })
+        .await;
+
+        assert_eq!(
+            events,
+            [DiallerEvent::Failed {
+                call_id: 7,
+                backend: "ESL",
+                message: "the bgapi command can't contain a line break".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn settings_that_would_break_a_command_are_refused() {
+        assert_eq!(config(8021).validate(), Ok(()));
+        let with_name = |name: &str| EslConfig {
+            caller_id_name: Some(name.to_string()),
+            ..config(8021)
+        };
+        assert_eq!(with_name("Smith & Jones").validate(), Ok(()));
+        for name in ["O'Brien", "Smith, Jones", "Dialler}"] {
+            let refused = Err("caller_id_name can't contain ', commas or braces".to_string());
+            assert_eq!(with_name(name).validate(), refused, "{name}");
+        }
+        let password = EslConfig {
+            password: "ClueCon\nexit".to_string(),
+            ..config(8021)
+        };
+        assert_eq!(password.validate(), Err("password can't contain a line break".to_string()));
+    }
+}
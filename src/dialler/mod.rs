@@ -0,0 +1,222 @@
+pub mod ami;
+pub mod baresip;
+pub mod dtmf;
+pub mod esl;
+pub mod process;
+pub mod profile;
+
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use crate::uri::DialNumber;
+use std::fmt::Debug;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::warn;
+
+use profile::{Backend, DiallerProfile};
+
+/// A call for a [`Dialler`] to place.
+#[derive(Debug, Clone)]
+pub struct CallRequest {
+    /// The call's id in the history, which every [`DiallerEvent`] about it carries.
+    pub call_id: u64,
+    pub number: DialNumber,
+    /// What softphone-style backends dial: the digits, or a URI built from the profile's `uri`
+    /// settings. PBX backends dial [`DialNumber::number`] in their dialplan instead. Post-dial
+    /// tones are only included for backends that can't play them themselves.
+    pub address: String,
+    pub name: String,
+}
+
+/// How far a call has got, for backends that can tell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallState {
+    /// The number is being dialled but the far end hasn't answered the request yet.
+    Dialling,
+    /// Waiting for our own phone to pick up before the number is dialled.
+    Originating,
+    Ringing,
+    Established,
+    /// Connected, and waiting for the user to say when to send the rest of the tones.
+    Waiting,
+}
+
+impl CallState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CallState::Dialling => "Dialling",
+            CallState::Originating => "Calling you",
+            CallState::Ringing => "Ringing",
+            CallState::Established => "In call",
+            CallState::Waiting => "Waiting to send tones",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiallerEvent {
+    Progress {
+        call_id: u64,
+        state: CallState,
+    },
+    /// The dialler process for call `call_id` exited. `code` is `None` if it was killed by a
+    /// signal or couldn't be waited on.
+    Exited {
+        call_id: u64,
+        program: String,
+        success: bool,
+        code: Option<i32>,
+        stderr: String,
+    },
+    /// A network backend finished the call normally, with the reason it gave if any.
+    Ended {
+        call_id: u64,
+        reason: Option<String>,
+    },
+    /// A network backend couldn't place the call, or it failed part way.
+    Failed {
+        call_id: u64,
+        backend: &'static str,
+        message: String,
+    },
+}
+
+impl DiallerEvent {
+    /// The [`DiallerEvent::Failed`] for `err`, without repeating the backend's name in the message.
+    pub fn failed(call_id: u64, backend: &'static str, err: Error) -> Self {
+        let message = match err {
+            Error::DiallerBackend { message, .. } => message,
+            err => err.full_message(),
+        };
+        Self::Failed {
+            call_id,
+            backend,
+            message,
+        }
+    }
+}
+
+/// Something that can place calls. Calls are started synchronously, so a dialler that can't even
+/// start reports it straight away; everything after that arrives as [`DiallerEvent`]s.
+pub trait Dialler: Debug {
+    fn dial(&self, request: CallRequest, events: Option<UnboundedSender<Event>>) -> AppResult<CallControl>;
+}
+
+/// Something the app can ask of a call in progress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallCommand {
+    HangUp,
+    /// Go on with the post-dial tones after a wait.
+    SendTones,
+}
+
+/// Lets the app steer a call its dialler is still working on, for backends that can.
+#[derive(Debug, Clone, Default)]
+pub struct CallControl {
+    commands: Option<UnboundedSender<CallCommand>>,
+    hangs_up: bool,
+}
+
+impl CallControl {
+    /// A control for a backend that can take commands, and the end its dialler listens on.
+    /// `hangs_up` says whether it acts on [`CallCommand::HangUp`].
+    pub fn channel(hangs_up: bool) -> (Self, UnboundedReceiver<CallCommand>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let control = Self {
+            commands: Some(sender),
+            hangs_up,
+        };
+        (control, receiver)
+    }
+
+    pub fn can_hang_up(&self) -> bool {
+        self.hangs_up && self.commands.as_ref().is_some_and(|commands| !commands.is_closed())
+    }
+
+    /// Asks the dialler to hang up, returning whether it's listening.
+    pub fn hang_up(&self) -> bool {
+        self.can_hang_up() && self.command(CallCommand::HangUp)
+    }
+
+    /// Tells the dialler to go on with the tones after a wait, returning whether it's listening.
+    pub fn send_tones(&self) -> bool {
+        self.command(CallCommand::SendTones)
+    }
+
+    fn command(&self, command: CallCommand) -> bool {
+        self.commands
+            .as_ref()
+            .is_some_and(|commands| commands.send(command).is_ok())
+    }
+}
+
+/// The dialler for a profile's backend.
+pub fn for_profile(profile: &DiallerProfile) -> Box<dyn Dialler> {
+    match &profile.backend {
+        Backend::Process(config) => Box::new(process::ProcessDialler::new(config.clone())),
+        Backend::Ami(config) => Box::new(ami::AmiDialler::new(config.clone())),
+        Backend::Esl(config) => Box::new(esl::EslDialler::new(config.clone())),
+        Backend::Baresip(config) => Box::new(baresip::BaresipDialler::new(config.clone())),
+    }
+}
+
+/// A call whose dialler is still working on it.
+#[derive(Debug, Clone)]
+pub struct ActiveCall {
+    pub call_id: u64,
+    pub name: String,
+    pub phone_number: String,
+    pub started: Instant,
+    /// The latest progress reported by the backend, if it reports any.
+    pub state: Option<CallState>,
+    pub control: CallControl,
+}
+
+/// Sends `event` to the loop, if there is one listening.
+fn send(events: &Option<UnboundedSender<Event>>, event: DiallerEvent) {
+    if let Some(events) = events {
+        if events.send(Event::Dialler(event)).is_err() {
+            warn!("Dialler event arrived after the app closed");
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// A request to call `number`, with its tones if it has any after a `,` or `;`.
+    pub fn request(number: &str) -> CallRequest {
+        let (number, tones) = match number.find([',', ';']) {
+            Some(index) => (&number[..index], Some(&number[index..])),
+            None => (number, None),
+        };
+        let number = DialNumber::with_tones(number, tones);
+        CallRequest {
+            call_id: 7,
+            address: number.number.clone(),
+            number,
+            name: "Jane Smith".to_string(),
+        }
+    }
+
+    /// The events sent about a call, up to and including the one that finishes it.
+    pub async fn call_events(events: &mut UnboundedReceiver<Event>) -> Vec<DiallerEvent> {
+        let mut received = vec![];
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(10), events.recv())
+                .await
+                .expect("the call should finish")
+                .expect("the dialler should keep the channel open until it finishes");
+            let Event::Dialler(event) = event else {
+                continue;
+            };
+            let finished = !matches!(event, DiallerEvent::Progress { .. });
+            received.push(event);
+            if finished {
+                return received;
+            }
+        }
+    }
+}
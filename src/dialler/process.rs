@@ -0,0 +1,80 @@
+use crate::dialler::{send, CallControl, CallRequest, Dialler, DiallerEvent};
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use serde::Deserialize;
+use std::process::Stdio;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::info;
+
+/// How much of a dialler's stderr is kept, from the end, for the call history and error messages.
+const MAX_STDERR: usize = 1000;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProcessConfig {
+    /// The softphone or script to run, given the number as its only argument.
+    pub program: String,
+}
+
+/// Places calls by running a local program, such as a softphone, on the number.
+#[derive(Debug)]
+pub struct ProcessDialler {
+    config: ProcessConfig,
+}
+
+impl ProcessDialler {
+    pub fn new(config: ProcessConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Dialler for ProcessDialler {
+    /// Starts the program and watches it on the tokio runtime, reaping it when it exits and
+    /// sending a [`DiallerEvent::Exited`] with its status and stderr.
+    fn dial(&self, request: CallRequest, events: Option<UnboundedSender<Event>>) -> AppResult<CallControl> {
+        let program = self.config.program.clone();
+        let call_id = request.call_id;
+        let child = tokio::process::Command::new(&program)
+            .arg(&request.address)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| Error::DiallerSpawn {
+                program: program.clone(),
+                source,
+            })?;
+        info!("Started dialler for call {call_id} with pid {:?}", child.id());
+
+        tokio::spawn(async move {
+            let event = match child.wait_with_output().await {
+                Ok(output) => DiallerEvent::Exited {
+                    call_id,
+                    program,
+                    success: output.status.success(),
+                    code: output.status.code(),
+                    stderr: tail(String::from_utf8_lossy(&output.stderr).trim()),
+                },
+                Err(err) => DiallerEvent::Exited {
+                    call_id,
+                    program,
+                    success: false,
+                    code: None,
+                    stderr: format!("could not wait for the dialler: {err}"),
+                },
+            };
+            info!("Dialler for call {call_id} finished: {event:?}");
+            send(&events, event);
+        });
+
+        Ok(CallControl::default())
+    }
+}
+
+/// The last [`MAX_STDERR`] bytes of `text`, on a character boundary.
+pub(crate) fn tail(text: &str) -> String {
+    let mut start = text.len().saturating_sub(MAX_STDERR);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    text[start..].to_string()
+}
@@ -0,0 +1,183 @@
+use crate::dialler::ami::AmiConfig;
+use crate::dialler::baresip::BaresipConfig;
+use crate::dialler::esl::EslConfig;
+use crate::dialler::process::ProcessConfig;
+use crate::error::{AppResult, Error};
+use crate::uri::{DialNumber, UriConfig};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// How a profile places calls, chosen by its `backend` key.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum Backend {
+    Process(ProcessConfig),
+    Ami(AmiConfig),
+    Esl(EslConfig),
+    Baresip(BaresipConfig),
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Process(_) => "process",
+            Backend::Ami(_) => "ami",
+            Backend::Esl(_) => "esl",
+            Backend::Baresip(_) => "baresip",
+        }
+    }
+
+    /// Whether the backend sends post-dial tones itself once the call is up, rather than having
+    /// them in what it dials.
+    pub fn plays_tones(&self) -> bool {
+        !matches!(self, Backend::Process(_))
+    }
+
+    /// Checks settings the backend can't use, beyond what reading the file already checked.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Backend::Esl(config) => config.validate(),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A named way of placing calls, e.g. a local softphone or the office PBX.
+#[derive(Debug, Clone)]
+pub struct DiallerProfile {
+    pub name: String,
+    pub backend: Backend,
+    /// How numbers are written for the dialler, if not as bare digits.
+    pub uri: Option<UriConfig>,
+}
+
+impl DiallerProfile {
+    /// What the backend is given to dial for `number`: its URI if the profile has `uri` settings,
+    /// or else the dial string. The tones are left out for backends that play them themselves.
+    pub fn address(&self, number: &DialNumber) -> AppResult<String> {
+        let dialled = if self.backend.plays_tones() { number.without_tones() } else { number.clone() };
+        match &self.uri {
+            Some(uri) => uri.build(&dialled),
+            None => Ok(dialled.dial_string()),
+        }
+    }
+}
+
+/// A `[profiles.<name>]` table: the backend's settings, with an optional `uri` table beside them.
+#[derive(Debug, Deserialize)]
+struct ProfileEntry {
+    #[serde(flatten)]
+    backend: Backend,
+    uri: Option<UriConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfilesFile {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, ProfileEntry>,
+}
+
+/// The profiles from `profiles.toml`, with the one to use by default.
+#[derive(Debug, Default)]
+pub struct Profiles {
+    pub profiles: Vec<DiallerProfile>,
+    pub default: Option<String>,
+}
+
+impl Profiles {
+    /// Reads `path`, returning no profiles if it doesn't exist.
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let file: ProfilesFile = toml::from_str(&text)
+            .map_err(|err| Error::ConfigError(format!("{}: {}", path.display(), err.message())))?;
+
+        let mut profiles = vec![];
+        for (name, entry) in file.profiles {
+            entry
+                .backend
+                .validate()
+                .map_err(|message| Error::ConfigError(format!("{}: profile {name}: {message}", path.display())))?;
+            profiles.push(DiallerProfile {
+                name,
+                backend: entry.backend,
+                uri: entry.uri,
+            });
+        }
+
+        Ok(Self {
+            profiles,
+            default: file.default_profile,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(toml: &str) -> DiallerProfile {
+        let entry: ProfileEntry = toml::from_str(toml).unwrap();
+        DiallerProfile {
+            name: "test".to_string(),
+            backend: entry.backend,
+            uri: entry.uri,
+        }
+    }
+
+    const PROCESS: &str = r#"
+        backend = "process"
+        program = "softphone"
+    "#;
+    const BARESIP: &str = r#"
+        backend = "baresip"
+    "#;
+    const AMI: &str = r#"
+        backend = "ami"
+        host = "pbx.example.com"
+        username = "dialler"
+        secret = "s3cret"
+        channel = "PJSIP/1001"
+        context = "from-internal"
+    "#;
+    const TEL: &str = r#"
+        [uri]
+        scheme = "tel"
+        domain = "pbx.example.com"
+    "#;
+    const SIP: &str = r#"
+        [uri]
+        scheme = "sip"
+        domain = "pbx.example.com"
+    "#;
+
+    fn number() -> DialNumber {
+        DialNumber::with_tones("02 9999 1234", Some(",,2;345#"))
+    }
+
+    #[test]
+    fn a_process_is_given_the_tones_in_its_dial_string() {
+        assert_eq!(profile(PROCESS).address(&number()).unwrap(), "0299991234,,2;345#");
+    }
+
+    #[test]
+    fn a_process_is_given_the_tones_as_postd_in_a_uri() {
+        let tel = profile(&format!("{PROCESS}{TEL}"));
+        assert_eq!(tel.address(&number()).unwrap(), "tel:0299991234;phone-context=pbx.example.com;postd=pp2w345%23");
+        let sip = profile(&format!("{PROCESS}{SIP}"));
+        assert_eq!(sip.address(&number()).unwrap(), "sip:0299991234;postd=pp2w345%23@pbx.example.com");
+    }
+
+    #[test]
+    fn backends_that_play_tones_are_given_the_number_alone() {
+        assert_eq!(profile(BARESIP).address(&number()).unwrap(), "0299991234");
+        assert_eq!(profile(&format!("{BARESIP}{SIP}")).address(&number()).unwrap(), "sip:0299991234@pbx.example.com");
+        assert_eq!(profile(AMI).address(&number()).unwrap(), "0299991234");
+    }
+}
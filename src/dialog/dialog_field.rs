@@ -1,51 +1,535 @@
 use crate::app::TypingMode;
+use crate::dialog::validator::Validator;
+use crate::editing::EditContext;
 use crate::error::AppResult;
-use crate::line_buffer::LineBuffer;
-use crossterm::event::KeyEvent;
+use crate::line_buffer::{LineBuffer, Viewport};
+use chrono::{Days, NaiveDate};
+use crossterm::event::{KeyCode, KeyEvent};
+use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The kind of input a [`DialogField`] accepts, which decides how it is edited and drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    Text,
+    /// Free text over several rows. Enter starts a new line rather than submitting.
+    MultiLine { rows: u16 },
+    /// One of a fixed set of options, changed with Left/Right or Space.
+    Select { options: Vec<String> },
+    Checkbox,
+    /// Phone numbers separated by commas.
+    NumberList,
+    /// A `YYYY-MM-DD` date. PageUp/PageDown step it by a day.
+    Date,
+}
+
+#[derive(Debug)]
+enum FieldInput {
+    Line(LineBuffer),
+    Lines {
+        lines: Vec<LineBuffer>,
+        row: usize,
+        scroll: usize,
+    },
+    Select(usize),
+    Checkbox(bool),
+}
+
 #[derive(Debug)]
 pub struct DialogField {
     pub name: String,
     pub label: String,
-    buffer: LineBuffer,
+    pub kind: FieldKind,
+    aliases: Vec<String>,
+    input: FieldInput,
+    validators: Vec<Validator>,
+    pub error: Option<String>,
+    pub needs_review: bool,
 }
 
 impl Display for DialogField {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.buffer)
+        write!(f, "{}", self.get_value())
     }
 }
 
 impl DialogField {
     pub fn new(name: impl Into<String>, label: impl Into<String>) -> Self {
+        Self::with_kind(name, label, FieldKind::Text)
+    }
+
+    pub fn multi_line(name: impl Into<String>, label: impl Into<String>, rows: u16) -> Self {
+        Self::with_kind(name, label, FieldKind::MultiLine { rows: rows.max(1) })
+    }
+
+    pub fn select(name: impl Into<String>, label: impl Into<String>, options: &[&str]) -> Self {
+        let options = options.iter().map(|option| option.to_string()).collect();
+        Self::with_kind(name, label, FieldKind::Select { options })
+    }
+
+    pub fn checkbox(name: impl Into<String>, label: impl Into<String>) -> Self {
+        Self::with_kind(name, label, FieldKind::Checkbox)
+    }
+
+    pub fn number_list(name: impl Into<String>, label: impl Into<String>) -> Self {
+        Self::with_kind(name, label, FieldKind::NumberList).validator(Validator::PhoneNumberList)
+    }
+
+    pub fn date(name: impl Into<String>, label: impl Into<String>) -> Self {
+        Self::with_kind(name, label, FieldKind::Date)
+            .max_length(10)
+            .validator(Validator::Date)
+    }
+
+    fn with_kind(name: impl Into<String>, label: impl Into<String>, kind: FieldKind) -> Self {
+        let input = match kind {
+            FieldKind::Text | FieldKind::NumberList | FieldKind::Date => FieldInput::Line(LineBuffer::default()),
+            FieldKind::MultiLine { .. } => FieldInput::Lines {
+                lines: vec![LineBuffer::default()],
+                row: 0,
+                scroll: 0,
+            },
+            FieldKind::Select { .. } => FieldInput::Select(0),
+            FieldKind::Checkbox => FieldInput::Checkbox(false),
+        };
+
         Self {
             name: name.into(),
             label: label.into(),
-            buffer: Default::default(),
+            kind,
+            aliases: vec![],
+            input,
+            validators: vec![],
+            error: None,
+            needs_review: false,
         }
     }
 
+    pub fn max_length(mut self, max: usize) -> Self {
+        match &mut self.input {
+            FieldInput::Line(buffer) => buffer.set_max_length(Some(max)),
+            FieldInput::Lines { lines, .. } => lines
+                .iter_mut()
+                .for_each(|line| line.set_max_length(Some(max))),
+            FieldInput::Select(_) | FieldInput::Checkbox(_) => return self,
+        }
+        self.validator(Validator::MaxLength(max))
+    }
+
+    pub fn validator(mut self, validator: Validator) -> Self {
+        self.validators.push(validator);
+        self
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.validators
+            .iter()
+            .any(|validator| matches!(validator, Validator::Unique))
+    }
+
+    /// Runs the local validators, storing the first failure as the field's error.
+    pub fn validate(&mut self) -> bool {
+        let value = self.get_value();
+        self.error = self
+            .validators
+            .iter()
+            .find_map(|validator| validator.validate(&value).err());
+        self.error.is_none()
+    }
+
+    /// Extra labels that identify this field in pasted "Label: value" text.
+    pub fn aliases(mut self, aliases: &[&str]) -> Self {
+        self.aliases = aliases.iter().map(|alias| alias.to_string()).collect();
+        self
+    }
+
+    /// Whether a pasted label refers to this field, ignoring case.
+    pub fn matches_label(&self, label: &str) -> bool {
+        let label = label.trim().to_lowercase();
+        label == self.name.to_lowercase()
+            || label == self.label.to_lowercase()
+            || self.aliases.contains(&label)
+    }
+
+    /// How many rows the input needs, not counting the error line beneath it.
+    pub fn height(&self) -> u16 {
+        match self.kind {
+            FieldKind::MultiLine { rows } => rows,
+            _ => 1,
+        }
+    }
+
+    /// Whether Enter belongs to the field rather than submitting the modal.
+    pub fn accepts_enter(&self) -> bool {
+        matches!(self.kind, FieldKind::MultiLine { .. })
+    }
+
+    /// Whether Up and Down move within the field rather than between fields.
+    pub fn accepts_vertical_movement(&self) -> bool {
+        matches!(self.input, FieldInput::Lines { .. })
+    }
+
     pub fn reset(&mut self) {
-        self.buffer.reset_cursor();
-        self.buffer.clear();
+        match &mut self.input {
+            FieldInput::Line(buffer) => {
+                buffer.reset_cursor();
+                buffer.clear();
+            }
+            FieldInput::Lines { lines, row, scroll } => {
+                let max = lines[0].get_max_length();
+                let mut line = LineBuffer::default();
+                line.set_max_length(max);
+                *lines = vec![line];
+                *row = 0;
+                *scroll = 0;
+            }
+            FieldInput::Select(selected) => *selected = 0,
+            FieldInput::Checkbox(checked) => *checked = false,
+        }
+        self.error = None;
+        self.needs_review = false;
     }
 
-    pub fn handle_key_events(&mut self, event: KeyEvent, type_mode: TypingMode) -> AppResult<()> {
-        self.buffer.handle_key_events(event, type_mode)?;
+    pub fn handle_key_events(
+        &mut self,
+        event: KeyEvent,
+        type_mode: TypingMode,
+        context: &mut EditContext,
+    ) -> AppResult<()> {
+        let option_count = match &self.kind {
+            FieldKind::Select { options } => options.len(),
+            _ => 0,
+        };
+
+        let mut refused = false;
+        match &mut self.input {
+            FieldInput::Line(buffer) if self.kind == FieldKind::Date => match event.code {
+                KeyCode::PageUp | KeyCode::PageDown => {
+                    let date = NaiveDate::parse_from_str(buffer.get_value(), DATE_FORMAT)
+                        .unwrap_or_else(|_| chrono::Local::now().date_naive());
+                    let date = if event.code == KeyCode::PageUp {
+                        date.checked_add_days(Days::new(1))
+                    } else {
+                        date.checked_sub_days(Days::new(1))
+                    };
+                    if let Some(date) = date {
+                        buffer.set_value(date.format(DATE_FORMAT).to_string());
+                        buffer.set_cursor_position(usize::MAX);
+                    }
+                }
+                _ => buffer.handle_key_events(event, type_mode, context)?,
+            },
+            FieldInput::Line(buffer) => buffer.handle_key_events(event, type_mode, context)?,
+            FieldInput::Lines { lines, row, .. } => {
+                refused = !Self::handle_lines_key_event(lines, row, event, type_mode, context)?
+            }
+            FieldInput::Select(selected) if option_count > 0 => match event.code {
+                KeyCode::Left => *selected = (*selected + option_count - 1) % option_count,
+                KeyCode::Right | KeyCode::Char(' ') => *selected = (*selected + 1) % option_count,
+                _ => {}
+            },
+            FieldInput::Select(_) => {}
+            FieldInput::Checkbox(checked) => {
+                if event.code == KeyCode::Char(' ') {
+                    *checked = !*checked;
+                }
+            }
+        }
+
+        self.needs_review = false;
+        if self.error.is_some() {
+            self.validate();
+        }
+        if refused {
+            self.error = Some("Too long to join onto one line".to_string());
+        }
 
         Ok(())
     }
 
+    /// Returns `false` if the key would have joined two lines into one over the maximum length, which
+    /// would lose the end of it, so it was refused.
+    fn handle_lines_key_event(
+        lines: &mut Vec<LineBuffer>,
+        row: &mut usize,
+        event: KeyEvent,
+        type_mode: TypingMode,
+        context: &mut EditContext,
+    ) -> AppResult<bool> {
+        let column = lines[*row].get_cursor_position();
+        let at_line_end = column >= lines[*row].get_value().chars().count();
+        let joining = match event.code {
+            KeyCode::Backspace if column == 0 && *row > 0 => Some(*row - 1),
+            KeyCode::Delete if at_line_end && *row + 1 < lines.len() => Some(*row),
+            _ => None,
+        };
+        if joining.is_some_and(|first| !Self::fit_on_one_line(&lines[first..=first + 1])) {
+            return Ok(false);
+        }
+
+        match (event.modifiers, event.code) {
+            // Any Enter the modal lets through, e.g. Shift+Enter, so the line buffer never clears
+            // the line the way it does for single-line fields.
+            (_, KeyCode::Enter) => Self::break_line(lines, row),
+            (_, KeyCode::Backspace) if column == 0 && *row > 0 => {
+                let line = lines.remove(*row);
+                *row -= 1;
+                let previous = &mut lines[*row];
+                let join_at = previous.get_value().chars().count();
+                previous.append(line.get_value());
+                previous.set_cursor_position(join_at);
+            }
+            (_, KeyCode::Delete) if at_line_end && *row + 1 < lines.len() => {
+                let next = lines.remove(*row + 1);
+                lines[*row].append(next.get_value());
+            }
+            (_, KeyCode::Up) if *row > 0 => {
+                *row -= 1;
+                lines[*row].set_cursor_position(column);
+            }
+            (_, KeyCode::Down) if *row + 1 < lines.len() => {
+                *row += 1;
+                lines[*row].set_cursor_position(column);
+            }
+            (_, KeyCode::Up | KeyCode::Down) => {}
+            _ => lines[*row].handle_key_events(event, type_mode, context)?,
+        }
+
+        Ok(true)
+    }
+
+    /// Whether `lines` joined together are within the maximum length.
+    fn fit_on_one_line(lines: &[LineBuffer]) -> bool {
+        let length: usize = lines.iter().map(|line| line.get_value().chars().count()).sum();
+        lines[0].get_max_length().is_none_or(|max| length <= max)
+    }
+
+    /// Moves the text after the cursor onto a new line below, and the cursor with it.
+    fn break_line(lines: &mut Vec<LineBuffer>, row: &mut usize) {
+        let tail = lines[*row].split_off_at_cursor();
+        let mut line = LineBuffer::default();
+        line.set_max_length(lines[*row].get_max_length());
+        line.set_value(tail);
+        lines.insert(*row + 1, line);
+        *row += 1;
+    }
+
     pub fn set_value(&mut self, value: impl Into<String>) {
-        self.buffer.set_value(value.into());
+        let value = value.into();
+        match (&mut self.input, &self.kind) {
+            (FieldInput::Line(buffer), _) => buffer.set_value(value),
+            (FieldInput::Lines { lines, row, scroll }, _) => {
+                let max = lines[0].get_max_length();
+                *lines = value
+                    .split('\n')
+                    .map(|text| {
+                        let mut line = LineBuffer::default();
+                        line.set_max_length(max);
+                        line.set_value(text);
+                        line
+                    })
+                    .collect();
+                *row = 0;
+                *scroll = 0;
+            }
+            (FieldInput::Select(selected), FieldKind::Select { options }) => {
+                if let Some(index) = options.iter().position(|option| option.eq_ignore_ascii_case(&value)) {
+                    *selected = index;
+                }
+            }
+            (FieldInput::Select(_), _) => {}
+            (FieldInput::Checkbox(checked), _) => {
+                *checked = matches!(value.to_lowercase().as_str(), "true" | "yes" | "1" | "x");
+            }
+        }
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        match &mut self.input {
+            FieldInput::Line(buffer) => buffer.paste(text),
+            FieldInput::Lines { lines, row, .. } => {
+                for (index, text) in text.lines().enumerate() {
+                    if index > 0 {
+                        Self::break_line(lines, row);
+                    }
+                    lines[*row].paste(text);
+                }
+            }
+            FieldInput::Select(_) | FieldInput::Checkbox(_) => self.set_value(text.trim()),
+        }
+        if self.error.is_some() {
+            self.validate();
+        }
+    }
+
+    /// The field's value as text: lines are joined with `\n`, a select gives
+    /// the chosen option and a checkbox gives `true` or `false`.
+    pub fn get_value(&self) -> Cow<'_, str> {
+        match (&self.input, &self.kind) {
+            (FieldInput::Line(buffer), _) => Cow::Borrowed(buffer.get_value()),
+            (FieldInput::Lines { lines, .. }, _) => Cow::Owned(
+                lines
+                    .iter()
+                    .map(LineBuffer::get_value)
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            (FieldInput::Select(selected), FieldKind::Select { options }) => {
+                Cow::Borrowed(options.get(*selected).map(String::as_str).unwrap_or_default())
+            }
+            (FieldInput::Select(_), _) => Cow::Borrowed(""),
+            (FieldInput::Checkbox(checked), _) => Cow::Borrowed(if *checked { "true" } else { "false" }),
+        }
+    }
+
+    pub fn is_checked(&self) -> bool {
+        matches!(self.input, FieldInput::Checkbox(true))
+    }
+
+    pub fn get_selected_index(&self) -> Option<usize> {
+        match self.input {
+            FieldInput::Select(selected) => Some(selected),
+            _ => None,
+        }
+    }
+
+    /// The numbers in a [`FieldKind::NumberList`], trimmed and without empty entries.
+    pub fn get_numbers(&self) -> Vec<String> {
+        split_number_list(&self.get_value())
+    }
+
+    pub fn get_date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.get_value().trim(), DATE_FORMAT).ok()
+    }
+
+    /// Where the cursor sits relative to the top-left of the input area, if the field shows one.
+    pub fn get_cursor_pos(&self) -> Option<(u16, u16)> {
+        match &self.input {
+            FieldInput::Line(buffer) => Some((buffer.viewport().cursor as u16, 0)),
+            FieldInput::Lines { lines, row, scroll } => {
+                Some((lines[*row].viewport().cursor as u16, (*row - *scroll) as u16))
+            }
+            FieldInput::Select(_) | FieldInput::Checkbox(_) => None,
+        }
+    }
+
+    /// Sets the size of the input area, scrolling so the cursor stays visible.
+    pub fn set_display_size(&mut self, width: u16, height: u16) {
+        match &mut self.input {
+            FieldInput::Line(buffer) => buffer.set_display_width(width as usize),
+            FieldInput::Lines { lines, row, scroll } => {
+                for line in lines.iter_mut() {
+                    line.set_display_width(width as usize);
+                }
+                let height = height.max(1) as usize;
+                if *row < *scroll {
+                    *scroll = *row;
+                } else if *row >= *scroll + height {
+                    *scroll = *row + 1 - height;
+                }
+            }
+            FieldInput::Select(_) | FieldInput::Checkbox(_) => {}
+        }
     }
 
-    pub fn get_value(&self) -> &str {
-        self.buffer.get_value()
+    /// The visible rows of a text field.
+    pub fn viewports(&self) -> Vec<Viewport> {
+        match &self.input {
+            FieldInput::Line(buffer) => vec![buffer.viewport()],
+            FieldInput::Lines { lines, scroll, .. } => {
+                lines.iter().skip(*scroll).map(LineBuffer::viewport).collect()
+            }
+            FieldInput::Select(_) | FieldInput::Checkbox(_) => vec![],
+        }
     }
+}
+
+pub fn split_number_list(value: &str) -> Vec<String> {
+    value
+        .split([',', ';'])
+        .map(str::trim)
+        .filter(|number| !number.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
 
-    pub fn get_cursor_pos(&self) -> u16 {
-        self.buffer.get_cursor_position() as u16
+    fn type_into(field: &mut DialogField, modifiers: KeyModifiers, code: KeyCode) {
+        field
+            .handle_key_events(KeyEvent::new(code, modifiers), TypingMode::Insert, &mut EditContext::default())
+            .unwrap();
+    }
+
+    fn type_text(field: &mut DialogField, text: &str) {
+        for c in text.chars() {
+            type_into(field, KeyModifiers::NONE, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn enter_with_any_modifier_starts_a_new_line() {
+        for modifiers in [KeyModifiers::NONE, KeyModifiers::SHIFT, KeyModifiers::ALT] {
+            let mut field = DialogField::multi_line("body", "Text", 4);
+            type_text(&mut field, "hello");
+            type_into(&mut field, modifiers, KeyCode::Enter);
+            type_text(&mut field, "world");
+            assert_eq!(field.get_value(), "hello\nworld", "{modifiers:?}");
+        }
+    }
+
+    #[test]
+    fn enter_splits_the_line_at_the_cursor() {
+        let mut field = DialogField::multi_line("body", "Text", 4);
+        type_text(&mut field, "helloworld");
+        for _ in 0..5 {
+            type_into(&mut field, KeyModifiers::NONE, KeyCode::Left);
+        }
+        type_into(&mut field, KeyModifiers::SHIFT, KeyCode::Enter);
+        assert_eq!(field.get_value(), "hello\nworld");
+
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Backspace);
+        assert_eq!(field.get_value(), "helloworld");
+    }
+
+    #[test]
+    fn pasted_lines_stay_separate() {
+        let mut field = DialogField::multi_line("body", "Text", 4);
+        type_text(&mut field, "a");
+        field.paste("b\nc");
+        assert_eq!(field.get_value(), "ab\nc");
+    }
+
+    #[test]
+    fn lines_too_long_to_join_are_left_as_they_are() {
+        let mut field = DialogField::multi_line("body", "Text", 4).max_length(10);
+        type_text(&mut field, "abcdefgh");
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Enter);
+        type_text(&mut field, "ijklmnop");
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Home);
+
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Backspace);
+        assert_eq!(field.get_value(), "abcdefgh\nijklmnop");
+        assert_eq!(field.error.as_deref(), Some("Too long to join onto one line"));
+
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Up);
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::End);
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Delete);
+        assert_eq!(field.get_value(), "abcdefgh\nijklmnop");
+
+        // Once it fits, it joins.
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Down);
+        for _ in 0..6 {
+            type_into(&mut field, KeyModifiers::NONE, KeyCode::Backspace);
+        }
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Home);
+        type_into(&mut field, KeyModifiers::NONE, KeyCode::Backspace);
+        assert_eq!(field.get_value(), "abcdefghij");
+        assert_eq!(field.error, None);
     }
 }
@@ -1,2 +1,4 @@
 pub mod dialog_field;
 pub mod modal;
+pub mod popup;
+pub mod validator;
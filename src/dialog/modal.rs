@@ -1,17 +1,66 @@
 use crate::app::TypingMode;
+use crate::dialler::dtmf;
 use crate::dialog::dialog_field::DialogField;
+use crate::dialog::validator::{UniqueCheck, Validator};
+use crate::editing::EditContext;
+use crate::line_buffer::sanitise;
 
 use crate::error::AppResult;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use tracing::info;
 
+const DEFAULT_INPUT_WIDTH: u16 = 36;
+
+/// Choices for the contact form's speed-dial field; the index is the slot.
+pub const SPEED_DIAL_OPTIONS: [&str; 10] = ["None", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+/// The keys [`Modal::handle_key_events`] handles itself, for the help screen.
+pub const MODAL_KEYS: &[(&str, &str)] = &[
+    ("Tab / Down", "Next field"),
+    ("Shift+Tab / Up", "Previous field"),
+    ("Enter / Ctrl+S", "Save (Enter adds a new line in multi-line fields)"),
+    ("Space", "Toggle a checkbox or step through a choice"),
+    ("Left / Right", "Change a choice"),
+    ("Esc", "Cancel"),
+];
+
+/// A form made of [`DialogField`]s, built declaratively and drawn by
+/// [`Renderer::render_modal`](crate::renderer::Renderer::render_modal).
 #[derive(Default, Debug)]
 pub struct Modal {
+    pub title: String,
     pub focused_index: usize,
     pub fields: Vec<DialogField>,
+    pub error: Option<String>,
+    /// Shown under the fields when there's no error, e.g. how long a message is.
+    pub note: Option<String>,
+    pub input_width: u16,
+    /// The value each [`Validator::Unique`] field last had checked in the background, by name.
+    unique_checked: HashMap<String, String>,
 }
 
 impl Modal {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            input_width: DEFAULT_INPUT_WIDTH,
+            ..Default::default()
+        }
+    }
+
+    pub fn field(mut self, field: DialogField) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Sets how many columns the inputs get, which decides the modal's width.
+    pub fn input_width(mut self, width: u16) -> Self {
+        self.input_width = width;
+        self
+    }
+
     pub fn get_max_label(&self) -> u16 {
         self.fields
             .iter()
@@ -19,18 +68,136 @@ impl Modal {
             .max()
             .unwrap_or(0) as u16
     }
+
+    /// The width and height the modal needs to show every field, including borders.
+    pub fn get_size(&self) -> (u16, u16) {
+        let width = self.get_max_label() + 2 + self.input_width + 4;
+        let fields: u16 = self.fields.iter().map(|field| field.height() + 1).sum();
+        let inner_width = width.saturating_sub(4).max(1) as usize;
+        let note: usize = self
+            .note
+            .iter()
+            .flat_map(|note| note.lines())
+            .map(|line| line.chars().count().div_ceil(inner_width).max(1))
+            .sum();
+        (width, fields + 6 + (note as u16).saturating_sub(3))
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<&DialogField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn get_field_mut(&mut self, name: &str) -> Option<&mut DialogField> {
+        self.fields.iter_mut().find(|field| field.name == name)
+    }
+
+    /// The value of the named field, or an empty string if there is no such field.
+    pub fn value(&self, name: &str) -> Cow<'_, str> {
+        self.get_field(name)
+            .map(DialogField::get_value)
+            .unwrap_or_default()
+    }
+
+    /// Like [`Modal::value`], but `None` when the value is blank.
+    pub fn optional_value(&self, name: &str) -> Option<String> {
+        let value = self.value(name);
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+
+    pub fn set_value(&mut self, name: &str, value: impl Into<String>) {
+        if let Some(field) = self.get_field_mut(name) {
+            field.set_value(value);
+        }
+    }
+
     pub fn add_contact() -> Self {
-        let mut modal = Modal::default();
-        modal.fields.push(DialogField::new("first", "First Name"));
-        modal.fields.push(DialogField::new("last", "Last Name"));
-        modal.fields.push(DialogField::new("phone", "Phone Number"));
-        modal.fields.push(DialogField::new("company", "Company"));
+        Modal::new("Add Contact")
+            .field(
+                DialogField::new("first", "First Name")
+                    .max_length(50)
+                    .aliases(&["first", "given name"]),
+            )
+            .field(
+                DialogField::new("last", "Last Name")
+                    .max_length(50)
+                    .aliases(&["last", "surname", "family name"]),
+            )
+            .field(
+                DialogField::new("phone", "Phone Number")
+                    .max_length(30)
+                    .validator(Validator::Required)
+                    .validator(Validator::PhoneNumber)
+                    .validator(Validator::Unique)
+                    .aliases(&["phone", "ph", "tel", "telephone", "mobile", "mob", "cell"]),
+            )
+            .field(
+                DialogField::new("company", "Company")
+                    .max_length(100)
+                    .aliases(&["organisation", "organization", "business", "org"]),
+            )
+            .field(
+                DialogField::new("email", "Email")
+                    .max_length(100)
+                    .validator(Validator::email())
+                    .aliases(&["e", "e-mail", "mail"]),
+            )
+            .field(
+                DialogField::new("dtmf", "Tones After Dialling")
+                    .max_length(dtmf::MAX_LENGTH)
+                    .validator(Validator::Dtmf)
+                    .aliases(&["dtmf", "tones", "post-dial"]),
+            )
+            .field(DialogField::checkbox("favourite", "Favourite"))
+            .field(
+                DialogField::select("speed_dial", "Speed Dial", &SPEED_DIAL_OPTIONS)
+                    .validator(Validator::Unique),
+            )
+    }
 
-        modal
+    /// Enter starts a new line in the message, so Ctrl+S sends it. "To" takes several numbers,
+    /// separated by commas.
+    pub fn compose_sms() -> Self {
+        Modal::new("Send SMS")
+            .input_width(50)
+            .field(
+                DialogField::number_list("to", "To")
+                    .max_length(200)
+                    .validator(Validator::Required),
+            )
+            .field(DialogField::multi_line("message", "Message", 6).validator(Validator::Required))
+    }
+
+    /// Enter starts a new line in the text, so Ctrl+S saves it.
+    pub fn add_template() -> Self {
+        Modal::new("Add Template")
+            .input_width(50)
+            .field(
+                DialogField::new("name", "Name")
+                    .max_length(50)
+                    .validator(Validator::Required)
+                    .validator(Validator::Unique),
+            )
+            .field(DialogField::multi_line("body", "Text", 6).validator(Validator::Required))
+    }
+
+    pub fn edit_template() -> Self {
+        Modal {
+            title: "Edit Template".to_string(),
+            ..Self::add_template()
+        }
+    }
+
+    pub fn edit_contact() -> Self {
+        Modal {
+            title: "Edit Contact".to_string(),
+            ..Self::add_contact()
+        }
     }
 
     pub fn reset(&mut self) {
         self.focused_index = 0;
+        self.error = None;
         for field in self.fields.iter_mut() {
             field.reset();
         }
@@ -40,8 +207,16 @@ impl Modal {
         &mut self,
         event: KeyEvent,
         type_mode: TypingMode,
+        context: &mut EditContext,
     ) -> AppResult<DialogResult> {
         let mut result = DialogResult::None;
+        let focused = &self.fields[self.focused_index];
+        let submit = match (event.modifiers, event.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('s')) => true,
+            (_, KeyCode::Enter) => !focused.accepts_enter(),
+            _ => false,
+        };
+        let vertical = focused.accepts_vertical_movement();
 
         match event.code {
             KeyCode::Tab => {
@@ -50,19 +225,136 @@ impl Modal {
             KeyCode::BackTab => {
                 self.focus_previous();
             }
-            KeyCode::Enter => {
-                info!("Setting Dialog OK");
-                result = DialogResult::Ok;
+            KeyCode::Down if !vertical => self.focus_next(),
+            KeyCode::Up if !vertical => self.focus_previous(),
+            _ if submit => {
+                if self.validate() {
+                    info!("Setting Dialog OK");
+                    result = DialogResult::Ok;
+                }
             }
             KeyCode::Esc => result = DialogResult::Cancel,
             _ => {
-                self.fields[self.focused_index].handle_key_events(event, type_mode)?;
+                self.error = None;
+                self.fields[self.focused_index].handle_key_events(event, type_mode, context)?;
             }
         }
 
         Ok(result)
     }
 
+    /// Validates every field, focusing the first one that fails.
+    pub fn validate(&mut self) -> bool {
+        let mut valid = true;
+        for field in self.fields.iter_mut() {
+            valid &= field.validate();
+        }
+
+        if let Some(index) = self.fields.iter().position(|field| field.error.is_some()) {
+            self.focused_index = index;
+        }
+        valid
+    }
+
+    /// Runs the [`Validator::Unique`] checks, which need the database.
+    /// `exists` is given a field's name and trimmed value, and reports whether the value is taken.
+    pub fn validate_unique(
+        &mut self,
+        exists: impl Fn(&str, &str) -> AppResult<bool>,
+    ) -> AppResult<bool> {
+        for (index, field) in self.fields.iter_mut().enumerate() {
+            if field.is_unique() && exists(&field.name, field.get_value().trim())? {
+                field.error = Some(taken_message(field));
+                self.focused_index = index;
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The names and trimmed values of the [`Validator::Unique`] fields that changed since they
+    /// were last handed out, for checking in the background while the user types.
+    pub fn unique_values_to_check(&mut self) -> Vec<(String, String)> {
+        let mut changed = vec![];
+        for field in self.fields.iter().filter(|field| field.is_unique()) {
+            let value = field.get_value().trim().to_string();
+            if self.unique_checked.get(&field.name) != Some(&value) {
+                self.unique_checked.insert(field.name.clone(), value.clone());
+                if !value.is_empty() {
+                    changed.push((field.name.clone(), value));
+                }
+            }
+        }
+        changed
+    }
+
+    /// Shows the outcome of a background check, unless the field has changed since.
+    pub fn apply_unique_check(&mut self, check: &UniqueCheck) {
+        let Some(field) = self.get_field_mut(&check.field) else {
+            return;
+        };
+        if field.get_value().trim() != check.value {
+            return;
+        }
+
+        let message = taken_message(field);
+        if check.taken {
+            field.error = Some(message);
+        } else if field.error.as_ref() == Some(&message) {
+            field.error = None;
+        }
+    }
+
+    /// Handles a bracketed paste.
+    ///
+    /// Multi-line text made of "Label: value" lines fills every field it
+    /// recognises, with a plain "Name" split across the first and last name
+    /// fields. Anything else is inserted into the focused field.
+    pub fn handle_paste(&mut self, text: &str) {
+        let labelled: Vec<(&str, &str)> = text
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(label, value)| (label.trim(), value.trim()))
+            .collect();
+
+        let focused_multi_line = self.fields[self.focused_index].accepts_enter();
+        if text.trim().lines().count() < 2 || labelled.is_empty() || focused_multi_line {
+            self.fields[self.focused_index].paste(text);
+            return;
+        }
+
+        let mut recognised = false;
+        for (label, value) in labelled {
+            if let Some(field) = self.fields.iter_mut().find(|field| field.matches_label(label)) {
+                field.set_value(sanitise(value));
+                recognised = true;
+            } else if label.eq_ignore_ascii_case("name") || label.eq_ignore_ascii_case("full name") {
+                recognised |= self.set_full_name(value);
+            }
+        }
+
+        if !recognised {
+            self.fields[self.focused_index].paste(text);
+        }
+    }
+
+    fn set_full_name(&mut self, value: &str) -> bool {
+        let value = sanitise(value);
+        let (first, last) = value.split_once(' ').unwrap_or((&value, ""));
+
+        let first_field = self.fields.iter().position(|field| field.name == "first");
+        let last_field = self.fields.iter().position(|field| field.name == "last");
+        match (first_field, last_field) {
+            (Some(first_field), Some(last_field)) => {
+                self.fields[first_field].set_value(first);
+                self.fields[last_field].set_value(last);
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn focus_previous(&mut self) {
         if self.focused_index == 0 {
             self.focused_index = self.fields.len() - 1
@@ -84,3 +376,77 @@ pub enum DialogResult {
     Cancel,
     None,
 }
+
+fn taken_message(field: &DialogField) -> String {
+    format!("{} is already in use", field.label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialog::validator::UniqueScope;
+
+    const SCOPE: UniqueScope = UniqueScope::Contacts { excluding: None };
+
+    fn check(field: &str, value: &str, taken: bool) -> UniqueCheck {
+        UniqueCheck {
+            scope: SCOPE,
+            field: field.to_string(),
+            value: value.to_string(),
+            taken,
+        }
+    }
+
+    #[test]
+    fn validate_unique_asks_about_each_unique_field_by_name() {
+        let mut modal = Modal::add_contact();
+        modal.set_value("phone", " 0299991234 ");
+        modal.set_value("speed_dial", "3");
+
+        let asked = std::cell::RefCell::new(vec![]);
+        let unique = modal
+            .validate_unique(|field, value| {
+                asked.borrow_mut().push((field.to_string(), value.to_string()));
+                Ok(field == "speed_dial")
+            })
+            .unwrap();
+
+        assert!(!unique);
+        assert_eq!(
+            asked.into_inner(),
+            [("phone".to_string(), "0299991234".to_string()), ("speed_dial".to_string(), "3".to_string())]
+        );
+        assert_eq!(modal.fields[modal.focused_index].name, "speed_dial");
+        assert_eq!(modal.get_field("speed_dial").unwrap().error.as_deref(), Some("Speed Dial is already in use"));
+    }
+
+    #[test]
+    fn only_changed_unique_values_are_checked_in_the_background() {
+        let mut modal = Modal::add_contact();
+        assert_eq!(modal.unique_values_to_check(), [("speed_dial".to_string(), "None".to_string())]);
+
+        modal.set_value("phone", "0299991234");
+        assert_eq!(modal.unique_values_to_check(), [("phone".to_string(), "0299991234".to_string())]);
+        assert!(modal.unique_values_to_check().is_empty());
+
+        modal.set_value("phone", "");
+        assert!(modal.unique_values_to_check().is_empty());
+    }
+
+    #[test]
+    fn background_checks_flag_and_clear_the_current_value() {
+        let mut modal = Modal::add_contact();
+        modal.set_value("phone", "0299991234");
+
+        modal.apply_unique_check(&check("phone", "0299991234", true));
+        let message = Some("Phone Number is already in use".to_string());
+        assert_eq!(modal.get_field("phone").unwrap().error, message);
+
+        // A stale answer about an earlier value is ignored.
+        modal.apply_unique_check(&check("phone", "029999123", false));
+        assert_eq!(modal.get_field("phone").unwrap().error, message);
+
+        modal.apply_unique_check(&check("phone", "0299991234", false));
+        assert_eq!(modal.get_field("phone").unwrap().error, None);
+    }
+}
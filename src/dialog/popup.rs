@@ -0,0 +1,168 @@
+use crate::app::{App, TypingMode};
+use crate::editing::EditContext;
+use crate::error::AppResult;
+use crate::line_buffer::LineBuffer;
+use crossterm::event::{KeyCode, KeyEvent};
+use std::fmt::{Debug, Formatter};
+
+/// The keys [`Popup::handle_key_events`] understands, for the help screen.
+pub const POPUP_KEYS: &[(&str, &str)] = &[
+    ("Y / N", "Answer a yes / no question"),
+    ("Left / Right / Tab", "Move between Yes and No"),
+    ("Up / Down / J / K", "Move through a list of choices"),
+    ("1-9", "Pick a numbered choice"),
+    ("Enter", "Accept"),
+    ("Esc", "Cancel"),
+];
+
+/// Called with the outcome once a [`Popup`] closes.
+pub type PopupCallback = Box<dyn FnOnce(&mut App, PopupResult) -> AppResult<()>>;
+
+#[derive(Debug)]
+pub enum PopupKind {
+    /// Yes/No question. `yes_selected` is the button Enter currently picks.
+    Confirm { yes_selected: bool },
+    Alert,
+    Prompt(LineBuffer),
+    Choice { options: Vec<String>, selected: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PopupResult {
+    Confirmed,
+    Cancelled,
+    Text(String),
+    Choice(usize),
+}
+
+/// A small dialog pushed on top of whatever is on screen.
+pub struct Popup {
+    pub title: String,
+    pub message: String,
+    pub kind: PopupKind,
+    /// Draws the popup in red, for destructive actions.
+    pub danger: bool,
+    callback: Option<PopupCallback>,
+}
+
+impl Debug for Popup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Popup")
+            .field("title", &self.title)
+            .field("message", &self.message)
+            .field("kind", &self.kind)
+            .field("danger", &self.danger)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Popup {
+    fn new(title: impl Into<String>, message: impl Into<String>, kind: PopupKind) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            kind,
+            danger: false,
+            callback: None,
+        }
+    }
+
+    pub fn confirm(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(title, message, PopupKind::Confirm { yes_selected: true })
+    }
+
+    pub fn alert(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(title, message, PopupKind::Alert)
+    }
+
+    pub fn prompt(title: impl Into<String>, message: impl Into<String>, initial: impl Into<String>) -> Self {
+        let mut buffer = LineBuffer::default();
+        buffer.set_value(initial);
+        buffer.set_cursor_position(usize::MAX);
+        Self::new(title, message, PopupKind::Prompt(buffer))
+    }
+
+    pub fn choice(title: impl Into<String>, message: impl Into<String>, options: Vec<String>) -> Self {
+        Self::new(title, message, PopupKind::Choice { options, selected: 0 })
+    }
+
+    /// Marks the popup as destructive: it is drawn in red and a confirm defaults to "No".
+    pub fn danger(mut self) -> Self {
+        self.danger = true;
+        if let PopupKind::Confirm { yes_selected } = &mut self.kind {
+            *yes_selected = false;
+        }
+        self
+    }
+
+    pub fn on_result(mut self, callback: impl FnOnce(&mut App, PopupResult) -> AppResult<()> + 'static) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    pub fn take_callback(&mut self) -> Option<PopupCallback> {
+        self.callback.take()
+    }
+
+    /// Handles a key, returning the result once the popup should close.
+    pub fn handle_key_events(
+        &mut self,
+        event: KeyEvent,
+        type_mode: TypingMode,
+        context: &mut EditContext,
+    ) -> AppResult<Option<PopupResult>> {
+        if event.code == KeyCode::Esc {
+            return Ok(Some(PopupResult::Cancelled));
+        }
+
+        let result = match &mut self.kind {
+            PopupKind::Confirm { yes_selected } => match event.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => Some(PopupResult::Confirmed),
+                KeyCode::Char('n') | KeyCode::Char('N') => Some(PopupResult::Cancelled),
+                KeyCode::Left | KeyCode::Right | KeyCode::Tab | KeyCode::BackTab => {
+                    *yes_selected = !*yes_selected;
+                    None
+                }
+                KeyCode::Enter if *yes_selected => Some(PopupResult::Confirmed),
+                KeyCode::Enter => Some(PopupResult::Cancelled),
+                _ => None,
+            },
+            PopupKind::Alert => match event.code {
+                KeyCode::Enter | KeyCode::Char(' ') => Some(PopupResult::Confirmed),
+                _ => None,
+            },
+            PopupKind::Prompt(buffer) => match event.code {
+                KeyCode::Enter => Some(PopupResult::Text(buffer.get_value().to_string())),
+                _ => {
+                    buffer.handle_key_events(event, type_mode, context)?;
+                    None
+                }
+            },
+            PopupKind::Choice { options, selected } => match event.code {
+                KeyCode::Up | KeyCode::Char('k') => {
+                    *selected = selected.saturating_sub(1);
+                    None
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    *selected = (*selected + 1).min(options.len().saturating_sub(1));
+                    None
+                }
+                KeyCode::Enter if !options.is_empty() => Some(PopupResult::Choice(*selected)),
+                KeyCode::Char(c) => c
+                    .to_digit(10)
+                    .map(|digit| digit as usize)
+                    .filter(|digit| (1..=options.len()).contains(digit))
+                    .map(|digit| PopupResult::Choice(digit - 1)),
+                _ => None,
+            },
+        };
+
+        Ok(result)
+    }
+
+    pub fn handle_paste(&mut self, text: &str) {
+        if let PopupKind::Prompt(buffer) = &mut self.kind {
+            buffer.paste(text);
+        }
+    }
+}
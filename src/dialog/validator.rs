@@ -0,0 +1,176 @@
+use crate::dialler::dtmf;
+use crate::dialog::dialog_field::{split_number_list, DATE_FORMAT};
+use chrono::NaiveDate;
+use regex::Regex;
+
+/// A rule a [`DialogField`](crate::dialog::dialog_field::DialogField) value must satisfy before its modal can be submitted.
+#[derive(Debug, Clone)]
+pub enum Validator {
+    Required,
+    MaxLength(usize),
+    Pattern { regex: Regex, message: String },
+    PhoneNumber,
+    /// Every comma-separated entry must be a valid phone number.
+    PhoneNumberList,
+    /// Post-dial tones, pauses and waits, e.g. `,,2;345#`.
+    Dtmf,
+    /// A `YYYY-MM-DD` date.
+    Date,
+    /// The value must not already be in the database. This can't be checked locally, so the owner
+    /// of the modal checks it in the background as the value changes, and again on submit.
+    Unique,
+}
+
+/// Where the values of a modal's [`Validator::Unique`] fields must be unique.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniqueScope {
+    /// Among the contacts, other than the one being edited.
+    Contacts { excluding: Option<u64> },
+    /// Among the templates, other than the one being edited.
+    Templates { excluding: Option<u64> },
+}
+
+/// The outcome of checking a [`Validator::Unique`] field's value against the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniqueCheck {
+    pub scope: UniqueScope,
+    pub field: String,
+    pub value: String,
+    pub taken: bool,
+}
+
+impl Validator {
+    pub fn pattern(regex: &str, message: impl Into<String>) -> Self {
+        Self::Pattern {
+            regex: Regex::new(regex).expect("validator pattern should be a valid regex"),
+            message: message.into(),
+        }
+    }
+
+    pub fn email() -> Self {
+        Self::pattern(r"^[^@\s]+@[^@\s]+\.[^@\s]+$", "Not a valid email address")
+    }
+
+    /// Checks `value`, returning the message to show under the field if it fails.
+    /// Optional rules pass on an empty value, so pair them with [`Validator::Required`] if needed.
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        match self {
+            Validator::Required if value.is_empty() => Err("Required".to_string()),
+            Validator::MaxLength(max) if value.chars().count() > *max => {
+                Err(format!("Must be at most {max} characters"))
+            }
+            Validator::Pattern { regex, message } if !value.is_empty() && !regex.is_match(value) => {
+                Err(message.clone())
+            }
+            Validator::PhoneNumber if !value.is_empty() => validate_phone_number(value),
+            Validator::PhoneNumberList => split_number_list(value)
+                .iter()
+                .try_for_each(|number| {
+                    validate_phone_number(number).map_err(|err| format!("{number}: {err}"))
+                }),
+            Validator::Dtmf => dtmf::validate(value),
+            Validator::Date if !value.is_empty() => NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .map(|_| ())
+                .map_err(|_| "Must be a date like 2024-12-31".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn validate_phone_number(value: &str) -> Result<(), String> {
+    let allowed = |c: char| c.is_ascii_digit() || matches!(c, ' ' | '-' | '(' | ')' | '.');
+    let number = value.strip_prefix('+').unwrap_or(value);
+    if !number.chars().all(allowed) {
+        return Err("Only digits, spaces, + - ( ) and . are allowed".to_string());
+    }
+
+    let digits = number.chars().filter(char::is_ascii_digit).count();
+    if !(3..=15).contains(&digits) {
+        return Err("Must contain between 3 and 15 digits".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_rejects_blank_values() {
+        assert!(Validator::Required.validate("x").is_ok());
+        assert_eq!(Validator::Required.validate("  "), Err("Required".to_string()));
+    }
+
+    #[test]
+    fn max_length_counts_characters() {
+        let validator = Validator::MaxLength(4);
+        assert!(validator.validate("café").is_ok());
+        assert!(validator.validate(" abcd ").is_ok());
+        assert_eq!(validator.validate("abcde"), Err("Must be at most 4 characters".to_string()));
+    }
+
+    #[test]
+    fn pattern_checks_non_empty_values() {
+        let validator = Validator::email();
+        assert!(validator.validate("").is_ok());
+        assert!(validator.validate("jane@example.com").is_ok());
+        assert_eq!(validator.validate("jane@example"), Err("Not a valid email address".to_string()));
+        assert!(validator.validate("jane smith@example.com").is_err());
+    }
+
+    #[test]
+    fn phone_numbers_allow_punctuation_and_a_leading_plus() {
+        let validator = Validator::PhoneNumber;
+        assert!(validator.validate("").is_ok());
+        assert!(validator.validate("+61 (2) 9999-1234").is_ok());
+        assert!(validator.validate("000").is_ok());
+        assert!(validator.validate("12").is_err());
+        assert!(validator.validate("1234567890123456").is_err());
+        assert!(validator.validate("0299 991 234 x5").is_err());
+        assert!(validator.validate("++61 2 9999 1234").is_err());
+    }
+
+    #[test]
+    fn phone_number_lists_name_the_bad_entry() {
+        let validator = Validator::PhoneNumberList;
+        assert!(validator.validate("").is_ok());
+        assert!(validator.validate("0299991234, +61 412 345 678; 000").is_ok());
+        assert_eq!(
+            validator.validate("0299991234, 12"),
+            Err("12: Must contain between 3 and 15 digits".to_string())
+        );
+    }
+
+    #[test]
+    fn dtmf_accepts_tones_pauses_and_waits() {
+        assert!(Validator::Dtmf.validate("").is_ok());
+        assert!(Validator::Dtmf.validate(",,2;345#").is_ok());
+        assert!(Validator::Dtmf.validate("12x").is_err());
+    }
+
+    #[test]
+    fn dates_must_be_real_days() {
+        let validator = Validator::Date;
+        assert!(validator.validate("").is_ok());
+        assert!(validator.validate("2024-02-29").is_ok());
+        assert_eq!(validator.validate("2023-02-29"), Err("Must be a date like 2024-12-31".to_string()));
+        assert!(validator.validate("31/12/2024").is_err());
+    }
+
+    #[test]
+    fn dates_are_checked_after_trimming() {
+        let validator = Validator::Date;
+        assert!(validator.validate("   ").is_ok());
+        assert!(validator.validate(" 2024-12-31 ").is_ok());
+        assert!(validator.validate("2024-13-01").is_err());
+        assert!(validator.validate("2024-04-31").is_err());
+        assert!(validator.validate("2024-12-31 09:00").is_err());
+    }
+
+    #[test]
+    fn unique_is_left_to_the_database() {
+        assert!(Validator::Unique.validate("anything").is_ok());
+    }
+}
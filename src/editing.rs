@@ -0,0 +1,169 @@
+use crate::config::Config;
+
+/// State shared by every [`LineBuffer`](crate::line_buffer::LineBuffer) in the app, so text killed
+/// in one field can be yanked into another.
+#[derive(Debug, Default)]
+pub struct EditContext {
+    pub kill_ring: KillRing,
+    pub word_boundary: WordBoundary,
+}
+
+impl EditContext {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            kill_ring: KillRing::default(),
+            word_boundary: WordBoundary::new(&config.word_delimiters),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KillDirection {
+    Forward,
+    Backward,
+}
+
+#[derive(Debug)]
+pub struct KillRing {
+    entries: Vec<String>,
+    capacity: usize,
+    yank_index: usize,
+    appending: bool,
+}
+
+impl Default for KillRing {
+    fn default() -> Self {
+        Self {
+            entries: vec![],
+            capacity: 30,
+            yank_index: 0,
+            appending: false,
+        }
+    }
+}
+
+impl KillRing {
+    /// Stores killed text. Consecutive kills are merged into one entry, like readline.
+    pub fn kill(&mut self, text: &str, direction: KillDirection) {
+        if text.is_empty() {
+            return;
+        }
+
+        match self.entries.last_mut() {
+            Some(last) if self.appending => match direction {
+                KillDirection::Forward => last.push_str(text),
+                KillDirection::Backward => last.insert_str(0, text),
+            },
+            _ => {
+                self.entries.push(text.to_string());
+                if self.entries.len() > self.capacity {
+                    self.entries.remove(0);
+                }
+            }
+        }
+
+        self.appending = true;
+        self.yank_index = self.entries.len() - 1;
+    }
+
+    /// Called after every non-kill command so the next kill starts a new entry.
+    pub fn end_kill_sequence(&mut self) {
+        self.appending = false;
+    }
+
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = self.entries.len().checked_sub(1)?;
+        self.entries.last().map(String::as_str)
+    }
+
+    /// Rotates to the previous entry for `Alt+Y` after a yank.
+    pub fn yank_pop(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        self.yank_index = match self.yank_index {
+            0 => self.entries.len() - 1,
+            i => i - 1,
+        };
+        self.entries.get(self.yank_index).map(String::as_str)
+    }
+}
+
+/// Decides which characters make up a word for word-wise movement and deletion.
+#[derive(Debug)]
+pub struct WordBoundary {
+    delimiters: String,
+}
+
+impl Default for WordBoundary {
+    fn default() -> Self {
+        Self::new(Config::DEFAULT_WORD_DELIMITERS)
+    }
+}
+
+impl WordBoundary {
+    pub fn new(delimiters: impl Into<String>) -> Self {
+        Self {
+            delimiters: delimiters.into(),
+        }
+    }
+
+    pub fn is_word_char(&self, c: char) -> bool {
+        !c.is_whitespace() && !self.delimiters.contains(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yank_returns_the_last_kill() {
+        let mut ring = KillRing::default();
+        assert_eq!(ring.yank(), None);
+        ring.kill("hello", KillDirection::Forward);
+        assert_eq!(ring.yank(), Some("hello"));
+    }
+
+    #[test]
+    fn consecutive_kills_are_joined() {
+        let mut ring = KillRing::default();
+        ring.kill("world", KillDirection::Forward);
+        ring.kill("!", KillDirection::Forward);
+        ring.kill("hello ", KillDirection::Backward);
+        assert_eq!(ring.yank(), Some("hello world!"));
+
+        ring.end_kill_sequence();
+        ring.kill("again", KillDirection::Forward);
+        assert_eq!(ring.yank(), Some("again"));
+    }
+
+    #[test]
+    fn yank_pop_cycles_through_older_kills() {
+        let mut ring = KillRing::default();
+        for text in ["one", "two", "three"] {
+            ring.kill(text, KillDirection::Forward);
+            ring.end_kill_sequence();
+        }
+        assert_eq!(ring.yank(), Some("three"));
+        assert_eq!(ring.yank_pop(), Some("two"));
+        assert_eq!(ring.yank_pop(), Some("one"));
+        assert_eq!(ring.yank_pop(), Some("three"));
+    }
+
+    #[test]
+    fn the_oldest_kill_is_dropped_at_capacity() {
+        let mut ring = KillRing::default();
+        for index in 0..31 {
+            ring.kill(&index.to_string(), KillDirection::Forward);
+            ring.end_kill_sequence();
+        }
+        ring.yank();
+        let popped: Vec<String> = (0..30)
+            .map(|_| ring.yank_pop().unwrap().to_string())
+            .collect();
+        assert!(!popped.contains(&"0".to_string()));
+        assert!(popped.contains(&"1".to_string()));
+    }
+}
@@ -10,14 +10,201 @@ pub enum Error {
 
     #[from]
     RusqlError(rusqlite::Error),
+
+    /// Another contact already uses this phone number.
+    DuplicatePhoneNumber(String),
+
+    /// Any other database constraint, with SQLite's description of it.
+    ConstraintViolation(String),
+
+    DiallerSpawn {
+        program: String,
+        source: std::io::Error,
+    },
+
+    /// The dialler started but exited unsuccessfully. `code` is `None` if it was killed by a signal.
+    DiallerExited {
+        program: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+
+    /// A network dialler backend, such as AMI, failed to place or follow a call.
+    DiallerBackend {
+        backend: &'static str,
+        message: String,
+    },
+
+    /// The SMS gateway couldn't send a message.
+    SmsGateway(String),
+
+    /// Neither the terminal nor the clipboard command could copy the text.
+    Clipboard(String),
+
+    /// A URI given to `dialler-rs dial` that can't be called.
+    InvalidUri {
+        uri: String,
+        reason: String,
+    },
+
+    /// The command line didn't make sense.
+    Usage(String),
+
+    /// `dialler-rs lookup` found no contact with the number.
+    UnknownNumber(String),
+
+    /// The lookup service couldn't listen for other programs.
+    LookupService(String),
+
+    /// A line of a contacts file being imported that can't be read, counting from 1.
+    ImportParse {
+        line: usize,
+        message: String,
+    },
+
+    /// Setting up, drawing to or restoring the terminal failed.
+    Terminal(std::io::Error),
+
+    EventChannelClosed,
+}
+
+impl Error {
+    /// Maps a SQLite constraint failure on a contact write to a typed error.
+    pub fn from_contact_write(err: rusqlite::Error, phone_number: &str) -> Self {
+        match err {
+            rusqlite::Error::SqliteFailure(failure, message)
+                if failure.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                let message = message.unwrap_or_default();
+                if message.contains("phone_number") {
+                    Error::DuplicatePhoneNumber(phone_number.to_string())
+                } else {
+                    Error::ConstraintViolation(message)
+                }
+            }
+            err => Error::RusqlError(err),
+        }
+    }
+
+    /// The error followed by each of its sources, e.g. `could not start dialler "x": No such file or directory`.
+    pub fn full_message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(err) = source {
+            message.push_str(&format!(": {err}"));
+            source = err.source();
+        }
+        message
+    }
+
+    /// A suggestion for the user on how to get past the error, if there is one.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::ConfigError(_) => Some("check the DIALLER_* environment variables and that your home directory is writable"),
+            Error::DuplicatePhoneNumber(_) => Some("edit the existing contact instead, or use a different number"),
+            Error::DiallerSpawn { .. } => Some("set DIALLER_PROGRAM to the path of your softphone"),
+            Error::DiallerExited { .. } => Some("check the softphone is running and logged in"),
+            Error::DiallerBackend { .. } => Some("check the dialler profile in profiles.toml"),
+            Error::SmsGateway(_) => Some("check the gateway settings in sms.toml"),
+            Error::Clipboard(_) => Some("set DIALLER_CLIPBOARD_COMMAND to a program that copies its input, e.g. wl-copy"),
+            Error::InvalidUri { .. } => Some("pass a tel:, sip: or callto: URI with a phone number, e.g. tel:+61299991234"),
+            Error::Usage(_) => Some("run dialler-rs with no arguments, dialler-rs dial <URI> or dialler-rs lookup <number>"),
+            Error::LookupService(_) => Some("point DIALLER_LOOKUP_SOCKET or DIALLER_LOOKUP_PORT somewhere free, or set them to off"),
+            Error::ImportParse { .. } => Some("fix the line in the import file and try again"),
+            Error::Terminal(_) => Some("make sure dialler-rs is running in an interactive terminal"),
+            _ => None,
+        }
+    }
+
+    /// The process exit code for this error, following the BSD `sysexits.h` conventions.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::ConfigError(_) => 78,
+            Error::IoError(_) => 74,
+            Error::RusqlError(_) => 73,
+            Error::DuplicatePhoneNumber(_) | Error::ConstraintViolation(_) => 65,
+            Error::ImportParse { .. } | Error::InvalidUri { .. } => 65,
+            Error::Usage(_) => 64,
+            Error::UnknownNumber(_) => 67,
+            Error::LookupService(_) => 69,
+            Error::DiallerSpawn { .. } | Error::DiallerExited { .. } | Error::DiallerBackend { .. } => 69,
+            Error::SmsGateway(_) => 69,
+            Error::Clipboard(_) => 74,
+            Error::Terminal(_) => 71,
+            Error::EventChannelClosed => 70,
+        }
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Error::ConfigError(message) => write!(f, "configuration error: {message}"),
+            Error::IoError(_) => write!(f, "I/O error"),
+            Error::RusqlError(_) => write!(f, "database error"),
+            Error::DuplicatePhoneNumber(number) => {
+                write!(f, "a contact with the phone number {number} already exists")
+            }
+            Error::ConstraintViolation(message) => write!(f, "database constraint failed: {message}"),
+            Error::DiallerSpawn { program, .. } => write!(f, "could not start dialler \"{program}\""),
+            Error::DiallerExited { program, code: Some(code), stderr } if stderr.is_empty() => {
+                write!(f, "dialler \"{program}\" exited with code {code}")
+            }
+            Error::DiallerExited { program, code: Some(code), stderr } => {
+                write!(f, "dialler \"{program}\" exited with code {code}: {stderr}")
+            }
+            Error::DiallerExited { program, code: None, .. } => {
+                write!(f, "dialler \"{program}\" was killed")
+            }
+            Error::DiallerBackend { backend, message } => write!(f, "{backend} dialler: {message}"),
+            Error::SmsGateway(message) => write!(f, "SMS gateway: {message}"),
+            Error::Clipboard(message) => write!(f, "clipboard: {message}"),
+            Error::InvalidUri { uri, reason } => write!(f, "can't dial {uri}: {reason}"),
+            Error::Usage(message) => write!(f, "{message}"),
+            Error::UnknownNumber(number) => write!(f, "no contact has the number {number}"),
+            Error::LookupService(message) => write!(f, "lookup service: {message}"),
+            Error::ImportParse { line, message } => write!(f, "import failed on line {line}: {message}"),
+            Error::Terminal(_) => write!(f, "terminal error"),
+            Error::EventChannelClosed => write!(f, "the terminal event stream closed unexpectedly"),
+        }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError(err) | Error::Terminal(err) => Some(err),
+            Error::DiallerSpawn { source, .. } => Some(source),
+            Error::RusqlError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 pub type AppResult<T> = Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_errors_name_the_line() {
+        let err = Error::ImportParse {
+            line: 12,
+            message: "expected 4 columns, found 3".to_string(),
+        };
+        assert_eq!(err.to_string(), "import failed on line 12: expected 4 columns, found 3");
+        assert_eq!(err.hint(), Some("fix the line in the import file and try again"));
+        assert_eq!(err.exit_code(), 65);
+    }
+
+    #[test]
+    fn the_full_message_follows_the_sources() {
+        let err = Error::DiallerSpawn {
+            program: "linphone-dial".to_string(),
+            source: std::io::Error::new(std::io::ErrorKind::NotFound, "No such file or directory"),
+        };
+        assert_eq!(err.full_message(), "could not start dialler \"linphone-dial\": No such file or directory");
+        assert_eq!(err.exit_code(), 69);
+    }
+}
@@ -1,15 +1,31 @@
+use crate::clipboard::ClipboardEvent;
+use crate::dialler::DiallerEvent;
+use crate::dialog::validator::UniqueCheck;
 use crate::error::{AppResult, Error};
+use crate::lookup::Lookup;
+use crate::sms::SmsEvent;
 use crossterm::event::{Event as CrosstermEvent, KeyEvent, MouseEvent};
 use futures::{FutureExt, StreamExt};
 use std::time::Duration;
 use tokio::sync::mpsc;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Event {
     Tick,
     Key(KeyEvent),
     Mouse(MouseEvent),
     Resize(u16, u16),
+    Paste(String),
+    /// News about a call placed by [`crate::dialler`].
+    Dialler(DiallerEvent),
+    /// Another program asked the lookup service who a number belongs to.
+    Lookup(Lookup),
+    /// How a text handed to [`crate::sms`] went.
+    Sms(SmsEvent),
+    /// Whether a value typed into a form is already taken, checked in the background.
+    UniqueCheck(UniqueCheck),
+    /// How the clipboard command took text handed to [`crate::clipboard`].
+    Clipboard(ClipboardEvent),
 }
 
 #[allow(dead_code)]
@@ -48,7 +64,10 @@ impl EventHandler {
                       CrosstermEvent::Resize(x, y) => {
                         _sender.send(Event::Resize(x, y)).unwrap();
                       },
-                      CrosstermEvent::FocusLost | CrosstermEvent::FocusGained | CrosstermEvent::Paste(_) => {},
+                      CrosstermEvent::Paste(text) => {
+                        _sender.send(Event::Paste(text)).unwrap();
+                      },
+                      CrosstermEvent::FocusLost | CrosstermEvent::FocusGained => {},
                     }
                   }
                 };
@@ -61,10 +80,15 @@ impl EventHandler {
         }
     }
 
+    /// A sender for background tasks to feed their own events into the loop.
+    pub fn sender(&self) -> mpsc::UnboundedSender<Event> {
+        self.sender.clone()
+    }
+
     pub async fn next(&mut self) -> AppResult<Event> {
         self.receiver
             .recv()
             .await
-            .ok_or(Error::ConfigError("This is an IO error".into()))
+            .ok_or(Error::EventChannelClosed)
     }
 }
@@ -1,9 +1,21 @@
+pub mod action;
 pub mod app;
+pub mod clipboard;
 pub mod config;
 pub mod data_layer;
+pub mod dialler;
 pub mod dialog;
+pub mod editing;
 pub mod error;
 pub mod event;
 pub mod line_buffer;
+pub mod lookup;
+pub mod notification;
 pub mod renderer;
+pub mod screen;
+pub mod signature;
+pub mod sms;
+pub mod template;
 pub mod tui;
+pub mod uri;
+pub mod vcard;
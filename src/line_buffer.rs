@@ -1,157 +1,656 @@
-use crate::app::TypingMode;
-use crate::error::AppResult;
-use crossterm::event::{KeyCode, KeyEvent};
-use std::fmt;
-use std::fmt::{Display, Formatter};
-use tracing::info;
-
-#[derive(Debug, Default)]
-pub struct LineBuffer {
-    buffer: String,
-    _max_buffer: usize,
-    _display_buffer: usize,
-    cursor_position: usize,
-    pub updated: bool,
-}
-
-impl LineBuffer {
-    pub fn get_value(&self) -> &str {
-        &self.buffer
-    }
-
-    pub fn clear(&mut self) {
-        self.buffer.clear();
-    }
-
-    pub fn set_value(&mut self, value: impl Into<String>) {
-        let value = value.into();
-        info!("Setting linebuffer buffer: {:?}", value);
-        self.buffer = value;
-    }
-
-    pub fn handle_key_events(&mut self, event: KeyEvent, type_mode: TypingMode) -> AppResult<()> {
-        match event.code {
-            KeyCode::Char(c) => {
-                self.enter_char(c, type_mode);
-            }
-            KeyCode::Enter => {
-                self.reset_cursor();
-                self.buffer.clear();
-            }
-            KeyCode::Backspace => {
-                self.backspace_char();
-            }
-            KeyCode::Delete => {
-                self.delete_char();
-            }
-            KeyCode::Left => {
-                self.move_cursor_left();
-            }
-            KeyCode::Right => self.move_cursor_right(),
-            KeyCode::Home => {
-                self.reset_cursor();
-            }
-            KeyCode::End => {
-                self.move_cursor_to_end();
-            }
-            _ => {}
-        }
-
-        Ok(())
-    }
-
-    pub fn get_cursor_position(&self) -> usize {
-        self.cursor_position
-    }
-
-    fn move_cursor_left(&mut self) {
-        let cursor_moved_left = self.cursor_position.saturating_sub(1);
-        self.cursor_position = self.clamp_cursor(cursor_moved_left);
-    }
-
-    fn move_cursor_right(&mut self) {
-        let cursor_moved_right = self.cursor_position.saturating_add(1);
-        self.cursor_position = self.clamp_cursor(cursor_moved_right);
-    }
-
-    fn enter_char(&mut self, new_char: char, type_mode: TypingMode) {
-        match type_mode {
-            TypingMode::Insert => self.insert_char(new_char),
-            TypingMode::Overwrite => self.replace_char(new_char),
-        }
-
-        self.move_cursor_right();
-    }
-
-    fn insert_char(&mut self, new_char: char) {
-        self.buffer.insert(self.cursor_position, new_char);
-        self.updated = true;
-    }
-
-    fn replace_char(&mut self, new_char: char) {
-        let is_not_cursor_rightmost = self.cursor_position != self.buffer.len();
-
-        if is_not_cursor_rightmost {
-            self.buffer = self
-                .buffer
-                .chars()
-                .enumerate()
-                .map(|(i, c)| {
-                    if i == self.cursor_position {
-                        new_char
-                    } else {
-                        c
-                    }
-                })
-                .collect()
-        } else {
-            self.buffer.push(new_char);
-        }
-
-        self.updated = true;
-    }
-
-    fn backspace_char(&mut self) {
-        let is_not_cursor_leftmost = self.cursor_position != 0;
-        if is_not_cursor_leftmost {
-            let current_index = self.cursor_position;
-            let from_left_to_current_index = current_index - 1;
-
-            let before_char_to_delete = self.buffer.chars().take(from_left_to_current_index);
-            let after_char_to_delete = self.buffer.chars().skip(current_index);
-            self.buffer = before_char_to_delete.chain(after_char_to_delete).collect();
-            self.move_cursor_left();
-            self.updated = true;
-        }
-    }
-
-    fn delete_char(&mut self) {
-        let is_not_cursor_rightmost = self.cursor_position != self.buffer.len();
-        if is_not_cursor_rightmost {
-            let current_index = self.cursor_position;
-            let from_left_to_current_index = current_index;
-            let before_char_to_delete = self.buffer.chars().take(from_left_to_current_index);
-            let after_char_to_delete = self.buffer.chars().skip(current_index + 1);
-            self.buffer = before_char_to_delete.chain(after_char_to_delete).collect();
-            self.updated = true;
-        }
-    }
-
-    pub fn reset_cursor(&mut self) {
-        self.cursor_position = 0;
-    }
-
-    fn move_cursor_to_end(&mut self) {
-        self.cursor_position = self.buffer.len();
-    }
-
-    fn clamp_cursor(&mut self, new_cursor_pos: usize) -> usize {
-        new_cursor_pos.clamp(0, self.buffer.len())
-    }
-}
-
-impl Display for LineBuffer {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "{}", self.buffer)
-    }
-}
+use crate::app::TypingMode;
+use crate::editing::{EditContext, KillDirection, WordBoundary};
+use crate::error::AppResult;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use tracing::info;
+use unicode_width::UnicodeWidthChar;
+
+/// The keys [`LineBuffer::handle_key_events`] understands, as `(keys, description)` for the help screen.
+pub const EDITING_KEYS: &[(&str, &str)] = &[
+    ("Left / Right", "Move one character"),
+    ("Home / End", "Move to the start / end of the line"),
+    ("Alt+B / Ctrl+Left", "Move back one word"),
+    ("Alt+F / Ctrl+Right", "Move forward one word"),
+    ("Backspace / Delete", "Delete the character before / under the cursor"),
+    ("Ctrl+W / Alt+Backspace", "Kill the word before the cursor"),
+    ("Alt+D / Ctrl+Delete", "Kill the word after the cursor"),
+    ("Ctrl+K", "Kill to the end of the line"),
+    ("Ctrl+U", "Kill to the start of the line"),
+    ("Ctrl+Y", "Yank the most recently killed text"),
+    ("Alt+Y", "Replace the text just yanked with the previous kill"),
+    ("Ctrl+T", "Transpose the characters around the cursor"),
+];
+
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    buffer: String,
+    max_buffer: Option<usize>,
+    display_buffer: usize,
+    view_offset: usize,
+    cursor_position: usize,
+    last_yank: Option<(usize, usize)>,
+    pub updated: bool,
+}
+
+/// The slice of a [`LineBuffer`] that fits in its display width.
+#[derive(Debug, PartialEq)]
+pub struct Viewport {
+    pub text: String,
+    pub cursor: usize,
+    pub overflow_left: bool,
+    pub overflow_right: bool,
+}
+
+impl LineBuffer {
+    pub fn get_value(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.view_offset = 0;
+    }
+
+    pub fn set_value(&mut self, value: impl Into<String>) {
+        let mut value = value.into();
+        info!("Setting linebuffer buffer: {:?}", value);
+        if let Some(max) = self.max_buffer {
+            value = value.chars().take(max).collect();
+        }
+        self.buffer = value;
+        self.cursor_position = self.clamp_cursor(self.cursor_position);
+        self.scroll_to_cursor();
+    }
+
+    /// Limits the buffer to `max` characters, truncating any existing value.
+    pub fn set_max_length(&mut self, max: Option<usize>) {
+        self.max_buffer = max;
+        let value = std::mem::take(&mut self.buffer);
+        self.set_value(value);
+    }
+
+    pub fn get_max_length(&self) -> Option<usize> {
+        self.max_buffer
+    }
+
+    /// Sets the number of columns available to draw the buffer, scrolling so the cursor stays visible.
+    pub fn set_display_width(&mut self, width: usize) {
+        self.display_buffer = width;
+        self.scroll_to_cursor();
+    }
+
+    pub fn handle_key_events(
+        &mut self,
+        event: KeyEvent,
+        type_mode: TypingMode,
+        context: &mut EditContext,
+    ) -> AppResult<()> {
+        let mut killed = false;
+        let mut yanked = false;
+
+        match (event.modifiers, event.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('w')) | (KeyModifiers::ALT, KeyCode::Backspace) => {
+                let start = self.word_start_before(self.cursor_position, &context.word_boundary);
+                self.kill_range(start, self.cursor_position, KillDirection::Backward, context);
+                killed = true;
+            }
+            (KeyModifiers::ALT, KeyCode::Char('d')) | (KeyModifiers::CONTROL, KeyCode::Delete) => {
+                let end = self.word_end_after(self.cursor_position, &context.word_boundary);
+                self.kill_range(self.cursor_position, end, KillDirection::Forward, context);
+                killed = true;
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('k')) => {
+                self.kill_range(self.cursor_position, self.len(), KillDirection::Forward, context);
+                killed = true;
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('u')) => {
+                self.kill_range(0, self.cursor_position, KillDirection::Backward, context);
+                killed = true;
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('y')) => {
+                if let Some(text) = context.kill_ring.yank() {
+                    let text = text.to_string();
+                    yanked = self.yank_text(&text);
+                }
+            }
+            (KeyModifiers::ALT, KeyCode::Char('y')) => {
+                if let Some((start, end)) = self.last_yank {
+                    if let Some(text) = context.kill_ring.yank_pop() {
+                        let text = text.to_string();
+                        self.remove_range(start, end);
+                        self.cursor_position = start;
+                        yanked = self.yank_text(&text);
+                    }
+                }
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('t')) => {
+                self.transpose_chars();
+            }
+            (KeyModifiers::ALT, KeyCode::Char('b')) | (KeyModifiers::CONTROL, KeyCode::Left) => {
+                self.cursor_position = self.word_start_before(self.cursor_position, &context.word_boundary);
+            }
+            (KeyModifiers::ALT, KeyCode::Char('f')) | (KeyModifiers::CONTROL, KeyCode::Right) => {
+                self.cursor_position = self.word_end_after(self.cursor_position, &context.word_boundary);
+            }
+            (modifiers, KeyCode::Char(c)) if !modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                self.enter_char(c, type_mode);
+            }
+            (_, KeyCode::Enter) => {
+                self.reset_cursor();
+                self.clear();
+            }
+            (_, KeyCode::Backspace) => {
+                self.backspace_char();
+            }
+            (_, KeyCode::Delete) => {
+                self.delete_char();
+            }
+            (_, KeyCode::Left) => {
+                self.move_cursor_left();
+            }
+            (_, KeyCode::Right) => self.move_cursor_right(),
+            (_, KeyCode::Home) => {
+                self.reset_cursor();
+            }
+            (_, KeyCode::End) => {
+                self.move_cursor_to_end();
+            }
+            _ => {}
+        }
+
+        if !killed {
+            context.kill_ring.end_kill_sequence();
+        }
+        if !yanked {
+            self.last_yank = None;
+        }
+
+        self.scroll_to_cursor();
+        Ok(())
+    }
+
+    /// Inserts pasted text at the cursor after flattening it onto a single line.
+    pub fn paste(&mut self, text: &str) {
+        let text = sanitise(text);
+        if self.yank_text(&text) {
+            self.last_yank = None;
+        }
+        self.scroll_to_cursor();
+    }
+
+    pub fn get_cursor_position(&self) -> usize {
+        self.cursor_position
+    }
+
+    pub fn set_cursor_position(&mut self, position: usize) {
+        self.cursor_position = self.clamp_cursor(position);
+        self.scroll_to_cursor();
+    }
+
+    /// Removes and returns everything from the cursor to the end of the line.
+    pub fn split_off_at_cursor(&mut self) -> String {
+        let index = self.byte_index(self.cursor_position);
+        let tail = self.buffer.split_off(index);
+        self.updated |= !tail.is_empty();
+        self.scroll_to_cursor();
+        tail
+    }
+
+    /// Appends `text` to the end of the line without moving the cursor, truncated to the maximum length.
+    pub fn append(&mut self, text: &str) {
+        let room = self.max_buffer.map_or(usize::MAX, |max| max.saturating_sub(self.len()));
+        let text: String = text.chars().take(room).collect();
+        self.buffer.push_str(&text);
+        self.updated |= !text.is_empty();
+        self.scroll_to_cursor();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns the visible part of the buffer and the cursor's column within it.
+    ///
+    /// When the text is wider than the display, one column at each edge is
+    /// reserved for the overflow indicators. Widths are in terminal columns, so
+    /// wide characters such as CJK take two.
+    pub fn viewport(&self) -> Viewport {
+        let widths = self.widths();
+        let total: usize = widths.iter().sum();
+        let width = self.display_buffer;
+
+        if width == 0 || total < width {
+            return Viewport {
+                text: self.buffer.clone(),
+                cursor: widths[..self.cursor_position].iter().sum(),
+                overflow_left: false,
+                overflow_right: false,
+            };
+        }
+
+        let inner = Self::inner_width(width);
+        let mut end = self.view_offset;
+        let mut columns = 0;
+        while end < widths.len() && columns + widths[end] <= inner {
+            columns += widths[end];
+            end += 1;
+        }
+        Viewport {
+            text: self.buffer.chars().skip(self.view_offset).take(end - self.view_offset).collect(),
+            cursor: widths[self.view_offset..self.cursor_position].iter().sum::<usize>() + 1,
+            overflow_left: self.view_offset > 0,
+            overflow_right: end < widths.len(),
+        }
+    }
+
+    fn inner_width(width: usize) -> usize {
+        width.saturating_sub(2).max(1)
+    }
+
+    /// How many columns each character takes on screen.
+    fn widths(&self) -> Vec<usize> {
+        self.buffer.chars().map(|c| c.width().unwrap_or(0)).collect()
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        let widths = self.widths();
+        let total: usize = widths.iter().sum();
+        let width = self.display_buffer;
+
+        if width == 0 || total < width {
+            self.view_offset = 0;
+            return;
+        }
+
+        // The cursor takes up the character under it, or one column past the end of the line.
+        let inner = Self::inner_width(width);
+        let cursor = self.cursor_position;
+        let cursor_width = widths.get(cursor).copied().unwrap_or(1);
+        if cursor < self.view_offset {
+            self.view_offset = cursor;
+        }
+        while self.view_offset < cursor
+            && widths[self.view_offset..cursor].iter().sum::<usize>() + cursor_width > inner
+        {
+            self.view_offset += 1;
+        }
+        // Don't leave empty columns on the right while text is hidden on the left. The cursor can
+        // take one more column past the end of the line.
+        while self.view_offset > 0 && widths[self.view_offset - 1..].iter().sum::<usize>() < inner {
+            self.view_offset -= 1;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.buffer.chars().count()
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.buffer
+            .char_indices()
+            .nth(char_index)
+            .map(|(i, _)| i)
+            .unwrap_or(self.buffer.len())
+    }
+
+    fn is_full(&self) -> bool {
+        self.max_buffer.is_some_and(|max| self.len() >= max)
+    }
+
+    /// Finds the start of the word at or before `from`, skipping any delimiters first.
+    fn word_start_before(&self, from: usize, boundary: &WordBoundary) -> usize {
+        let chars: Vec<char> = self.buffer.chars().collect();
+        let mut index = from.min(chars.len());
+        while index > 0 && !boundary.is_word_char(chars[index - 1]) {
+            index -= 1;
+        }
+        while index > 0 && boundary.is_word_char(chars[index - 1]) {
+            index -= 1;
+        }
+        index
+    }
+
+    /// Finds the end of the word at or after `from`, skipping any delimiters first.
+    fn word_end_after(&self, from: usize, boundary: &WordBoundary) -> usize {
+        let chars: Vec<char> = self.buffer.chars().collect();
+        let mut index = from.min(chars.len());
+        while index < chars.len() && !boundary.is_word_char(chars[index]) {
+            index += 1;
+        }
+        while index < chars.len() && boundary.is_word_char(chars[index]) {
+            index += 1;
+        }
+        index
+    }
+
+    fn remove_range(&mut self, start: usize, end: usize) -> String {
+        let (start, end) = (self.byte_index(start), self.byte_index(end));
+        let removed = self.buffer[start..end].to_string();
+        self.buffer.replace_range(start..end, "");
+        if !removed.is_empty() {
+            self.updated = true;
+        }
+        removed
+    }
+
+    fn kill_range(&mut self, start: usize, end: usize, direction: KillDirection, context: &mut EditContext) {
+        let killed = self.remove_range(start, end);
+        context.kill_ring.kill(&killed, direction);
+        self.cursor_position = start;
+    }
+
+    /// Inserts `text` at the cursor, truncated to the maximum length, and remembers where it went for `Alt+Y`.
+    fn yank_text(&mut self, text: &str) -> bool {
+        let room = self.max_buffer.map_or(usize::MAX, |max| max.saturating_sub(self.len()));
+        let text: String = text.chars().take(room).collect();
+        if text.is_empty() {
+            return false;
+        }
+
+        let start = self.cursor_position;
+        let index = self.byte_index(start);
+        self.buffer.insert_str(index, &text);
+        self.cursor_position = start + text.chars().count();
+        self.last_yank = Some((start, self.cursor_position));
+        self.updated = true;
+        true
+    }
+
+    /// Swaps the characters either side of the cursor, or the last two when at the end of the line.
+    fn transpose_chars(&mut self) {
+        let mut chars: Vec<char> = self.buffer.chars().collect();
+        if chars.len() < 2 || self.cursor_position == 0 {
+            return;
+        }
+
+        let right = self.cursor_position.min(chars.len() - 1);
+        chars.swap(right - 1, right);
+        self.buffer = chars.into_iter().collect();
+        self.cursor_position = right + 1;
+        self.updated = true;
+    }
+
+    fn move_cursor_left(&mut self) {
+        let cursor_moved_left = self.cursor_position.saturating_sub(1);
+        self.cursor_position = self.clamp_cursor(cursor_moved_left);
+    }
+
+    fn move_cursor_right(&mut self) {
+        let cursor_moved_right = self.cursor_position.saturating_add(1);
+        self.cursor_position = self.clamp_cursor(cursor_moved_right);
+    }
+
+    fn enter_char(&mut self, new_char: char, type_mode: TypingMode) {
+        let entered = match type_mode {
+            TypingMode::Insert => self.insert_char(new_char),
+            TypingMode::Overwrite => self.replace_char(new_char),
+        };
+
+        if entered {
+            self.move_cursor_right();
+        }
+    }
+
+    fn insert_char(&mut self, new_char: char) -> bool {
+        if self.is_full() {
+            return false;
+        }
+
+        let index = self.byte_index(self.cursor_position);
+        self.buffer.insert(index, new_char);
+        self.updated = true;
+        true
+    }
+
+    fn replace_char(&mut self, new_char: char) -> bool {
+        let is_not_cursor_rightmost = self.cursor_position != self.len();
+
+        if is_not_cursor_rightmost {
+            self.buffer = self
+                .buffer
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if i == self.cursor_position {
+                        new_char
+                    } else {
+                        c
+                    }
+                })
+                .collect()
+        } else if self.is_full() {
+            return false;
+        } else {
+            self.buffer.push(new_char);
+        }
+
+        self.updated = true;
+        true
+    }
+
+    fn backspace_char(&mut self) {
+        let is_not_cursor_leftmost = self.cursor_position != 0;
+        if is_not_cursor_leftmost {
+            let current_index = self.cursor_position;
+            let from_left_to_current_index = current_index - 1;
+
+            let before_char_to_delete = self.buffer.chars().take(from_left_to_current_index);
+            let after_char_to_delete = self.buffer.chars().skip(current_index);
+            self.buffer = before_char_to_delete.chain(after_char_to_delete).collect();
+            self.move_cursor_left();
+            self.updated = true;
+        }
+    }
+
+    fn delete_char(&mut self) {
+        let is_not_cursor_rightmost = self.cursor_position != self.len();
+        if is_not_cursor_rightmost {
+            let current_index = self.cursor_position;
+            let from_left_to_current_index = current_index;
+            let before_char_to_delete = self.buffer.chars().take(from_left_to_current_index);
+            let after_char_to_delete = self.buffer.chars().skip(current_index + 1);
+            self.buffer = before_char_to_delete.chain(after_char_to_delete).collect();
+            self.updated = true;
+        }
+    }
+
+    pub fn reset_cursor(&mut self) {
+        self.cursor_position = 0;
+        self.scroll_to_cursor();
+    }
+
+    fn move_cursor_to_end(&mut self) {
+        self.cursor_position = self.len();
+    }
+
+    fn clamp_cursor(&mut self, new_cursor_pos: usize) -> usize {
+        new_cursor_pos.clamp(0, self.len())
+    }
+}
+
+/// Collapses runs of whitespace (including line breaks) into single spaces and drops other control characters.
+pub fn sanitise(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| word.chars().filter(|c| !c.is_control()).collect::<String>())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Display for LineBuffer {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(value: &str, width: usize, cursor: usize) -> LineBuffer {
+        let mut buffer = LineBuffer::default();
+        buffer.set_value(value);
+        buffer.set_display_width(width);
+        buffer.set_cursor_position(cursor);
+        buffer
+    }
+
+    fn press(buffer: &mut LineBuffer, code: KeyCode) {
+        let mut context = EditContext::default();
+        buffer
+            .handle_key_events(KeyEvent::new(code, KeyModifiers::NONE), TypingMode::Insert, &mut context)
+            .unwrap();
+    }
+
+    #[test]
+    fn text_narrower_than_the_display_is_shown_whole() {
+        let viewport = buffer("abc", 10, 3).viewport();
+        assert_eq!(viewport.text, "abc");
+        assert_eq!(viewport.cursor, 3);
+        assert!(!viewport.overflow_left && !viewport.overflow_right);
+    }
+
+    #[test]
+    fn text_as_wide_as_the_display_scrolls_with_indicators() {
+        let viewport = buffer("abcde", 5, 0).viewport();
+        assert_eq!(viewport.text, "abc");
+        assert_eq!(viewport.cursor, 1);
+        assert!(!viewport.overflow_left);
+        assert!(viewport.overflow_right);
+    }
+
+    #[test]
+    fn cursor_at_the_end_shows_the_tail_and_a_free_column() {
+        let viewport = buffer("abcdefghij", 6, 10).viewport();
+        assert_eq!(viewport.text, "hij");
+        assert_eq!(viewport.cursor, 4);
+        assert!(viewport.overflow_left);
+        assert!(!viewport.overflow_right);
+    }
+
+    #[test]
+    fn moving_left_past_the_offset_scrolls_back() {
+        let mut buffer = buffer("abcdefghij", 6, 10);
+        for _ in 0..4 {
+            press(&mut buffer, KeyCode::Left);
+        }
+        let viewport = buffer.viewport();
+        assert_eq!(buffer.get_cursor_position(), 6);
+        assert_eq!(viewport.text, "ghij");
+        assert_eq!(viewport.cursor, 1);
+        assert!(viewport.overflow_left);
+
+        press(&mut buffer, KeyCode::Home);
+        let viewport = buffer.viewport();
+        assert_eq!(viewport.text, "abcd");
+        assert_eq!(viewport.cursor, 1);
+        assert!(!viewport.overflow_left);
+        assert!(viewport.overflow_right);
+    }
+
+    #[test]
+    fn multi_byte_characters_count_as_one_column() {
+        let viewport = buffer("café société", 8, 12).viewport();
+        assert_eq!(viewport.text, "ciété");
+        assert_eq!(viewport.cursor, 6);
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        let viewport = buffer("電話番号", 20, 4).viewport();
+        assert_eq!(viewport.cursor, 8);
+
+        let mut buffer = buffer("電話番号です", 8, 6);
+        let viewport = buffer.viewport();
+        assert_eq!(viewport.text, "です");
+        assert_eq!(viewport.cursor, 5);
+        assert!(viewport.overflow_left);
+
+        buffer.set_cursor_position(2);
+        let viewport = buffer.viewport();
+        assert_eq!(viewport.text, "番号で");
+        assert_eq!(viewport.cursor, 1);
+        assert!(viewport.overflow_right);
+    }
+
+    fn press_with(buffer: &mut LineBuffer, modifiers: KeyModifiers, code: KeyCode, context: &mut EditContext) {
+        buffer
+            .handle_key_events(KeyEvent::new(code, modifiers), TypingMode::Insert, context)
+            .unwrap();
+    }
+
+    #[test]
+    fn killed_words_can_be_yanked_back() {
+        let mut context = EditContext::default();
+        let mut buffer = buffer("call Jane Smith", 0, 15);
+        press_with(&mut buffer, KeyModifiers::CONTROL, KeyCode::Char('w'), &mut context);
+        press_with(&mut buffer, KeyModifiers::CONTROL, KeyCode::Char('w'), &mut context);
+        assert_eq!(buffer.get_value(), "call ");
+
+        press_with(&mut buffer, KeyModifiers::CONTROL, KeyCode::Char('y'), &mut context);
+        assert_eq!(buffer.get_value(), "call Jane Smith");
+        assert_eq!(buffer.get_cursor_position(), 15);
+    }
+
+    #[test]
+    fn yank_pop_replaces_the_text_just_yanked() {
+        let mut context = EditContext::default();
+        let mut buffer = buffer("one two", 0, 7);
+        press_with(&mut buffer, KeyModifiers::CONTROL, KeyCode::Char('w'), &mut context);
+        press_with(&mut buffer, KeyModifiers::NONE, KeyCode::Home, &mut context);
+        press_with(&mut buffer, KeyModifiers::CONTROL, KeyCode::Char('k'), &mut context);
+        assert_eq!(buffer.get_value(), "");
+
+        press_with(&mut buffer, KeyModifiers::CONTROL, KeyCode::Char('y'), &mut context);
+        assert_eq!(buffer.get_value(), "one ");
+        press_with(&mut buffer, KeyModifiers::ALT, KeyCode::Char('y'), &mut context);
+        assert_eq!(buffer.get_value(), "two");
+        press_with(&mut buffer, KeyModifiers::ALT, KeyCode::Char('y'), &mut context);
+        assert_eq!(buffer.get_value(), "one ");
+
+        // Alt+Y only works straight after a yank.
+        press_with(&mut buffer, KeyModifiers::NONE, KeyCode::Left, &mut context);
+        press_with(&mut buffer, KeyModifiers::ALT, KeyCode::Char('y'), &mut context);
+        assert_eq!(buffer.get_value(), "one ");
+    }
+
+    #[test]
+    fn sanitise_collapses_whitespace_and_drops_control_characters() {
+        assert_eq!(sanitise("  +44 20\n7946\t0000\r\n"), "+44 20 7946 0000");
+        assert_eq!(sanitise("Jane\x1b[31m Smith\x07"), "Jane[31m Smith");
+        assert_eq!(sanitise("\x00\x1f \u{7f}"), "");
+    }
+
+    #[test]
+    fn paste_is_sanitised_and_inserted_at_the_cursor() {
+        let mut buffer = buffer("call ", 0, 5);
+        buffer.paste("Jane\n\tSmith\x1b\r\n");
+        assert_eq!(buffer.get_value(), "call Jane Smith");
+        assert_eq!(buffer.get_cursor_position(), 15);
+    }
+
+    #[test]
+    fn paste_is_truncated_to_the_maximum_length() {
+        let mut buffer = buffer("01", 0, 2);
+        buffer.set_max_length(Some(6));
+        buffer.paste("234\n567\t89");
+        assert_eq!(buffer.get_value(), "01234 ");
+        assert_eq!(buffer.get_cursor_position(), 6);
+
+        buffer.paste("more");
+        assert_eq!(buffer.get_value(), "01234 ");
+    }
+
+    #[test]
+    fn append_respects_the_maximum_length() {
+        let mut buffer = LineBuffer::default();
+        buffer.set_max_length(Some(5));
+        buffer.set_value("abc");
+        buffer.append("defgh");
+        assert_eq!(buffer.get_value(), "abcde");
+        buffer.append("x");
+        assert_eq!(buffer.get_value(), "abcde");
+    }
+}
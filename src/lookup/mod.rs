@@ -0,0 +1,253 @@
+pub mod service;
+
+use crate::data_layer::contact::Contact;
+use crate::data_layer::db::Db;
+use crate::error::AppResult;
+use crate::uri::DialNumber;
+use serde::Serialize;
+
+/// The fewest trailing digits two numbers must share to be the same number written another way,
+/// e.g. `+61 2 9999 1234` and `(02) 9999 1234`.
+const MIN_SUFFIX: usize = 7;
+
+/// The fewest digits worth searching inside other numbers for.
+const MIN_PARTIAL: usize = 3;
+
+/// The most matches a lookup returns. Candidates are ranked first, so the best always make it.
+const MAX_MATCHES: usize = 50;
+
+/// How closely a contact's number matched the one looked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchKind {
+    /// The same digits.
+    Exact,
+    /// The same number with or without its country or trunk prefix.
+    Suffix,
+    /// The contact's number contains the digits looked up.
+    Partial,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LookupMatch {
+    pub id: u64,
+    pub name: String,
+    pub company: Option<String>,
+    pub phone_number: String,
+    #[serde(rename = "match")]
+    pub kind: MatchKind,
+}
+
+impl LookupMatch {
+    fn new(contact: &Contact, kind: MatchKind) -> Self {
+        Self {
+            id: contact.id,
+            name: contact.display_name(),
+            company: contact.company_name.clone().filter(|company| !company.is_empty()),
+            phone_number: contact.phone_number.clone(),
+            kind,
+        }
+    }
+
+    /// The name with the company after it, e.g. `Jane Smith (Acme)`.
+    pub fn label(&self) -> String {
+        match &self.company {
+            Some(company) if *company != self.name => format!("{} ({company})", self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+/// Who a number belongs to, best match first.
+#[derive(Debug, Clone, Serialize)]
+pub struct Lookup {
+    /// The number looked up, as [`normalise`] left it.
+    pub number: String,
+    pub matches: Vec<LookupMatch>,
+}
+
+impl Lookup {
+    pub fn best(&self) -> Option<&LookupMatch> {
+        self.matches.first()
+    }
+
+    /// The best match's name and company, or an empty string if nobody matched.
+    pub fn to_text(&self) -> String {
+        self.best().map(LookupMatch::label).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("lookup results should serialise to JSON")
+    }
+}
+
+/// Finds the contacts `number` could belong to. It can be written any way a softphone passes on
+/// caller ID, including as a URI or a SIP `From` header.
+pub fn lookup(db: &Db, number: &str) -> AppResult<Lookup> {
+    let number = normalise(number);
+    let digits = digits(&number);
+    let significant = significant(digits);
+    let pattern = if significant.len() >= MIN_SUFFIX {
+        &significant[significant.len() - MIN_SUFFIX..]
+    } else if digits.len() >= MIN_PARTIAL {
+        digits
+    } else {
+        return Ok(Lookup { number, matches: vec![] });
+    };
+
+    let mut matches: Vec<LookupMatch> = db
+        .find_by_digits(pattern)?
+        .iter()
+        .filter_map(|contact| match_kind(digits, &contact.phone_number).map(|kind| LookupMatch::new(contact, kind)))
+        .collect();
+    matches.sort_by_key(|m| m.kind);
+    matches.truncate(MAX_MATCHES);
+    Ok(Lookup { number, matches })
+}
+
+/// Reduces a caller ID to digits, with a leading `+` if it's in international form. `00` is taken
+/// as the international prefix.
+pub fn normalise(number: &str) -> String {
+    let mut number = number.trim();
+    // A SIP header such as `"Jane" <sip:0299991234@pbx>`.
+    if let Some((_, address)) = number.split_once('<') {
+        number = address.split('>').next().unwrap_or(address);
+    }
+    let number = match DialNumber::parse_uri(number) {
+        Ok(dial) => dial.number,
+        Err(_) => {
+            let user = number.split('@').next().unwrap_or(number);
+            user.rsplit(':').next().unwrap_or(user).to_string()
+        }
+    };
+
+    let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+    if number.starts_with('+') {
+        format!("+{digits}")
+    } else if let Some(digits) = digits.strip_prefix("00") {
+        format!("+{digits}")
+    } else {
+        digits
+    }
+}
+
+fn digits(number: &str) -> &str {
+    number.trim_start_matches('+')
+}
+
+/// The digits without trunk prefix zeros, which are the part two ways of writing a number share.
+fn significant(digits: &str) -> &str {
+    digits.trim_start_matches('0')
+}
+
+fn match_kind(digits: &str, phone_number: &str) -> Option<MatchKind> {
+    let normalised = normalise(phone_number);
+    let other = self::digits(&normalised);
+    if digits == other {
+        return Some(MatchKind::Exact);
+    }
+
+    let (a, b) = (significant(digits), significant(other));
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if shorter.len() >= MIN_SUFFIX && longer.ends_with(shorter) {
+        Some(MatchKind::Suffix)
+    } else if digits.len() >= MIN_PARTIAL && other.contains(digits) {
+        Some(MatchKind::Partial)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::data_layer::contact::ContactForUpdate;
+    use crate::data_layer::migrations;
+    use rusqlite::Connection;
+    use std::sync::{Arc, Mutex};
+
+    /// A database holding a contact for each `(first name, phone number)`.
+    pub fn contacts(contacts: &[(&str, &str)]) -> Arc<Mutex<Option<Connection>>> {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(migrations::BASELINE, []).unwrap();
+        migrations::run(&mut conn).unwrap();
+        let conn = Arc::new(Mutex::new(Some(conn)));
+
+        let db = Db::new(conn.clone());
+        for (first_name, phone_number) in contacts {
+            let contact = ContactForUpdate {
+                first_name: Some(first_name.to_string()),
+                last_name: Some("Smith".to_string()),
+                company_name: None,
+                phone_number: phone_number.to_string(),
+                email: None,
+                favourite: false,
+                speed_dial: None,
+                dtmf: None,
+            };
+            db.insert(contact).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn normalise_keeps_digits_and_a_leading_plus() {
+        assert_eq!(normalise(" (02) 9999-1234 "), "0299991234");
+        assert_eq!(normalise("+61 2 9999 1234"), "+61299991234");
+        assert_eq!(normalise("0061 2 9999 1234"), "+61299991234");
+    }
+
+    #[test]
+    fn normalise_reads_uris_and_sip_headers() {
+        assert_eq!(normalise("tel:+61-2-9999-1234"), "+61299991234");
+        assert_eq!(normalise("sip:0299991234@pbx.example.com"), "0299991234");
+        assert_eq!(normalise("\"Jane Smith\" <sip:+61299991234@pbx.example.com>;tag=a1b2"), "+61299991234");
+        assert_eq!(normalise("<sip:1001@pbx.example.com>"), "1001");
+        assert_eq!(normalise("sip:jane@pbx.example.com"), "");
+    }
+
+    #[test]
+    fn the_same_digits_match_exactly() {
+        assert_eq!(match_kind("61299991234", "+61 2 9999 1234"), Some(MatchKind::Exact));
+        assert_eq!(match_kind("0299991234", "(02) 9999-1234"), Some(MatchKind::Exact));
+    }
+
+    #[test]
+    fn numbers_with_and_without_a_prefix_match_by_suffix() {
+        assert_eq!(match_kind("61299991234", "(02) 9999 1234"), Some(MatchKind::Suffix));
+        assert_eq!(match_kind("0299991234", "0061 2 9999 1234"), Some(MatchKind::Suffix));
+        assert_eq!(match_kind("99991234", "+61 2 9999 1234"), Some(MatchKind::Suffix));
+    }
+
+    #[test]
+    fn short_numbers_match_inside_longer_ones() {
+        assert_eq!(match_kind("1234", "02 9999 1234"), Some(MatchKind::Partial));
+        assert_eq!(match_kind("12", "02 9999 1234"), None);
+        assert_eq!(match_kind("0299991235", "02 9999 1234"), None);
+    }
+
+    #[test]
+    fn matches_are_ranked_before_they_are_cut_short() {
+        // More partial matches than are returned, all sorting ahead of the exact one by name.
+        let mut numbers: Vec<(String, String)> =
+            (0..MAX_MATCHES + 10).map(|i| (format!("Aaron {i:02}"), format!("0299{i:02}1234"))).collect();
+        numbers.push(("Zoe".to_string(), "1234".to_string()));
+        let numbers: Vec<_> = numbers.iter().map(|(name, number)| (name.as_str(), number.as_str())).collect();
+        let db = Db::new(contacts(&numbers));
+
+        let lookup = lookup(&db, "1234").unwrap();
+        assert_eq!(lookup.matches.len(), MAX_MATCHES);
+        let best = lookup.best().unwrap();
+        assert_eq!((best.name.as_str(), best.kind), ("Zoe Smith", MatchKind::Exact));
+        assert!(lookup.matches[1..].iter().all(|m| m.kind == MatchKind::Partial));
+    }
+
+    #[test]
+    fn a_lookup_reports_its_best_match() {
+        let db = Db::new(contacts(&[("Jane", "(02) 9999 1234")]));
+        let lookup = lookup(&db, "+61 2 9999 1234").unwrap();
+        assert_eq!(lookup.best().map(|m| m.kind), Some(MatchKind::Suffix));
+        assert_eq!(lookup.to_text(), "Jane Smith");
+        assert!(lookup.to_json().contains("\"match\":\"suffix\""));
+    }
+}
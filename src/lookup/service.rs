@@ -0,0 +1,451 @@
+use crate::data_layer::db::Db;
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use crate::lookup::{self, Lookup};
+use crate::uri::unescape;
+use rusqlite::Connection;
+use std::fs::{DirBuilder, Permissions};
+use std::net::Ipv4Addr;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+/// The longest request line accepted, so a confused client can't make us buffer freely.
+const MAX_LINE: usize = 1024;
+
+const MAX_HEADERS: usize = 100;
+
+/// How long an HTTP client gets to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the lookup service listens, from the `DIALLER_LOOKUP_*` environment variables.
+#[derive(Debug, Clone, Default)]
+pub struct LookupConfig {
+    /// The Unix socket to answer on, if any.
+    pub socket: Option<PathBuf>,
+    /// The port to answer HTTP on, on the loopback address only.
+    pub http_port: Option<u16>,
+    /// A secret HTTP clients must send as `Authorization: Bearer <token>` or `?token=<token>`.
+    pub http_token: Option<String>,
+    /// Whether the app pops up who a number belongs to when another program looks it up.
+    pub popup: bool,
+}
+
+/// Answers "who is this number?" for other programs while the app runs, such as a softphone's
+/// hook for incoming calls. Dropping it stops listening and removes the socket.
+#[derive(Debug, Default)]
+pub struct LookupService {
+    tasks: Vec<JoinHandle<()>>,
+    socket: Option<PathBuf>,
+}
+
+impl LookupService {
+    pub fn start(
+        config: &LookupConfig,
+        conn: Arc<Mutex<Option<Connection>>>,
+        events: Option<UnboundedSender<Event>>,
+    ) -> AppResult<Self> {
+        let lookups = Lookups { conn, events };
+        let mut service = Self::default();
+
+        if let Some(path) = &config.socket {
+            let listener = bind_socket(path)?;
+            info!("Answering lookups on {}", path.display());
+            service.socket = Some(path.clone());
+            service.tasks.push(tokio::spawn(accept_socket(listener, lookups.clone())));
+        }
+
+        if let Some(port) = config.http_port {
+            let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+                .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+                .and_then(TcpListener::from_std)
+                .map_err(|err| lookup_error(format!("could not listen on port {port}: {err}")))?;
+            info!("Answering HTTP lookups on port {port}");
+            service.tasks.push(tokio::spawn(accept_http(listener, lookups, config.http_token.clone())));
+        }
+
+        Ok(service)
+    }
+}
+
+impl Drop for LookupService {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        if let Some(path) = &self.socket {
+            if let Err(err) = std::fs::remove_file(path) {
+                warn!("Could not remove the lookup socket {}: {err}", path.display());
+            }
+        }
+    }
+}
+
+fn lookup_error(message: impl Into<String>) -> Error {
+    Error::LookupService(message.into())
+}
+
+/// Binds the socket only the user can connect to, replacing one left behind by an instance that
+/// didn't shut down cleanly. It's bound in a directory only the user can enter and moved into
+/// place once its permissions are set, so nobody else can connect in between.
+fn bind_socket(path: &Path) -> AppResult<UnixListener> {
+    let failed = |err: std::io::Error| lookup_error(format!("could not listen on {}: {err}", path.display()));
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(lookup_error(format!("another dialler-rs is already answering on {}", path.display())));
+        }
+        std::fs::remove_file(path).map_err(failed)?;
+    }
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent).map_err(failed)?;
+
+    let private = parent.join(format!(".dialler-lookup-{}", std::process::id()));
+    DirBuilder::new().mode(0o700).create(&private).map_err(failed)?;
+    let staged = private.join("lookup.sock");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    if bound.is_err() {
+        std::fs::remove_file(&staged).ok();
+    }
+    if let Err(err) = std::fs::remove_dir(&private) {
+        warn!("Could not remove {}: {err}", private.display());
+    }
+    bound.map_err(failed)
+}
+
+/// Looks numbers up for connections, and tells the app about each one.
+#[derive(Clone)]
+struct Lookups {
+    conn: Arc<Mutex<Option<Connection>>>,
+    events: Option<UnboundedSender<Event>>,
+}
+
+impl Lookups {
+    /// Looks `number` up on a blocking thread, since the database is behind a std mutex the UI
+    /// holds too.
+    async fn lookup(&self, number: &str) -> AppResult<Lookup> {
+        let db = Db::new(self.conn.clone());
+        let number = number.to_string();
+        let lookup = tokio::task::spawn_blocking(move || lookup::lookup(&db, &number))
+            .await
+            .map_err(|err| lookup_error(format!("the lookup stopped: {err}")))??;
+        info!("Looked up {}: {} matches", lookup.number, lookup.matches.len());
+        if let Some(events) = self.events.as_ref().filter(|_| !lookup.number.is_empty()) {
+            if events.send(Event::Lookup(lookup.clone())).is_err() {
+                warn!("Lookup arrived after the app closed");
+            }
+        }
+        Ok(lookup)
+    }
+}
+
+async fn accept_socket(listener: UnixListener, lookups: Lookups) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let lookups = lookups.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = serve_socket(stream, &lookups).await {
+                        warn!("Lookup socket connection failed: {}", err.full_message());
+                    }
+                });
+            }
+            Err(err) => {
+                warn!("Stopped answering lookups on the socket: {err}");
+                return;
+            }
+        }
+    }
+}
+
+/// Answers one line per number: the best match's name and company, or an empty line if nobody
+/// matched. Numbers sent as `json <number>` get the whole [`Lookup`] as JSON instead.
+async fn serve_socket(stream: UnixStream, lookups: &Lookups) -> AppResult<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    while let Some(line) = read_line(&mut reader).await? {
+        if line.is_empty() {
+            continue;
+        }
+        let reply = match line.strip_prefix("json ") {
+            Some(number) => lookups.lookup(number).await?.to_json(),
+            None => lookups.lookup(&line).await?.to_text(),
+        };
+        writer.write_all(format!("{reply}\n").as_bytes()).await?;
+    }
+    Ok(())
+}
+
+async fn accept_http(listener: TcpListener, lookups: Lookups, token: Option<String>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let lookups = lookups.clone();
+                let token = token.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = serve_http(stream, &lookups, token.as_deref()).await {
+                        warn!("HTTP lookup failed: {}", err.full_message());
+                    }
+                });
+            }
+            Err(err) => {
+                warn!("Stopped answering HTTP lookups: {err}");
+                return;
+            }
+        }
+    }
+}
+
+/// Answers `GET /lookup?number=<number>` or `GET /lookup/<number>` with the [`Lookup`] as JSON,
+/// or with `format=text` the best match's name and company, or a 404 if nobody matched.
+async fn serve_http(stream: TcpStream, lookups: &Lookups, token: Option<&str>) -> AppResult<()> {
+    let mut stream = BufReader::new(stream);
+    let Ok(request) = timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await else {
+        return Ok(());
+    };
+    let Some(request) = request? else {
+        return Ok(());
+    };
+
+    let (status, content_type, body) = respond(&request, lookups, token).await;
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let stream = stream.get_mut();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+const TEXT: &str = "text/plain; charset=utf-8";
+const JSON: &str = "application/json";
+
+async fn respond(request: &Request, lookups: &Lookups, token: Option<&str>) -> (&'static str, &'static str, String) {
+    // A web page can only reach the port through DNS rebinding, which leaves its own host name in
+    // the Host header.
+    if !request.host.as_deref().is_some_and(is_loopback_host) {
+        return ("403 Forbidden", TEXT, "Only requests to 127.0.0.1 or localhost are answered\n".to_string());
+    }
+    if request.method != "GET" {
+        return ("405 Method Not Allowed", TEXT, "Only GET is supported\n".to_string());
+    }
+
+    let target = request.target.as_str();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| unescape(value))
+    };
+    if let Some(token) = token {
+        let bearer = request.authorization.as_deref().and_then(|auth| auth.strip_prefix("Bearer "));
+        if bearer.map(str::trim) != Some(token) && param("token").as_deref() != Some(token) {
+            return ("401 Unauthorized", TEXT, "Pass the token set in DIALLER_LOOKUP_TOKEN\n".to_string());
+        }
+    }
+    let number = match path.strip_prefix("/lookup") {
+        Some("" | "/") => param("number"),
+        Some(rest) => rest.strip_prefix('/').map(unescape),
+        None => return ("404 Not Found", TEXT, "Try /lookup?number=<number>\n".to_string()),
+    };
+    let Some(number) = number.filter(|number| !number.trim().is_empty()) else {
+        return ("400 Bad Request", TEXT, "Pass a number, e.g. /lookup?number=0299991234\n".to_string());
+    };
+
+    let lookup = match lookups.lookup(&number).await {
+        Ok(lookup) => lookup,
+        Err(err) => {
+            warn!("HTTP lookup of {number} failed: {}", err.full_message());
+            return ("500 Internal Server Error", TEXT, format!("{}\n", err.full_message()));
+        }
+    };
+    match param("format").as_deref() {
+        Some("text") if lookup.best().is_none() => ("404 Not Found", TEXT, String::new()),
+        Some("text") => ("200 OK", TEXT, format!("{}\n", lookup.to_text())),
+        _ => ("200 OK", JSON, lookup.to_json()),
+    }
+}
+
+/// Whether a `Host` header names the loopback address the service listens on.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    name == "127.0.0.1" || name.eq_ignore_ascii_case("localhost")
+}
+
+/// The parts of an HTTP request the service looks at.
+#[derive(Debug, Default, PartialEq)]
+struct Request {
+    method: String,
+    target: String,
+    host: Option<String>,
+    authorization: Option<String>,
+}
+
+/// Reads the request line and headers, keeping the headers the service needs.
+async fn read_request(reader: &mut (impl AsyncBufRead + Unpin)) -> AppResult<Option<Request>> {
+    let Some(request_line) = read_line(reader).await? else {
+        return Ok(None);
+    };
+    let mut parts = request_line.split_whitespace();
+    let mut request = Request {
+        method: parts.next().unwrap_or_default().to_string(),
+        target: parts.next().unwrap_or_default().to_string(),
+        ..Request::default()
+    };
+
+    let mut headers = 0;
+    while let Some(line) = read_line(reader).await? {
+        if line.is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return Err(lookup_error("the request had too many headers"));
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = Some(value.trim().to_string());
+        if name.eq_ignore_ascii_case("Host") {
+            request.host = value;
+        } else if name.eq_ignore_ascii_case("Authorization") {
+            request.authorization = value;
+        }
+    }
+    Ok(Some(request))
+}
+
+/// The next line without its line ending, or `None` at the end of the stream.
+async fn read_line(reader: &mut (impl AsyncBufRead + Unpin)) -> AppResult<Option<String>> {
+    let mut line = String::new();
+    let read = (&mut *reader).take(MAX_LINE as u64).read_line(&mut line).await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && read == MAX_LINE {
+        return Err(lookup_error("the request was too long"));
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lookup::tests::contacts;
+
+    fn lookups() -> Lookups {
+        Lookups {
+            conn: contacts(&[("Jane", "(02) 9999 1234")]),
+            events: None,
+        }
+    }
+
+    fn get(target: &str) -> Request {
+        Request {
+            method: "GET".to_string(),
+            target: target.to_string(),
+            host: Some("127.0.0.1:8765".to_string()),
+            authorization: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn numbers_are_looked_up_by_path_or_query() {
+        let lookups = lookups();
+        let (status, content_type, body) = respond(&get("/lookup/02%209999%201234"), &lookups, None).await;
+        assert_eq!((status, content_type), ("200 OK", JSON));
+        assert!(body.contains("\"match\":\"exact\""), "{body}");
+
+        let text = respond(&get("/lookup?number=%2B61299991234&format=text"), &lookups, None).await;
+        assert_eq!(text, ("200 OK", TEXT, "Jane Smith\n".to_string()));
+        let nobody = respond(&get("/lookup?number=0400000000&format=text"), &lookups, None).await;
+        assert_eq!(nobody.0, "404 Not Found");
+        assert_eq!(respond(&get("/lookup"), &lookups, None).await.0, "400 Bad Request");
+    }
+
+    #[tokio::test]
+    async fn requests_for_other_hosts_are_refused() {
+        let lookups = lookups();
+        for host in ["localhost:8765", "LOCALHOST", "127.0.0.1"] {
+            let request = Request {
+                host: Some(host.to_string()),
+                ..get("/lookup/0299991234")
+            };
+            assert_eq!(respond(&request, &lookups, None).await.0, "200 OK", "{host}");
+        }
+        for host in [Some("attacker.example.com:8765"), Some("localhost.attacker.example.com"), None] {
+            let request = Request {
+                host: host.map(str::to_string),
+                ..get("/lookup/0299991234")
+            };
+            assert_eq!(respond(&request, &lookups, None).await.0, "403 Forbidden", "{host:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn a_configured_token_must_be_sent() {
+        let lookups = lookups();
+        let token = Some("s3cret");
+        assert_eq!(respond(&get("/lookup/0299991234"), &lookups, token).await.0, "401 Unauthorized");
+        assert_eq!(respond(&get("/lookup/0299991234?token=wrong"), &lookups, token).await.0, "401 Unauthorized");
+        assert_eq!(respond(&get("/lookup/0299991234?token=s3cret"), &lookups, token).await.0, "200 OK");
+
+        let request = Request {
+            authorization: Some("Bearer s3cret".to_string()),
+            ..get("/lookup/0299991234")
+        };
+        assert_eq!(respond(&request, &lookups, token).await.0, "200 OK");
+    }
+
+    #[tokio::test]
+    async fn requests_keep_the_host_and_authorization_headers() {
+        let mut wire: &[u8] = b"GET /lookup/1234 HTTP/1.1\r\nhost: localhost:8765\r\nAccept: */*\r\n\
+                                Authorization: Bearer s3cret\r\n\r\n";
+        let request = read_request(&mut wire).await.unwrap().unwrap();
+        assert_eq!(
+            request,
+            Request {
+                method: "GET".to_string(),
+                target: "/lookup/1234".to_string(),
+                host: Some("localhost:8765".to_string()),
+                authorization: Some("Bearer s3cret".to_string()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn the_socket_is_only_open_to_the_user() {
+        let dir = std::env::temp_dir().join(format!("dialler-lookup-test-{}", std::process::id()));
+        let path = dir.join("lookup.sock");
+        let listener = bind_socket(&path).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let left_behind: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(left_behind, ["lookup.sock"]);
+
+        // It still answers where it was moved to, and refuses to be bound twice.
+        UnixStream::connect(&path).await.unwrap();
+        listener.accept().await.unwrap();
+        assert!(bind_socket(&path).is_err());
+
+        drop(listener);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,16 +1,22 @@
 use dialler_rs::app::App;
-use dialler_rs::error::AppResult;
+use dialler_rs::config::Config;
+use dialler_rs::data_layer::db::Db;
+use dialler_rs::error::{AppResult, Error};
 use dialler_rs::event::EventHandler;
+use dialler_rs::lookup;
 
 use dialler_rs::tui::Tui;
+use dialler_rs::uri::DialNumber;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use std::io;
-use tracing::info;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
 #[tokio::main]
-async fn main() -> AppResult<()> {
+async fn main() -> ExitCode {
     let file_appender = tracing_appender::rolling::daily("/home/shane/logs", "dialler.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
@@ -23,23 +29,105 @@ async fn main() -> AppResult<()> {
     tracing::subscriber::set_global_default(subscriber)
         .expect("Could not set default tracing subscriber");
 
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("Exiting with error: {}", err.full_message());
+            report(&err);
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+async fn run() -> AppResult<()> {
     info!("Started Application");
+    let dial = match command()? {
+        Command::Run => None,
+        Command::Dial(number) => Some(number),
+        Command::Lookup { number, json } => return lookup(&number, json),
+    };
     let mut app = App::new()?;
     app.get_contacts()?;
 
     let backend = CrosstermBackend::new(io::stderr());
-    let terminal = Terminal::new(backend)?;
+    let terminal = Terminal::new(backend).map_err(Error::Terminal)?;
     let events = EventHandler::new(250);
+    app.set_event_sender(events.sender());
+    app.start_lookup_service();
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
 
+    if let Some(number) = dial {
+        if let Err(err) = app.dial_number(number) {
+            tui.exit()?;
+            return Err(err);
+        }
+    }
+
+    let result = run_loop(&mut tui, &mut app).await;
+
+    tui.exit()?;
+    result
+}
+
+async fn run_loop(tui: &mut Tui<CrosstermBackend<io::Stderr>>, app: &mut App) -> AppResult<()> {
     while app.running {
-        tui.draw(&mut app)?;
+        tui.draw(app)?;
 
         let event = tui.events.next().await?;
         app.handle_event(event)?;
     }
 
-    tui.exit()?;
     Ok(())
 }
+
+/// What the command line asked for.
+enum Command {
+    Run,
+    /// `dialler-rs dial <URI>`, which lets the app be registered as the handler for `tel:`, `sip:`
+    /// and `callto:` links.
+    Dial(DialNumber),
+    /// `dialler-rs lookup [--json] <number>`, for scripts that want to know who is calling.
+    Lookup { number: String, json: bool },
+}
+
+fn command() -> AppResult<Command> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => Ok(Command::Run),
+        [command, uri] if command == "dial" => DialNumber::parse_uri(uri).map(Command::Dial),
+        [command] if command == "dial" => Err(Error::Usage("dial needs a URI, e.g. dialler-rs dial tel:+61299991234".to_string())),
+        [command, rest @ ..] if command == "lookup" => {
+            let json = rest.iter().any(|arg| arg == "--json");
+            // Numbers are often written with spaces, so the words are taken as one number.
+            let number = rest.iter().filter(|arg| *arg != "--json").cloned().collect::<Vec<_>>().join(" ");
+            if number.trim().is_empty() {
+                return Err(Error::Usage("lookup needs a number, e.g. dialler-rs lookup 0299991234".to_string()));
+            }
+            Ok(Command::Lookup { number, json })
+        }
+        [command, ..] => Err(Error::Usage(format!("unknown command \"{command}\""))),
+    }
+}
+
+/// Prints who `number` belongs to: the best match's name and company, or every match as JSON.
+fn lookup(number: &str, json: bool) -> AppResult<()> {
+    let db = Db::new(Arc::new(Mutex::new(Some(Config::create_db()?))));
+    let lookup = lookup::lookup(&db, number)?;
+    if json {
+        println!("{}", lookup.to_json());
+    } else if lookup.best().is_some() {
+        println!("{}", lookup.to_text());
+    }
+    match lookup.best() {
+        Some(_) => Ok(()),
+        None => Err(Error::UnknownNumber(lookup.number)),
+    }
+}
+
+fn report(err: &Error) {
+    eprintln!("dialler-rs: {}", err.full_message());
+    if let Some(hint) = err.hint() {
+        eprintln!("hint: {hint}");
+    }
+}
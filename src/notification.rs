@@ -0,0 +1,124 @@
+use crate::error::Error;
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use tracing::{error, info, warn};
+
+/// How many `Event::Tick`s a toast stays on screen, by severity.
+const INFO_TICKS: u32 = 16;
+const ERROR_TICKS: u32 = 40;
+const MAX_ACTIVE: usize = 4;
+const MAX_HISTORY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Info => "Info",
+            Severity::Success => "Success",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        }
+    }
+
+    fn ticks(&self) -> u32 {
+        match self {
+            Severity::Info | Severity::Success => INFO_TICKS,
+            Severity::Warning | Severity::Error => ERROR_TICKS,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub message: String,
+    pub severity: Severity,
+    pub created: DateTime<Local>,
+    remaining_ticks: u32,
+}
+
+/// Toasts currently on screen, plus a log of everything shown this session.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    active: VecDeque<Notification>,
+    history: VecDeque<Notification>,
+}
+
+impl Notifications {
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        let message = message.into();
+        match severity {
+            Severity::Error => error!("{message}"),
+            Severity::Warning => warn!("{message}"),
+            Severity::Info | Severity::Success => info!("{message}"),
+        }
+
+        let notification = Notification {
+            message,
+            severity,
+            created: Local::now(),
+            remaining_ticks: severity.ticks(),
+        };
+
+        self.history.push_front(notification.clone());
+        self.history.truncate(MAX_HISTORY);
+
+        self.active.push_back(notification);
+        if self.active.len() > MAX_ACTIVE {
+            self.active.pop_front();
+        }
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Severity::Info, message);
+    }
+
+    pub fn success(&mut self, message: impl Into<String>) {
+        self.push(Severity::Success, message);
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.push(Severity::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Severity::Error, message);
+    }
+
+    /// Shows `err`, with its causes and any recovery hint, as an error prefixed by `context`.
+    pub fn report(&mut self, context: impl Display, err: &Error) {
+        let mut message = format!("{context}: {}", err.full_message());
+        if let Some(hint) = err.hint() {
+            message.push_str(&format!("\nHint: {hint}"));
+        }
+        self.error(message);
+    }
+
+    /// Ages the active toasts, dropping any that have expired.
+    pub fn tick(&mut self) {
+        for notification in self.active.iter_mut() {
+            notification.remaining_ticks = notification.remaining_ticks.saturating_sub(1);
+        }
+        self.active.retain(|notification| notification.remaining_ticks > 0);
+    }
+
+    pub fn dismiss_all(&mut self) {
+        self.active.clear();
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &Notification> {
+        self.active.iter()
+    }
+
+    /// Past notifications, newest first.
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter()
+    }
+}
@@ -1,96 +1,543 @@
 use crate::app::App;
-use crate::dialog::dialog_field::DialogField;
+use crate::dialler::CallState;
+use crate::dialog::dialog_field::{DialogField, FieldKind};
+use crate::dialog::modal::Modal;
+use crate::dialog::popup::{Popup, PopupKind};
+use crate::line_buffer::Viewport;
+use crate::notification::{Notification, Severity};
+use crate::screen::command_palette::CommandPalette;
+use crate::screen::dial_pad::{DialPad, MAX_MATCHES};
+use crate::screen::help::{Help, HelpLine};
+use crate::screen::notification_log::NotificationLog;
+use crate::screen::template_list::TemplateList;
+use crate::screen::template_picker::TemplatePicker;
 use ratatui::prelude::{
-    Alignment, Color, Constraint, Direction, Layout, Line, Margin, Rect, Span, Style, Stylize,
+    Alignment, Color, Constraint, Direction, Layout, Line, Margin, Modifier, Rect, Span, Style, Stylize,
 };
-use ratatui::widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Wrap, ScrollbarOrientation, Scrollbar, ScrollbarState, ListState};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, Paragraph, Wrap, ScrollbarOrientation, Scrollbar, ScrollbarState, ListState};
 use ratatui::Frame;
 use ratatui::symbols::scrollbar;
 use tracing::info;
 
-pub struct Renderer;
+/// How many favourites the pinned pane shows before the rest are cut off.
+const MAX_FAVOURITE_ROWS: usize = 5;
 
-impl Renderer {
+/// The template manager and picker's size, including borders.
+const TEMPLATE_MENU_SIZE: (u16, u16) = (64, 22);
 
-    pub fn render_edit_contact_modal(app: &mut App, frame: &mut Frame) {
-        Self::render_contact_modal("Edit Contact", app, frame);
-    }
+/// How many template names the manager and picker show before scrolling.
+const TEMPLATE_MENU_ROWS: u16 = 8;
 
-    pub fn render_add_contact_modal(app: &mut App, frame: &mut Frame) {
-        Self::render_contact_modal("Add Contact", app, frame);
+pub struct Renderer;
+
+impl Renderer {
+    /// Fades everything drawn so far, so an overlay stands out from the layers beneath it.
+    pub fn dim(frame: &mut Frame) {
+        let area = frame.size();
+        frame
+            .buffer_mut()
+            .set_style(area, Style::default().fg(Color::DarkGray).add_modifier(Modifier::DIM));
     }
 
-    pub fn render_contact_modal(title: impl Into<String>, app: &mut App, frame: &mut Frame) {
+    /// Draws any [`Modal`] centred on screen, sized to fit its fields.
+    pub fn render_modal(modal: &mut Modal, frame: &mut Frame, focused: bool) {
         let size = frame.size();
-
-        let center_area = get_center_area((40, 14), size);
+        let center_area = get_center_area(modal.get_size(), size);
 
         let centered_box = Block::default()
-            .title(title.into())
+            .title(modal.title.as_str())
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::Cyan).bg(Color::Black));
 
+        frame.render_widget(Clear, center_area);
         frame.render_widget(centered_box, center_area);
 
         let center = center_area.inner(&Margin::new(2, 1));
 
+        let mut constraints = vec![Constraint::Length(1)];
+        constraints.extend(modal.fields.iter().map(|field| Constraint::Length(field.height() + 1)));
+        constraints.push(Constraint::Min(0));
+
         let field_areas = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(1),
-                Constraint::Length(2),
-                Constraint::Length(2),
-                Constraint::Length(2),
-                Constraint::Length(2),
-                Constraint::Length(3),
-            ])
+            .constraints(constraints)
             .split(center);
 
-        let max_label = app.state.modal.get_max_label();
+        let max_label = modal.get_max_label();
 
         draw_fields_in_rects(
             frame,
-            &app.state.modal.fields,
+            &mut modal.fields,
             &field_areas[1..],
             max_label,
-            app.state.modal.focused_index,
+            modal.focused_index,
+            focused,
+        );
+
+        if let Some(error) = &modal.error {
+            frame.render_widget(
+                Paragraph::new(error.as_str())
+                    .style(Style::default().fg(Color::Red).bg(Color::Black))
+                    .wrap(Wrap::default()),
+                field_areas[field_areas.len() - 1],
+            );
+        } else if let Some(note) = &modal.note {
+            frame.render_widget(
+                Paragraph::new(note.as_str())
+                    .style(Style::default().fg(Color::Gray).bg(Color::Black))
+                    .wrap(Wrap::default()),
+                field_areas[field_areas.len() - 1],
+            );
+        }
+    }
+
+    pub fn render_paste_signature_modal(_: &mut App, frame: &mut Frame) {
+        let center_area = get_center_area((44, 8), frame.size());
+
+        frame.render_widget(Clear, center_area);
+        frame.render_widget(
+            Block::default()
+                .title("Add From Paste")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Cyan).bg(Color::Black)),
+            center_area,
+        );
+
+        let style = Style::default().fg(Color::Gray).bg(Color::Black);
+        let text = vec![
+            Line::from(Span::styled("Paste an email signature or contact details now.", style)),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Guesses are shown in ", style),
+                Span::styled("yellow", style.fg(Color::Yellow)),
+                Span::styled(" for review. ESC to cancel.", style),
+            ]),
+        ];
+
+        frame.render_widget(
+            Paragraph::new(text).wrap(Wrap::default()),
+            center_area.inner(&Margin::new(2, 1)),
         );
     }
 
-    pub fn render_delete_confirmation_modal(_: &mut App, frame: &mut Frame) {
+    /// Draws the active toasts stacked down the top-right corner.
+    pub fn render_notifications(app: &mut App, frame: &mut Frame) {
         let size = frame.size();
+        let width = size.width.saturating_sub(2).min(50);
+        let mut y = size.y + 1;
 
-        let center_area = get_center_area((25, 7), size);
+        for notification in app.state.notifications.active() {
+            let lines = wrap_text(&notification.message, width.saturating_sub(2));
+            let height = lines.len() as u16 + 2;
+            if y + height > size.bottom() {
+                break;
+            }
+
+            let area = Rect::new(size.right().saturating_sub(width + 1), y, width, height);
+            let colour = severity_colour(notification.severity);
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(lines.join("\n"))
+                    .style(Style::default().fg(Color::White).bg(Color::Black))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Rounded)
+                            .title(format!(" {} ", notification.severity.label()))
+                            .style(Style::default().fg(colour).bg(Color::Black)),
+                    ),
+                area,
+            );
+            y += height;
+        }
+    }
 
+    pub fn render_notification_log(log: &mut NotificationLog, app: &App, frame: &mut Frame) {
+        let size = frame.size();
+        let center_area = get_center_area((size.width.saturating_sub(8).max(40), size.height.saturating_sub(4).max(8)), size);
+        let text_area = center_area.inner(&Margin::new(1, 1));
+
+        let lines: Vec<_> = app
+            .state
+            .notifications
+            .history()
+            .flat_map(|notification| notification_log_lines(notification, text_area.width))
+            .collect();
+        let empty = lines.is_empty();
+        log.clamp_scroll(lines.len(), text_area.height as usize);
+
+        frame.render_widget(Clear, center_area);
         frame.render_widget(
-            Block::default().title("Delete?").borders(Borders::ALL).style(
-                Style::default().fg(Color::Red).bg(Color::Black),
-            ),
+            Block::default()
+                .title("Messages (Up/Down/PgUp/PgDn to scroll, Esc or q to close)")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Cyan).bg(Color::Black)),
             center_area,
         );
 
+        if empty {
+            frame.render_widget(
+                Paragraph::new("No messages yet").style(Style::default().fg(Color::Gray).bg(Color::Black)),
+                center_area.inner(&Margin::new(2, 1)),
+            );
+            return;
+        }
+
+        let visible: Vec<_> = lines.into_iter().skip(log.scroll).take(text_area.height as usize).collect();
+        frame.render_widget(Paragraph::new(visible).style(Style::default().bg(Color::Black)), text_area);
+    }
+
+    /// Draws a [`Popup`] centred on screen, sized to fit its wrapped message.
+    pub fn render_popup(popup: &mut Popup, frame: &mut Frame, focused: bool) {
+        let size = frame.size();
+        let longest_option = match &popup.kind {
+            PopupKind::Choice { options, .. } => options.iter().map(|option| option.chars().count() + 4).max().unwrap_or(0),
+            _ => 0,
+        };
+        let natural_width = popup
+            .message
+            .chars()
+            .count()
+            .max(popup.title.chars().count())
+            .max(longest_option) as u16
+            + 4;
+        let width = natural_width.clamp(30, 60).min(size.width.saturating_sub(2));
+        let inner_width = width.saturating_sub(4);
+
+        let message = wrap_text(&popup.message, inner_width);
+        let controls_height = match &popup.kind {
+            PopupKind::Choice { options, .. } => options.len() as u16,
+            _ => 1,
+        };
+        let height = (message.len() as u16 + controls_height + 3).min(size.height.saturating_sub(2));
+        let area = get_center_area((width, height + 2), size);
+
+        let colour = if popup.danger { Color::Red } else { Color::Cyan };
+        let style = Style::default().fg(Color::Gray).bg(Color::Black);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Block::default()
+                .title(popup.title.as_str())
+                .borders(Borders::ALL)
+                .style(Style::default().fg(colour).bg(Color::Black)),
+            area,
+        );
+
+        let [message_area, _, controls_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(message.len() as u16),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .areas(area.inner(&Margin::new(2, 1)));
+
+        let lines: Vec<Line> = message.into_iter().map(|line| Line::styled(line, style)).collect();
+        frame.render_widget(Paragraph::new(lines), message_area);
+
+        let selected = Style::default().fg(Color::Black).bg(colour).bold();
+        match &mut popup.kind {
+            PopupKind::Confirm { yes_selected } => {
+                let (yes, no) = if *yes_selected { (selected, style) } else { (style, selected) };
+                let line = Line::from(vec![
+                    Span::styled(" Yes (y) ", yes),
+                    Span::styled("   ", style),
+                    Span::styled(" No (n) ", no),
+                ]);
+                frame.render_widget(Paragraph::new(line).alignment(Alignment::Center), controls_area);
+            }
+            PopupKind::Alert => {
+                frame.render_widget(
+                    Paragraph::new(Span::styled(" OK ", selected)).alignment(Alignment::Center),
+                    controls_area,
+                );
+            }
+            PopupKind::Prompt(buffer) => {
+                let input_style = Style::default().fg(Color::White).bg(Color::DarkGray);
+                buffer.set_display_width(controls_area.width as usize);
+                let viewport = buffer.viewport();
+                let input_area = Rect { height: 1, ..controls_area };
+                frame.render_widget(Block::default().style(input_style), input_area);
+                draw_viewport(frame, &viewport, input_area, input_style);
+                if focused {
+                    frame.set_cursor(input_area.x + viewport.cursor as u16, input_area.y);
+                }
+            }
+            PopupKind::Choice { options, selected: selected_index } => {
+                let items: Vec<ListItem> = options
+                    .iter()
+                    .enumerate()
+                    .map(|(index, option)| {
+                        let prefix = if index < 9 { format!("{} ", index + 1) } else { "  ".to_string() };
+                        ListItem::new(format!("{prefix}{option}"))
+                    })
+                    .collect();
+                let mut state = ListState::default();
+                state.select(Some(*selected_index));
+                frame.render_stateful_widget(
+                    List::new(items).style(style).highlight_style(selected),
+                    controls_area,
+                    &mut state,
+                );
+            }
+        }
+    }
+
+    /// Draws the [`Help`] screen: a search box over the matching keys, scrolled to `help.scroll`.
+    pub fn render_help(help: &mut Help, frame: &mut Frame, focused: bool) {
+        let size = frame.size();
+        let area = get_center_area((size.width.saturating_sub(4).min(90), size.height.saturating_sub(2)), size);
         let style = Style::default().fg(Color::Gray).bg(Color::Black);
-        let red = style.fg(Color::Red);
-        let white = style.fg(Color::White);
 
-        let line = Line::from(vec![
-            Span::styled("Are you sure you want to delete this contact? (", style),
-            Span::styled("y", red),
-            Span::styled("/", style),
-            Span::styled("n", white),
-            Span::styled(")", style),
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Block::default()
+                .title("Help (type to search, Esc to close)")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Cyan).bg(Color::Black)),
+            area,
+        );
+
+        let [search_area, _, text_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)])
+            .areas(area.inner(&Margin::new(2, 1)));
+
+        let [search_label, search_input] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(8), Constraint::Min(0)])
+            .areas(search_area);
+        let search_style = Style::default().fg(Color::Magenta).bg(Color::Black);
+        frame.render_widget(Paragraph::new("Search: ").style(search_style), search_label);
+        help.search.set_display_width(search_input.width as usize);
+        let viewport = help.search.viewport();
+        draw_viewport(frame, &viewport, search_input, search_style);
+        if focused {
+            frame.set_cursor(search_input.x + viewport.cursor as u16, search_input.y);
+        }
+
+        let lines = help.lines();
+        if lines.is_empty() {
+            frame.render_widget(Paragraph::new("No matching keys").style(style), text_area);
+            return;
+        }
+
+        help.clamp_scroll(lines.len(), text_area.height as usize);
+        let key_width = lines
+            .iter()
+            .filter_map(|line| match line {
+                HelpLine::Row(keys, _) => Some(keys.chars().count()),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .min(28);
 
-        ]);
+        let text: Vec<Line> = lines
+            .into_iter()
+            .skip(help.scroll)
+            .take(text_area.height as usize)
+            .map(|line| match line {
+                HelpLine::Heading(title) => Line::styled(title, Style::default().fg(Color::Cyan).bg(Color::Black).bold()),
+                HelpLine::Row(keys, description) => Line::from(vec![
+                    Span::styled(format!("  {keys:<key_width$}  "), Style::default().fg(Color::Yellow).bg(Color::Black)),
+                    Span::styled(description, style),
+                ]),
+                HelpLine::Blank => Line::from(""),
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(text).style(style), text_area);
+    }
+
+    /// Draws the [`CommandPalette`] near the top of the screen, with each action's keys on the right.
+    pub fn render_command_palette(palette: &mut CommandPalette, frame: &mut Frame, focused: bool) {
+        let size = frame.size();
+        let entries = palette.entries();
+        let width = size.width.saturating_sub(4).min(64);
+        let height = (entries.len().max(1) as u16 + 4).min(size.height.saturating_sub(2));
+        let area = Rect::new(size.x + (size.width.saturating_sub(width)) / 2, size.y + 1, width, height);
+        let style = Style::default().fg(Color::Gray).bg(Color::Black);
 
-        let question_area = center_area.inner(&Margin::new(2, 2));
+        frame.render_widget(Clear, area);
         frame.render_widget(
-            Paragraph::new(line)
-                .wrap(Wrap::default()),
-            question_area
+            Block::default()
+                .title("Commands")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Cyan).bg(Color::Black)),
+            area,
+        );
+
+        let [query_area, _, list_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)])
+            .areas(area.inner(&Margin::new(2, 1)));
+
+        let query_style = Style::default().fg(Color::Magenta).bg(Color::Black);
+        let [prompt_area, query_input] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(2), Constraint::Min(0)])
+            .areas(query_area);
+        frame.render_widget(Paragraph::new("> ").style(query_style), prompt_area);
+        palette.query.set_display_width(query_input.width as usize);
+        let viewport = palette.query.viewport();
+        draw_viewport(frame, &viewport, query_input, query_style);
+        if focused {
+            frame.set_cursor(query_input.x + viewport.cursor as u16, query_input.y);
+        }
+
+        if entries.is_empty() {
+            frame.render_widget(Paragraph::new("No matching commands").style(style), list_area);
+            return;
+        }
+
+        let inner_width = list_area.width as usize;
+        let items: Vec<ListItem> = entries
+            .iter()
+            .map(|entry| {
+                let description = entry.action.description();
+                let gap = inner_width.saturating_sub(description.chars().count() + entry.keys.chars().count());
+                ListItem::new(Line::from(vec![
+                    Span::raw(description),
+                    Span::raw(" ".repeat(gap)),
+                    Span::styled(entry.keys.clone(), Style::default().add_modifier(Modifier::DIM)),
+                ]))
+            })
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(palette.selected));
+        frame.render_stateful_widget(
+            List::new(items)
+                .style(style)
+                .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan)),
+            list_area,
+            &mut state,
         );
     }
 
-    pub fn render_main_window(app: &mut App, frame: &mut Frame) {
+    /// Draws the [`TemplateList`], previewing the highlighted template for the selected contact.
+    pub fn render_template_list(list: &mut TemplateList, app: &App, frame: &mut Frame) {
+        let contact = app.selected_contact();
+        let names: Vec<String> = app.state.templates.iter().map(|template| template.name.clone()).collect();
+        let preview = app.state.templates.get(list.selected).map(|template| match contact {
+            Some(contact) => (format!("For {}", contact.display_name()), template.render(Some(contact))),
+            None => ("Text".to_string(), template.body.clone()),
+        });
+        render_template_menu(
+            frame,
+            "Templates",
+            names,
+            list.selected,
+            preview,
+            "Ctrl+A add  Enter edit  Ctrl+D delete  Esc close",
+        );
+        if app.state.templates.is_empty() {
+            let area = get_center_area(TEMPLATE_MENU_SIZE, frame.size()).inner(&Margin::new(2, 1));
+            frame.render_widget(
+                Paragraph::new("No templates yet. Press Ctrl+A to add one.")
+                    .style(Style::default().fg(Color::Gray).bg(Color::Black)),
+                area,
+            );
+        }
+    }
+
+    /// Draws a [`TemplatePicker`], previewing the highlighted template for the picker's contact.
+    pub fn render_template_picker(picker: &mut TemplatePicker, app: &App, frame: &mut Frame) {
+        let names = picker
+            .blank
+            .clone()
+            .into_iter()
+            .chain(app.state.templates.iter().map(|template| template.name.clone()))
+            .collect();
+        let preview = picker.template(app).map(|template| {
+            let heading = match &picker.contact {
+                Some(contact) => format!("For {}", contact.display_name()),
+                None => "Preview".to_string(),
+            };
+            (heading, template.render(picker.contact.as_ref()))
+        });
+        render_template_menu(
+            frame,
+            &picker.title,
+            names,
+            picker.selected,
+            preview,
+            "Enter picks  Esc cancels",
+        );
+    }
+
+    /// Draws the [`DialPad`]: the typed number, any error, then contacts with a matching number.
+    pub fn render_dial_pad(dial_pad: &mut DialPad, frame: &mut Frame, focused: bool) {
+        let area = get_center_area((50, 6 + MAX_MATCHES as u16 + 2), frame.size());
+        let style = Style::default().fg(Color::Gray).bg(Color::Black);
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Block::default()
+                .title("Dial Number (Enter to call; ',' pauses, ';' waits)")
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::Cyan).bg(Color::Black)),
+            area,
+        );
+
+        let [number_area, error_area, heading_area, matches_area] = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .areas(area.inner(&Margin::new(2, 1)));
+
+        let [number_label, number_input] = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(8), Constraint::Min(0)])
+            .areas(number_area);
+        let input_style = Style::default().fg(Color::White).bg(Color::DarkGray);
+        frame.render_widget(Paragraph::new("Number: ").style(style), number_label);
+        frame.render_widget(Block::default().style(input_style), number_input);
+        dial_pad.number.set_display_width(number_input.width as usize);
+        let viewport = dial_pad.number.viewport();
+        draw_viewport(frame, &viewport, number_input, input_style);
+        if focused && dial_pad.selected.is_none() {
+            frame.set_cursor(number_input.x + viewport.cursor as u16, number_input.y);
+        }
+
+        if let Some(error) = &dial_pad.error {
+            frame.render_widget(
+                Paragraph::new(error.as_str()).style(Style::default().fg(Color::Red).bg(Color::Black)),
+                Rect { x: number_input.x, width: number_input.width, ..error_area },
+            );
+        }
+
+        if dial_pad.matches.is_empty() {
+            if !dial_pad.number.is_empty() {
+                frame.render_widget(Paragraph::new("No matching contacts").style(style), heading_area);
+            }
+            return;
+        }
+
+        frame.render_widget(
+            Paragraph::new("Matching contacts (Down to pick one):").style(style),
+            heading_area,
+        );
+        let items: Vec<ListItem> = dial_pad
+            .matches
+            .iter()
+            .map(|contact| ListItem::new(format!("{} - {}", contact.display_name(), contact.phone_number)))
+            .collect();
+        let mut state = ListState::default();
+        state.select(dial_pad.selected);
+        frame.render_stateful_widget(
+            List::new(items)
+                .style(Style::default().fg(Color::Cyan).bg(Color::Black))
+                .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan)),
+            matches_area,
+            &mut state,
+        );
+    }
+
+    pub fn render_main_window(app: &mut App, frame: &mut Frame, focused: bool) {
         let items: Vec<_> = app
             .state
             .contacts
@@ -104,31 +551,53 @@ impl Renderer {
                 } else {
                     style = style.fg(Color::Cyan).bg(Color::Black);
                 }
-                ListItem::new(format!("{contact}")).style(style)
+                let marker = if contact.favourite { "★ " } else { "  " };
+                ListItem::new(format!("{marker}{contact}")).style(style)
             })
             .collect();
 
-        let [filter_area, contact_area, status_area] = Layout::default()
+        let favourites_height = match app.state.favourites.len() {
+            0 => 0,
+            count => count.min(MAX_FAVOURITE_ROWS) as u16 + 2,
+        };
+        let [filter_area, favourites_area, contact_area, status_area] = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
                 Constraint::Length(1),
+                Constraint::Length(favourites_height),
                 Constraint::Min(0),
                 Constraint::Length(1),
             ])
             .areas(frame.size());
 
+        if favourites_height > 0 {
+            render_favourites(app, frame, favourites_area);
+        }
+
         let [status_area, quit_message] = Layout::default().direction(Direction::Horizontal).constraints([
             Constraint::Min(0),
-            Constraint::Length(10),
+            Constraint::Length(21),
         ]).areas(status_area);
 
 
+        let in_call = in_call_indicator(app);
+        let [filter_label, filter_input, in_call_area] = Layout::default().direction(Direction::Horizontal).constraints([
+            Constraint::Length(9),
+            Constraint::Min(0),
+            Constraint::Length(in_call.chars().count() as u16),
+        ]).areas(filter_area);
         frame.render_widget(
-            Paragraph::new(format!(" Filter: {}", app.state.filter))
-                .style(Style::default().fg(Color::Magenta).bg(Color::Black)),
-            filter_area,
+            Paragraph::new(in_call).style(Style::default().fg(Color::Black).bg(Color::Green).bold()),
+            in_call_area,
         );
 
+        let filter_style = Style::default().fg(Color::Magenta).bg(Color::Black);
+        frame.render_widget(Paragraph::new(" Filter: ").style(filter_style), filter_label);
+
+        app.state.filter.set_display_width(filter_input.width as usize);
+        let filter_viewport = app.state.filter.viewport();
+        draw_viewport(frame, &filter_viewport, filter_input, filter_style);
+
         let block_style = Style::default().fg(Color::Cyan).bg(Color::Black);
 
         let [contact_area, scrollbar] = Layout::default().direction(Direction::Horizontal).constraints([
@@ -158,7 +627,7 @@ impl Renderer {
         let mut spans = vec![Span::styled("Ctrl + ", Style::default().fg(Color::Gray).bg(Color::Black).bold())];
 
         let mut include_text = true;
-        if status_area.width < 58 {
+        if status_area.width < 95 {
             include_text = false;
         }
 
@@ -166,6 +635,18 @@ impl Renderer {
         spans.extend(construct_span("Edit", 'e', include_text));
         spans.extend(construct_span("Delete", 'd', include_text));
         spans.extend(construct_span("Call", 'c', include_text));
+        spans.extend(construct_span("SMS", 's', include_text));
+        spans.extend(construct_span("Fav", 'f', include_text));
+        spans.extend(construct_span("Number", 'n', include_text));
+        spans.extend(construct_span("Paste", 'v', include_text));
+        spans.extend(construct_span("Log", 'l', include_text));
+        spans.extend(construct_span("Commands", 'p', include_text));
+        if app.state.active_calls.last().is_some_and(|call| call.control.can_hang_up()) {
+            spans.extend(construct_span("Hang up", 'x', include_text));
+        }
+        if app.state.active_calls.iter().any(|call| call.state == Some(CallState::Waiting)) {
+            spans.extend(construct_span("Tones", 'g', include_text));
+        }
 
         let line = Line::from(spans);
 
@@ -173,12 +654,191 @@ impl Renderer {
 
 
         frame.render_widget(Paragraph::new(line), status_area);
-        frame.render_widget(Paragraph::new("ESC = Quit"), quit_message);
-        frame.set_cursor(
-            filter_area.x + app.state.filter.get_cursor_position() as u16 + 9,
-            filter_area.y,
+        frame.render_widget(Paragraph::new("F1 = Help  ESC = Quit"), quit_message);
+        if focused {
+            frame.set_cursor(
+                filter_input.x + filter_viewport.cursor as u16,
+                filter_input.y,
+            );
+        }
+    }
+}
+
+/// ` In call: Name 01:23 ` while a dialler is running, with a count if there are several.
+fn in_call_indicator(app: &App) -> String {
+    let Some(call) = app.state.active_calls.last() else {
+        return String::new();
+    };
+
+    let elapsed = call.started.elapsed().as_secs();
+    let others = match app.state.active_calls.len() {
+        1 => String::new(),
+        count => format!(" (+{})", count - 1),
+    };
+    let state = call.state.map_or("In call", |state| state.label());
+    format!(" {state}: {} {:02}:{:02}{others} ", call.name, elapsed / 60, elapsed % 60)
+}
+
+/// Draws the favourites pane: speed-dial contacts first, with their Alt+digit slot.
+fn render_favourites(app: &App, frame: &mut Frame, area: Rect) {
+    let style = Style::default().fg(Color::Yellow).bg(Color::Black);
+    let items: Vec<ListItem> = app
+        .state
+        .favourites
+        .iter()
+        .map(|contact| {
+            let slot = contact
+                .speed_dial
+                .map(|slot| format!("Alt+{slot}"))
+                .unwrap_or_default();
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{slot:>5}  "), style.add_modifier(Modifier::BOLD)),
+                Span::styled(format!("{} - {}", contact.display_name(), contact.phone_number), style),
+            ]))
+        })
+        .collect();
+
+    frame.render_widget(
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(" Favourites")
+                .style(style),
+        ),
+        area,
+    );
+}
+
+fn severity_colour(severity: Severity) -> Color {
+    match severity {
+        Severity::Info => Color::Cyan,
+        Severity::Success => Color::Green,
+        Severity::Warning => Color::Yellow,
+        Severity::Error => Color::Red,
+    }
+}
+
+/// The time and severity of `notification`, then its message wrapped to `width` columns, with
+/// the lines after the first indented to line up under it.
+fn notification_log_lines(notification: &Notification, width: u16) -> Vec<Line<'static>> {
+    const PREFIX_WIDTH: usize = 17;
+    let style = Style::default().bg(Color::Black);
+    let message = wrap_text(&notification.message, width.saturating_sub(PREFIX_WIDTH as u16));
+
+    let mut lines = vec![];
+    for (index, text) in message.into_iter().enumerate() {
+        let prefix = if index == 0 {
+            vec![
+                Span::styled(notification.created.format("%H:%M:%S ").to_string(), style.fg(Color::Gray)),
+                Span::styled(
+                    format!("{:<8}", notification.severity.label()),
+                    style.fg(severity_colour(notification.severity)),
+                ),
+            ]
+        } else {
+            vec![Span::styled(" ".repeat(PREFIX_WIDTH), style)]
+        };
+        lines.push(Line::from([prefix, vec![Span::styled(text, style.fg(Color::White))]].concat()));
+    }
+    lines
+}
+
+/// Draws a list of template names over a preview of the highlighted one, with `hint` at the bottom.
+fn render_template_menu(
+    frame: &mut Frame,
+    title: &str,
+    names: Vec<String>,
+    selected: usize,
+    preview: Option<(String, String)>,
+    hint: &str,
+) {
+    let area = get_center_area(TEMPLATE_MENU_SIZE, frame.size());
+    let style = Style::default().fg(Color::Gray).bg(Color::Black);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Cyan).bg(Color::Black)),
+        area,
+    );
+
+    let rows = (names.len() as u16).clamp(1, TEMPLATE_MENU_ROWS);
+    let [list_area, _, preview_area, hint_area] = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(rows),
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(area.inner(&Margin::new(2, 1)));
+
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    frame.render_stateful_widget(
+        List::new(names)
+            .style(style)
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan)),
+        list_area,
+        &mut state,
+    );
+
+    if let Some((heading, text)) = preview {
+        frame.render_widget(
+            Paragraph::new(text)
+                .block(
+                    Block::default()
+                        .title(heading)
+                        .borders(Borders::TOP)
+                        .border_style(Style::default().fg(Color::DarkGray)),
+                )
+                .style(style)
+                .wrap(Wrap { trim: false }),
+            preview_area,
         );
     }
+    frame.render_widget(
+        Paragraph::new(hint).style(Style::default().fg(Color::DarkGray).bg(Color::Black)),
+        hint_area,
+    );
+}
+
+/// Word-wraps `text` to `width` columns, splitting words that are too long to fit.
+fn wrap_text(text: &str, width: u16) -> Vec<String> {
+    let width = width.max(1) as usize;
+    let mut lines = vec![];
+
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            while word.len() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                lines.push(word.drain(..width).collect());
+            }
+
+            let word: String = word.into_iter().collect();
+            let needed = if line.is_empty() { word.chars().count() } else { line.chars().count() + 1 + word.chars().count() };
+            if needed > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+        }
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
 }
 
 fn get_center_area(dimensions: (u16, u16), size: Rect) -> Rect {
@@ -206,7 +866,7 @@ fn get_center_area(dimensions: (u16, u16), size: Rect) -> Rect {
     areas[1]
 }
 
-fn construct_span(text: &str, short_code: char, include_text: bool) -> Vec<Span>{
+fn construct_span(text: &str, short_code: char, include_text: bool) -> Vec<Span<'_>>{
     let mut spans = vec![];
     let style = Style::default().fg(Color::Gray).bg(Color::Black).bold();
 
@@ -227,41 +887,141 @@ fn construct_span(text: &str, short_code: char, include_text: bool) -> Vec<Span>
     spans
 }
 
-fn draw_field_in_rect(frame: &mut Frame, field: &DialogField, label_area: Rect, input_area: Rect) {
-    let value = field.get_value();
+fn draw_viewport(frame: &mut Frame, viewport: &Viewport, area: Rect, style: Style) {
+    if !viewport.overflow_left && !viewport.overflow_right && viewport.text.chars().count() < area.width as usize {
+        frame.render_widget(Paragraph::new(viewport.text.clone()).style(style), area);
+        return;
+    }
+
+    let indicator = style.add_modifier(Modifier::DIM);
+    let line = Line::from(vec![
+        Span::styled(if viewport.overflow_left { "<" } else { " " }, indicator),
+        Span::styled(viewport.text.clone(), style),
+        Span::styled(if viewport.overflow_right { ">" } else { " " }, indicator),
+    ]);
+    frame.render_widget(Paragraph::new(line).style(style), area);
+}
+
+fn draw_field_in_rect(frame: &mut Frame, field: &mut DialogField, label_area: Rect, input_area: Rect, focused: bool) {
+    field.set_display_size(input_area.width, input_area.height);
+    let colour = if field.needs_review { Color::Yellow } else { Color::Cyan };
+    let style = Style::default().fg(colour).bg(Color::Black);
     frame.render_widget(
         Paragraph::new(format!("{}: ", field.label))
-            .style(Style::default().fg(Color::Cyan).bg(Color::Black))
+            .style(style)
             .alignment(Alignment::Right),
         label_area,
     );
 
-    frame.render_widget(
-        Paragraph::new(value.to_string())
-            .style(Style::default().fg(Color::Cyan).bg(Color::Black)),
-        input_area,
-    );
+    match &field.kind {
+        FieldKind::Checkbox => {
+            let mark = if field.is_checked() { "[x]" } else { "[ ]" };
+            let style = if focused { style.add_modifier(Modifier::REVERSED) } else { style };
+            frame.render_widget(Paragraph::new(mark).style(style), input_area);
+        }
+        FieldKind::Select { .. } => {
+            let arrows = if focused { style.add_modifier(Modifier::BOLD) } else { style.add_modifier(Modifier::DIM) };
+            let line = Line::from(vec![
+                Span::styled("< ", arrows),
+                Span::styled(field.get_value().to_string(), style),
+                Span::styled(" >", arrows),
+            ]);
+            frame.render_widget(Paragraph::new(line).style(style), input_area);
+        }
+        _ => {
+            for (row, viewport) in field.viewports().iter().take(input_area.height as usize).enumerate() {
+                let row_area = Rect {
+                    y: input_area.y + row as u16,
+                    height: 1,
+                    ..input_area
+                };
+                draw_viewport(frame, viewport, row_area, style);
+            }
+        }
+    }
 }
 
 fn draw_fields_in_rects(
     frame: &mut Frame,
-    fields: &[DialogField],
+    fields: &mut [DialogField],
     areas: &[Rect],
     max_label: u16,
     focused_index: usize,
+    show_cursor: bool,
 ) {
-    for (i, field) in fields.iter().enumerate() {
+    for (i, field) in fields.iter_mut().enumerate() {
         if i < areas.len() {
+            let [field_area, error_area] = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(field.height()), Constraint::Length(1)])
+                .areas(areas[i]);
             let rects = Layout::default()
                 .direction(Direction::Horizontal)
                 .constraints([Constraint::Length(max_label + 2), Constraint::Min(1)])
-                .split(areas[i]);
-            draw_field_in_rect(frame, field, rects[0], rects[1]);
-            if i == focused_index {
-                frame.set_cursor(rects[1].x + field.get_cursor_pos(), rects[1].y);
+                .split(field_area);
+            let label_area = Rect { height: 1, ..rects[0] };
+            draw_field_in_rect(frame, field, label_area, rects[1], i == focused_index);
+
+            if show_cursor && i == focused_index {
+                if let Some((x, y)) = field.get_cursor_pos() {
+                    frame.set_cursor(rects[1].x + x, rects[1].y + y);
+                }
+            }
+
+            if let Some(error) = &field.error {
+                let error_area = Rect {
+                    x: rects[1].x,
+                    width: rects[1].width,
+                    ..error_area
+                };
+                frame.render_widget(
+                    Paragraph::new(error.as_str())
+                        .style(Style::default().fg(Color::Red).bg(Color::Black)),
+                    error_area,
+                );
             }
         } else {
             panic!("Can't draw field, no area to draw it in");
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_wrapped_between_words() {
+        assert_eq!(wrap_text("the quick brown fox", 10), ["the quick", "brown fox"]);
+        assert_eq!(wrap_text("one\n\ntwo", 10), ["one", "", "two"]);
+        assert_eq!(wrap_text("", 10), [""]);
+    }
+
+    #[test]
+    fn words_too_long_for_a_line_are_split() {
+        assert_eq!(wrap_text("see abcdefghijkl", 5), ["see", "abcde", "fghij", "kl"]);
+    }
+
+    #[test]
+    fn long_messages_in_the_log_wrap_under_the_first_line() {
+        let mut notifications = crate::notification::Notifications::default();
+        notifications.error("Could not call Jane Smith: the dialler program exited with code 1");
+        let notification = notifications.history().next().unwrap();
+
+        let text: Vec<String> = notification_log_lines(notification, 40)
+            .iter()
+            .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        assert!(text[0].ends_with(" Error   Could not call Jane"), "{text:?}");
+        let indent = " ".repeat(17);
+        assert_eq!(
+            text[1..],
+            [
+                format!("{indent}Smith: the dialler"),
+                format!("{indent}program exited with"),
+                format!("{indent}code 1")
+            ]
+        );
+        assert!(text.iter().all(|line| line.chars().count() <= 40));
+    }
+}
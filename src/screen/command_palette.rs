@@ -0,0 +1,105 @@
+use crate::action::Action;
+use crate::app::App;
+use crate::error::AppResult;
+use crate::line_buffer::LineBuffer;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+
+/// A palette entry that matched the query.
+#[derive(Debug, Clone)]
+pub struct PaletteEntry {
+    pub action: Action,
+    pub keys: String,
+}
+
+/// Lists every [`Action`], fuzzy filtered by what's typed, and performs the one picked.
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    pub query: LineBuffer,
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    /// The actions matching the query, best match first.
+    pub fn entries(&self) -> Vec<PaletteEntry> {
+        let query = self.query.get_value();
+        let mut scored: Vec<_> = Action::PALETTE
+            .iter()
+            .enumerate()
+            .filter_map(|(order, action)| {
+                fuzzy_score(query, action.description()).map(|score| (score, order, *action))
+            })
+            .collect();
+        scored.sort_by(|(a, a_order, _), (b, b_order, _)| b.cmp(a).then(a_order.cmp(b_order)));
+
+        scored
+            .into_iter()
+            .map(|(_, _, action)| PaletteEntry {
+                action,
+                keys: action.key_labels().join(" / "),
+            })
+            .collect()
+    }
+}
+
+impl Screen for CommandPalette {
+    fn render(&mut self, _: &mut App, frame: &mut Frame, focused: bool) {
+        Renderer::render_command_palette(self, frame, focused);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        match key_event.code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected += 1,
+            KeyCode::Enter => {
+                if let Some(entry) = self.entries().get(self.selected) {
+                    app.perform(entry.action);
+                    return Ok(ScreenAction::Close);
+                }
+            }
+            _ => {
+                let type_mode = app.type_mode();
+                self.query
+                    .handle_key_events(key_event, type_mode, &mut app.state.edit_context)?;
+                if self.query.updated {
+                    self.selected = 0;
+                }
+            }
+        }
+
+        self.selected = self.selected.min(self.entries().len().saturating_sub(1));
+        Ok(ScreenAction::None)
+    }
+
+    fn handle_paste(&mut self, _: &mut App, text: &str) -> AppResult<ScreenAction> {
+        self.query.paste(text);
+        self.selected = 0;
+        Ok(ScreenAction::None)
+    }
+}
+
+/// Scores `text` against `query` when every character of the query appears in it in order,
+/// ignoring case. Runs of consecutive characters and matches at the start of words score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let index = position + text[position..].iter().position(|&t| t == c)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !text[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(index);
+        position = index + 1;
+    }
+
+    Some(score)
+}
@@ -0,0 +1,118 @@
+use crate::app::App;
+use crate::data_layer::contact::Contact;
+use crate::dialog::modal::{DialogResult, Modal};
+use crate::dialog::validator::{UniqueCheck, UniqueScope};
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crate::signature::{Detected, ParsedSignature};
+use crossterm::event::KeyEvent;
+use ratatui::Frame;
+
+/// The add/edit contact dialog. Saving inserts when `contact_id` is `None`.
+#[derive(Debug)]
+pub struct ContactForm {
+    pub modal: Modal,
+    pub contact_id: Option<u64>,
+}
+
+impl ContactForm {
+    pub fn add() -> Self {
+        Self {
+            modal: Modal::add_contact(),
+            contact_id: None,
+        }
+    }
+
+    /// An add form with the phone number already filled in.
+    pub fn for_number(phone_number: &str) -> Self {
+        let mut form = Self::add();
+        form.modal.set_value("phone", phone_number);
+        form
+    }
+
+    pub fn edit(contact: &Contact) -> Self {
+        let mut modal = Modal::edit_contact();
+        modal.set_value("first", contact.first_name.clone().unwrap_or_default());
+        modal.set_value("last", contact.last_name.clone().unwrap_or_default());
+        modal.set_value("phone", contact.phone_number.clone());
+        modal.set_value("company", contact.company_name.clone().unwrap_or_default());
+        modal.set_value("email", contact.email.clone().unwrap_or_default());
+        modal.set_value("dtmf", contact.dtmf.clone().unwrap_or_default());
+        modal.set_value("favourite", contact.favourite.to_string());
+        if let Some(slot) = contact.speed_dial {
+            modal.set_value("speed_dial", slot.to_string());
+        }
+        Self {
+            modal,
+            contact_id: Some(contact.id),
+        }
+    }
+
+    /// An add form pre-filled from a pasted signature, with guesses flagged for review.
+    pub fn from_signature(text: &str) -> Self {
+        let parsed = ParsedSignature::parse(text);
+        let mut form = Self::add();
+
+        let detected = [
+            ("first", parsed.first_name.clone()),
+            ("last", parsed.last_name.clone()),
+            ("phone", parsed.phone_number().cloned()),
+            ("company", parsed.company.clone()),
+            ("email", parsed.email()),
+        ];
+        for (name, detected) in detected {
+            if let (Some(field), Some(Detected { value, confident })) = (form.modal.get_field_mut(name), detected) {
+                field.set_value(value);
+                field.needs_review = !confident;
+            }
+        }
+
+        form
+    }
+
+    fn scope(&self) -> UniqueScope {
+        UniqueScope::Contacts {
+            excluding: self.contact_id,
+        }
+    }
+}
+
+impl Screen for ContactForm {
+    fn render(&mut self, _: &mut App, frame: &mut Frame, focused: bool) {
+        Renderer::render_modal(&mut self.modal, frame, focused);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        let type_mode = app.type_mode();
+        match self
+            .modal
+            .handle_key_events(key_event, type_mode, &mut app.state.edit_context)?
+        {
+            DialogResult::Ok => {
+                if !app.save_contact(&mut self.modal, self.contact_id) {
+                    return Ok(ScreenAction::None);
+                }
+                app.refresh_contacts();
+                Ok(ScreenAction::Close)
+            }
+            DialogResult::Cancel => Ok(ScreenAction::Close),
+            DialogResult::None => {
+                app.check_unique(self.scope(), self.modal.unique_values_to_check());
+                Ok(ScreenAction::None)
+            }
+        }
+    }
+
+    fn handle_unique_check(&mut self, check: &UniqueCheck) {
+        if check.scope == self.scope() {
+            self.modal.apply_unique_check(check);
+        }
+    }
+
+    fn handle_paste(&mut self, app: &mut App, text: &str) -> AppResult<ScreenAction> {
+        self.modal.handle_paste(text);
+        app.check_unique(self.scope(), self.modal.unique_values_to_check());
+        Ok(ScreenAction::None)
+    }
+}
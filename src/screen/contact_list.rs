@@ -0,0 +1,52 @@
+use crate::action::{self, Action, CONTACT_LIST_BINDINGS};
+use crate::app::App;
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+
+/// The main window: the filter box and contact list, always at the bottom of the stack.
+#[derive(Debug, Default)]
+pub struct ContactList;
+
+impl Screen for ContactList {
+    fn render(&mut self, app: &mut App, frame: &mut Frame, focused: bool) {
+        Renderer::render_main_window(app, frame, focused);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        if key_event.code == KeyCode::Char('?') && app.state.filter.is_empty() {
+            app.perform(Action::ShowHelp);
+        } else if let Some(action) = action::lookup(CONTACT_LIST_BINDINGS, &key_event) {
+            app.perform(action);
+        } else {
+            let type_mode = app.type_mode();
+            app.state
+                .filter
+                .handle_key_events(key_event, type_mode, &mut app.state.edit_context)?;
+            if app.state.filter.updated {
+                app.state.selected_contact_index = 0;
+                app.refresh_contacts();
+            }
+        }
+
+        Ok(ScreenAction::None)
+    }
+
+    fn handle_paste(&mut self, app: &mut App, text: &str) -> AppResult<ScreenAction> {
+        app.state.filter.paste(text);
+        if app.state.filter.updated {
+            app.refresh_contacts();
+        }
+        Ok(ScreenAction::None)
+    }
+
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    fn captures(&self, key_event: &KeyEvent) -> bool {
+        key_event.code == KeyCode::Esc
+    }
+}
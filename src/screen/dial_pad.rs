@@ -0,0 +1,139 @@
+use crate::app::App;
+use crate::data_layer::call::CallForInsert;
+use crate::data_layer::contact::Contact;
+use crate::dialog::popup::{Popup, PopupResult};
+use crate::dialog::validator::Validator;
+use crate::error::AppResult;
+use crate::line_buffer::LineBuffer;
+use crate::renderer::Renderer;
+use crate::screen::contact_form::ContactForm;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+
+/// How many matching contacts the reverse lookup shows.
+pub const MAX_MATCHES: usize = 5;
+
+/// Dials a number typed by hand, showing any contacts whose number matches as it's typed.
+#[derive(Debug)]
+pub struct DialPad {
+    pub number: LineBuffer,
+    pub matches: Vec<Contact>,
+    /// The match Enter calls instead of the typed number, picked with Up/Down.
+    pub selected: Option<usize>,
+    pub error: Option<String>,
+}
+
+impl Default for DialPad {
+    fn default() -> Self {
+        let mut number = LineBuffer::default();
+        number.set_max_length(Some(60));
+        Self {
+            number,
+            matches: vec![],
+            selected: None,
+            error: None,
+        }
+    }
+}
+
+impl DialPad {
+    /// The typed number, and any tones typed after it from the first `,` or `;`.
+    fn split(&self) -> (String, Option<String>) {
+        let typed = self.number.get_value().trim();
+        match typed.find([',', ';']) {
+            Some(index) => (typed[..index].trim().to_string(), Some(typed[index..].to_string())),
+            None => (typed.to_string(), None),
+        }
+    }
+
+    /// Looks up the contacts matching what's typed, showing why if that fails.
+    fn refresh_matches(&mut self, app: &App) {
+        let (digits, _) = self.split();
+        let digits = digits.as_str();
+        self.selected = None;
+        self.error = None;
+        self.matches = if digits.is_empty() {
+            vec![]
+        } else {
+            app.lookup_number(digits, MAX_MATCHES).unwrap_or_else(|err| {
+                self.error = Some(format!("Could not look up the number: {}", err.full_message()));
+                vec![]
+            })
+        };
+    }
+
+    /// Calls the selected match, or the typed number, offering to save a number nobody has.
+    fn dial(&mut self, app: &mut App) -> ScreenAction {
+        let (number, tones) = self.split();
+        if let Some(contact) = self.selected.and_then(|index| self.matches.get(index)) {
+            let mut call = CallForInsert::for_contact(contact);
+            call.dtmf = tones.or(call.dtmf);
+            app.call(call);
+            return ScreenAction::Close;
+        }
+
+        let valid = Validator::Required
+            .validate(&number)
+            .and_then(|_| Validator::PhoneNumber.validate(&number))
+            .and_then(|_| Validator::Dtmf.validate(tones.as_deref().unwrap_or_default()));
+        if let Err(err) = valid {
+            self.error = Some(err);
+            return ScreenAction::None;
+        }
+
+        let known = self
+            .matches
+            .iter()
+            .any(|contact| contact.phone_number.replace(' ', "") == number.replace(' ', ""));
+        let mut call = CallForInsert::for_number(number.clone());
+        call.dtmf = tones.clone();
+        app.call(call);
+
+        if !known {
+            let popup = Popup::confirm("Save number?", format!("Save {number} as a new contact?"))
+                .on_result(move |app, result| {
+                    if result == PopupResult::Confirmed {
+                        let mut form = ContactForm::for_number(&number);
+                        form.modal.set_value("dtmf", tones.unwrap_or_default());
+                        app.screens.push(form);
+                    }
+                    Ok(())
+                });
+            app.push_popup(popup);
+        }
+        ScreenAction::Close
+    }
+}
+
+impl Screen for DialPad {
+    fn render(&mut self, _: &mut App, frame: &mut Frame, focused: bool) {
+        Renderer::render_dial_pad(self, frame, focused);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        match key_event.code {
+            KeyCode::Enter => return Ok(self.dial(app)),
+            KeyCode::Down if !self.matches.is_empty() => {
+                self.selected = Some(self.selected.map_or(0, |index| (index + 1).min(self.matches.len() - 1)));
+            }
+            KeyCode::Up => self.selected = self.selected.and_then(|index| index.checked_sub(1)),
+            _ => {
+                let type_mode = app.type_mode();
+                self.number
+                    .handle_key_events(key_event, type_mode, &mut app.state.edit_context)?;
+                if self.number.updated {
+                    self.refresh_matches(app);
+                }
+            }
+        }
+
+        Ok(ScreenAction::None)
+    }
+
+    fn handle_paste(&mut self, app: &mut App, text: &str) -> AppResult<ScreenAction> {
+        self.number.paste(text);
+        self.refresh_matches(app);
+        Ok(ScreenAction::None)
+    }
+}
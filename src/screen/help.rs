@@ -0,0 +1,183 @@
+use crate::action::{Action, KeyBinding, CONTACT_LIST_BINDINGS, GLOBAL_BINDINGS};
+use crate::app::App;
+use crate::dialog::modal::MODAL_KEYS;
+use crate::dialog::popup::POPUP_KEYS;
+use crate::error::AppResult;
+use crate::line_buffer::{LineBuffer, EDITING_KEYS};
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+
+const PAGE: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct HelpSection {
+    pub title: &'static str,
+    /// `(keys, description)` pairs.
+    pub rows: Vec<(String, String)>,
+}
+
+impl HelpSection {
+    fn new(title: &'static str) -> Self {
+        Self { title, rows: vec![] }
+    }
+
+    fn bindings(mut self, bindings: &[KeyBinding]) -> Self {
+        self.rows.extend(
+            bindings
+                .iter()
+                .filter(|binding| !matches!(binding.action, Action::SpeedDial(_)))
+                .map(|binding| (binding.label(), binding.action.description().to_string())),
+        );
+        self
+    }
+
+    fn keys(mut self, keys: &[(&str, &str)]) -> Self {
+        self.rows
+            .extend(keys.iter().map(|(keys, description)| (keys.to_string(), description.to_string())));
+        self
+    }
+
+    fn row(mut self, keys: &str, description: &str) -> Self {
+        self.rows.push((keys.to_string(), description.to_string()));
+        self
+    }
+
+    /// The rows matching `query` (case-insensitive), or all of them if the title matches.
+    fn matching(&self, query: &str) -> Vec<&(String, String)> {
+        if self.title.to_lowercase().contains(query) {
+            return self.rows.iter().collect();
+        }
+        self.rows
+            .iter()
+            .filter(|(keys, description)| {
+                keys.to_lowercase().contains(query) || description.to_lowercase().contains(query)
+            })
+            .collect()
+    }
+}
+
+/// A line of the help screen, after searching.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HelpLine {
+    Heading(&'static str),
+    Row(String, String),
+    Blank,
+}
+
+/// A scrollable, searchable list of every key the app understands, built from the same tables
+/// the key handlers use.
+#[derive(Debug)]
+pub struct Help {
+    pub sections: Vec<HelpSection>,
+    pub search: LineBuffer,
+    pub scroll: usize,
+}
+
+impl Default for Help {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Help {
+    pub fn new() -> Self {
+        let sections = vec![
+            HelpSection::new("Everywhere")
+                .bindings(GLOBAL_BINDINGS)
+                .row("Alt+1 to Alt+9", "Call the contact in that speed-dial slot"),
+            HelpSection::new("Contact list")
+                .bindings(CONTACT_LIST_BINDINGS)
+                .row("?", "Show this help (when the filter is empty)")
+                .row("Anything else", "Edit the filter"),
+            HelpSection::new("Filter")
+                .row("text", "Matches anywhere in the id, name, company, phone number or email, ignoring case")
+                .row("%", "Matches any run of characters, e.g. smi%th")
+                .row("_", "Matches any single character"),
+            HelpSection::new("Dial pad")
+                .row("Digits", "Type the number; contacts with a matching number are listed")
+                .row("Up / Down", "Pick a matching contact to call instead")
+                .row("Enter", "Call, then offer to save a new number as a contact"),
+            HelpSection::new("Dialogs").keys(MODAL_KEYS),
+            HelpSection::new("Popups").keys(POPUP_KEYS),
+            HelpSection::new("Text editing").keys(EDITING_KEYS),
+            HelpSection::new("Typing mode")
+                .row("Insert", "Switch between inserting and overwriting characters")
+                .row("Block cursor", "Typed characters are inserted at the cursor")
+                .row("Underline cursor", "Typed characters replace the one under the cursor"),
+            HelpSection::new("Message log")
+                .row("Up / Down / PgUp / PgDn", "Scroll")
+                .row("Esc / q", "Close"),
+            HelpSection::new("Help")
+                .row("Type", "Search the help")
+                .row("Up / Down / PgUp / PgDn", "Scroll")
+                .row("Esc / F1", "Close"),
+        ];
+
+        Self {
+            sections,
+            search: LineBuffer::default(),
+            scroll: 0,
+        }
+    }
+
+    /// The help text, limited to sections and rows matching the search.
+    pub fn lines(&self) -> Vec<HelpLine> {
+        let query = self.search.get_value().trim().to_lowercase();
+        let mut lines = vec![];
+        for section in &self.sections {
+            let rows = section.matching(&query);
+            if rows.is_empty() {
+                continue;
+            }
+            if !lines.is_empty() {
+                lines.push(HelpLine::Blank);
+            }
+            lines.push(HelpLine::Heading(section.title));
+            lines.extend(rows.into_iter().map(|(keys, description)| HelpLine::Row(keys.clone(), description.clone())));
+        }
+        lines
+    }
+
+    /// Keeps `scroll` within the text when `visible` lines fit on screen.
+    pub fn clamp_scroll(&mut self, line_count: usize, visible: usize) {
+        self.scroll = self.scroll.min(line_count.saturating_sub(visible));
+    }
+}
+
+impl Screen for Help {
+    fn render(&mut self, _: &mut App, frame: &mut Frame, focused: bool) {
+        Renderer::render_help(self, frame, focused);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        match key_event.code {
+            KeyCode::F(1) => return Ok(ScreenAction::Close),
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll += 1,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE),
+            KeyCode::PageDown => self.scroll += PAGE,
+            _ => {
+                let type_mode = app.type_mode();
+                self.search
+                    .handle_key_events(key_event, type_mode, &mut app.state.edit_context)?;
+                if self.search.updated {
+                    self.scroll = 0;
+                }
+            }
+        }
+
+        Ok(ScreenAction::None)
+    }
+
+    fn captures(&self, key_event: &KeyEvent) -> bool {
+        key_event.code == KeyCode::F(1)
+    }
+
+    fn handle_paste(&mut self, _: &mut App, text: &str) -> AppResult<ScreenAction> {
+        self.search.paste(text);
+        self.scroll = 0;
+        Ok(ScreenAction::None)
+    }
+}
@@ -0,0 +1,115 @@
+pub mod command_palette;
+pub mod contact_form;
+pub mod contact_list;
+pub mod dial_pad;
+pub mod help;
+pub mod notification_log;
+pub mod paste_signature;
+pub mod popup;
+pub mod sms_compose;
+pub mod template_form;
+pub mod template_list;
+pub mod template_picker;
+
+use crate::app::App;
+use crate::dialog::validator::UniqueCheck;
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crossterm::event::KeyEvent;
+use ratatui::Frame;
+use std::fmt::Debug;
+
+/// What the stack should do with a layer after it has handled an event.
+#[derive(Debug)]
+pub enum ScreenAction {
+    None,
+    Close,
+    /// Swaps the layer for another in the same position.
+    Replace(Box<dyn Screen>),
+}
+
+/// One layer of the UI. Layers render and handle input independently; only the topmost gets input.
+pub trait Screen: Debug {
+    /// Draws the layer. Only the `focused` (topmost) layer should place the cursor.
+    fn render(&mut self, app: &mut App, frame: &mut Frame, focused: bool);
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction>;
+
+    fn handle_paste(&mut self, _app: &mut App, _text: &str) -> AppResult<ScreenAction> {
+        Ok(ScreenAction::None)
+    }
+
+    /// Receives the outcome of a background uniqueness check. Forms that asked for it show it.
+    fn handle_unique_check(&mut self, _check: &UniqueCheck) {}
+
+    /// Overlays are drawn over the dimmed layers beneath them; anything else hides those layers.
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    /// Whether the layer handles `key_event` itself, ahead of the app-wide bindings and the stack
+    /// closing it on Esc.
+    fn captures(&self, _key_event: &KeyEvent) -> bool {
+        false
+    }
+}
+
+/// The layers on screen, bottom first.
+#[derive(Debug, Default)]
+pub struct ScreenStack {
+    layers: Vec<Box<dyn Screen>>,
+}
+
+impl ScreenStack {
+    pub fn push(&mut self, screen: impl Screen + 'static) {
+        self.layers.push(Box::new(screen));
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Takes the topmost layer off so it can be given `&mut App`, along with the index to restore it to.
+    pub(crate) fn take_top(&mut self) -> Option<(usize, Box<dyn Screen>)> {
+        let screen = self.layers.pop()?;
+        Some((self.layers.len(), screen))
+    }
+
+    /// Puts a layer taken by [`ScreenStack::take_top`] back, below anything it pushed meanwhile.
+    pub(crate) fn restore(&mut self, index: usize, screen: Box<dyn Screen>, action: ScreenAction) {
+        let index = index.min(self.layers.len());
+        match action {
+            ScreenAction::None => self.layers.insert(index, screen),
+            ScreenAction::Close => {}
+            ScreenAction::Replace(replacement) => self.layers.insert(index, replacement),
+        }
+    }
+
+    /// Gives a uniqueness check to every layer, since the form that asked may be under a popup.
+    pub fn handle_unique_check(&mut self, check: &UniqueCheck) {
+        for screen in self.layers.iter_mut() {
+            screen.handle_unique_check(check);
+        }
+    }
+
+    /// Draws every visible layer bottom up, dimming whatever sits beneath an overlay.
+    pub fn render(app: &mut App, frame: &mut Frame) {
+        let mut layers = std::mem::take(&mut app.screens.layers);
+        let first_visible = layers.iter().rposition(|screen| !screen.is_overlay()).unwrap_or(0);
+        let top = layers.len().saturating_sub(1);
+
+        for (index, screen) in layers.iter_mut().enumerate().skip(first_visible) {
+            if index > first_visible && screen.is_overlay() {
+                Renderer::dim(frame);
+            }
+            screen.render(app, frame, index == top);
+        }
+
+        layers.append(&mut app.screens.layers);
+        app.screens.layers = layers;
+    }
+}
@@ -0,0 +1,41 @@
+use crate::app::App;
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+
+const PAGE: usize = 10;
+
+/// The history of notifications shown this session, newest first, with long messages wrapped.
+/// Up/Down/PgUp/PgDn scroll it and Esc or `q` closes it.
+#[derive(Debug, Default)]
+pub struct NotificationLog {
+    pub scroll: usize,
+}
+
+impl NotificationLog {
+    /// Keeps `scroll` within the log when `visible` lines fit on screen.
+    pub fn clamp_scroll(&mut self, line_count: usize, visible: usize) {
+        self.scroll = self.scroll.min(line_count.saturating_sub(visible));
+    }
+}
+
+impl Screen for NotificationLog {
+    fn render(&mut self, app: &mut App, frame: &mut Frame, _: bool) {
+        Renderer::render_notification_log(self, app, frame);
+    }
+
+    fn handle_key_event(&mut self, _: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        match key_event.code {
+            KeyCode::Char('q') => return Ok(ScreenAction::Close),
+            KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down => self.scroll += 1,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE),
+            KeyCode::PageDown => self.scroll += PAGE,
+            _ => {}
+        }
+
+        Ok(ScreenAction::None)
+    }
+}
@@ -0,0 +1,25 @@
+use crate::app::App;
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::contact_form::ContactForm;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::KeyEvent;
+use ratatui::Frame;
+
+/// Waits for a pasted signature, then turns into a pre-filled [`ContactForm`].
+#[derive(Debug, Default)]
+pub struct PasteSignature;
+
+impl Screen for PasteSignature {
+    fn render(&mut self, app: &mut App, frame: &mut Frame, _: bool) {
+        Renderer::render_paste_signature_modal(app, frame);
+    }
+
+    fn handle_key_event(&mut self, _: &mut App, _: KeyEvent) -> AppResult<ScreenAction> {
+        Ok(ScreenAction::None)
+    }
+
+    fn handle_paste(&mut self, _: &mut App, text: &str) -> AppResult<ScreenAction> {
+        Ok(ScreenAction::Replace(Box::new(ContactForm::from_signature(text))))
+    }
+}
@@ -0,0 +1,39 @@
+use crate::app::App;
+use crate::dialog::popup::Popup;
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+
+impl Screen for Popup {
+    fn render(&mut self, _: &mut App, frame: &mut Frame, focused: bool) {
+        Renderer::render_popup(self, frame, focused);
+    }
+
+    /// Closes the popup once it has a result, running its callback. A failing callback is
+    /// reported rather than ending the app.
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        let type_mode = app.type_mode();
+        let Some(result) = self.handle_key_events(key_event, type_mode, &mut app.state.edit_context)? else {
+            return Ok(ScreenAction::None);
+        };
+
+        if let Some(callback) = self.take_callback() {
+            if let Err(err) = callback(app, result) {
+                app.state.notifications.report(&self.title, &err);
+            }
+        }
+        Ok(ScreenAction::Close)
+    }
+
+    fn handle_paste(&mut self, _: &mut App, text: &str) -> AppResult<ScreenAction> {
+        Popup::handle_paste(self, text);
+        Ok(ScreenAction::None)
+    }
+
+    /// Esc cancels the popup, which its callback needs to hear about.
+    fn captures(&self, key_event: &KeyEvent) -> bool {
+        key_event.code == KeyCode::Esc
+    }
+}
@@ -0,0 +1,97 @@
+use crate::app::App;
+use crate::data_layer::contact::Contact;
+use crate::data_layer::message::MessageForInsert;
+use crate::dialog::dialog_field::DialogField;
+use crate::dialog::modal::{DialogResult, Modal};
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crate::sms::{Segments, MAX_SEGMENTS};
+use crate::template::{self, PLACEHOLDERS};
+use crossterm::event::KeyEvent;
+use ratatui::Frame;
+
+/// How many lines of the filled-in message the dialog previews.
+const PREVIEW_LINES: usize = 4;
+
+/// Writes a text, counting how many texts it will take as it's typed.
+#[derive(Debug)]
+pub struct SmsCompose {
+    pub modal: Modal,
+    /// Who the placeholders are filled in from and the message is logged against.
+    pub contact: Option<Contact>,
+}
+
+impl SmsCompose {
+    pub fn for_contact(contact: &Contact) -> Self {
+        let mut modal = Modal::compose_sms();
+        modal.title = format!("SMS to {}", contact.display_name());
+        modal.set_value("to", contact.phone_number.clone());
+        modal.focused_index = 1;
+        Self {
+            modal,
+            contact: Some(contact.clone()),
+        }
+    }
+
+    /// The message as it will be sent, with the placeholders filled in.
+    fn body(&self) -> String {
+        template::render(self.modal.value("message").trim(), self.contact.as_ref())
+    }
+
+    /// Counts the texts, and previews the message once it has placeholders filled in.
+    fn update_note(&mut self) {
+        let body = self.body();
+        let mut note = format!("{}. Ctrl+S sends.", Segments::count(&body));
+        if self.contact.is_some() {
+            let placeholders: Vec<String> = PLACEHOLDERS.iter().map(|name| format!("{{{name}}}")).collect();
+            note.push_str(&format!("\nPlaceholders: {}", placeholders.join(" ")));
+        }
+        if body != self.modal.value("message").trim() {
+            note.push_str("\nAs sent:");
+            for line in body.lines().take(PREVIEW_LINES) {
+                note.push_str(&format!("\n  {line}"));
+            }
+        }
+        self.modal.note = Some(note);
+    }
+}
+
+impl Screen for SmsCompose {
+    fn render(&mut self, _: &mut App, frame: &mut Frame, focused: bool) {
+        self.update_note();
+        Renderer::render_modal(&mut self.modal, frame, focused);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        let type_mode = app.type_mode();
+        match self
+            .modal
+            .handle_key_events(key_event, type_mode, &mut app.state.edit_context)?
+        {
+            DialogResult::Ok => {
+                let body = self.body();
+                let segments = Segments::count(&body);
+                if segments.count > MAX_SEGMENTS {
+                    self.modal.error = Some(format!(
+                        "That's {} texts; messages can be at most {MAX_SEGMENTS}",
+                        segments.count
+                    ));
+                    return Ok(ScreenAction::None);
+                }
+                let numbers = self.modal.get_field("to").map(DialogField::get_numbers).unwrap_or_default();
+                for to in numbers {
+                    app.send_sms(MessageForInsert::new(to, self.contact.as_ref(), body.clone()));
+                }
+                Ok(ScreenAction::Close)
+            }
+            DialogResult::Cancel => Ok(ScreenAction::Close),
+            DialogResult::None => Ok(ScreenAction::None),
+        }
+    }
+
+    fn handle_paste(&mut self, _: &mut App, text: &str) -> AppResult<ScreenAction> {
+        self.modal.handle_paste(text);
+        Ok(ScreenAction::None)
+    }
+}
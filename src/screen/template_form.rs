@@ -0,0 +1,104 @@
+use crate::app::App;
+use crate::data_layer::contact::Contact;
+use crate::data_layer::template::Template;
+use crate::dialog::modal::{DialogResult, Modal};
+use crate::dialog::validator::{UniqueCheck, UniqueScope};
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crate::template::{self, PLACEHOLDERS};
+use crossterm::event::KeyEvent;
+use ratatui::Frame;
+
+/// How many lines of the preview the form shows under the text.
+const PREVIEW_LINES: usize = 4;
+
+/// The add/edit template dialog, previewing the text for a contact as it's typed. Saving inserts
+/// when `template_id` is `None`.
+#[derive(Debug)]
+pub struct TemplateForm {
+    pub modal: Modal,
+    pub template_id: Option<u64>,
+    /// Who the preview is filled in from.
+    pub contact: Option<Contact>,
+}
+
+impl TemplateForm {
+    pub fn add(contact: Option<Contact>) -> Self {
+        Self {
+            modal: Modal::add_template(),
+            template_id: None,
+            contact,
+        }
+    }
+
+    pub fn edit(template: &Template, contact: Option<Contact>) -> Self {
+        let mut modal = Modal::edit_template();
+        modal.set_value("name", template.name.clone());
+        modal.set_value("body", template.body.clone());
+        Self {
+            modal,
+            template_id: Some(template.id),
+            contact,
+        }
+    }
+
+    fn update_note(&mut self) {
+        let placeholders: Vec<String> = PLACEHOLDERS.iter().map(|name| format!("{{{name}}}")).collect();
+        let mut note = format!("Ctrl+S saves. Placeholders: {}", placeholders.join(" "));
+        let body = self.modal.value("body");
+        if let Some(contact) = self.contact.as_ref().filter(|_| !body.trim().is_empty()) {
+            note.push_str(&format!("\nFor {}:", contact.display_name()));
+            for line in template::render(body.trim(), Some(contact)).lines().take(PREVIEW_LINES) {
+                note.push_str(&format!("\n  {line}"));
+            }
+        }
+        self.modal.note = Some(note);
+    }
+
+    fn scope(&self) -> UniqueScope {
+        UniqueScope::Templates {
+            excluding: self.template_id,
+        }
+    }
+}
+
+impl Screen for TemplateForm {
+    fn render(&mut self, _: &mut App, frame: &mut Frame, focused: bool) {
+        self.update_note();
+        Renderer::render_modal(&mut self.modal, frame, focused);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        let type_mode = app.type_mode();
+        match self
+            .modal
+            .handle_key_events(key_event, type_mode, &mut app.state.edit_context)?
+        {
+            DialogResult::Ok => {
+                if !app.save_template(&mut self.modal, self.template_id) {
+                    return Ok(ScreenAction::None);
+                }
+                app.refresh_templates();
+                Ok(ScreenAction::Close)
+            }
+            DialogResult::Cancel => Ok(ScreenAction::Close),
+            DialogResult::None => {
+                app.check_unique(self.scope(), self.modal.unique_values_to_check());
+                Ok(ScreenAction::None)
+            }
+        }
+    }
+
+    fn handle_unique_check(&mut self, check: &UniqueCheck) {
+        if check.scope == self.scope() {
+            self.modal.apply_unique_check(check);
+        }
+    }
+
+    fn handle_paste(&mut self, app: &mut App, text: &str) -> AppResult<ScreenAction> {
+        self.modal.handle_paste(text);
+        app.check_unique(self.scope(), self.modal.unique_values_to_check());
+        Ok(ScreenAction::None)
+    }
+}
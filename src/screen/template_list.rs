@@ -0,0 +1,44 @@
+use crate::app::App;
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::template_form::TemplateForm;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Frame;
+
+/// Manages the saved templates, previewing the highlighted one for the selected contact.
+#[derive(Debug, Default)]
+pub struct TemplateList {
+    pub selected: usize,
+}
+
+impl Screen for TemplateList {
+    fn render(&mut self, app: &mut App, frame: &mut Frame, _: bool) {
+        self.selected = self.selected.min(app.state.templates.len().saturating_sub(1));
+        Renderer::render_template_list(self, app, frame);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        let contact = app.selected_contact().cloned();
+        let template = app.state.templates.get(self.selected).cloned();
+        match (key_event.modifiers, key_event.code) {
+            (_, KeyCode::Up) => self.selected = self.selected.saturating_sub(1),
+            (_, KeyCode::Down) => self.selected += 1,
+            (KeyModifiers::CONTROL, KeyCode::Char('a')) => app.screens.push(TemplateForm::add(contact)),
+            (KeyModifiers::NONE, KeyCode::Enter) | (KeyModifiers::CONTROL, KeyCode::Char('e')) => {
+                if let Some(template) = template {
+                    app.screens.push(TemplateForm::edit(&template, contact));
+                }
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('d')) => {
+                if let Some(template) = template {
+                    app.confirm_delete_template(template);
+                }
+            }
+            _ => {}
+        }
+
+        self.selected = self.selected.min(app.state.templates.len().saturating_sub(1));
+        Ok(ScreenAction::None)
+    }
+}
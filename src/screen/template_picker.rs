@@ -0,0 +1,92 @@
+use crate::app::App;
+use crate::data_layer::contact::Contact;
+use crate::data_layer::template::Template;
+use crate::error::AppResult;
+use crate::renderer::Renderer;
+use crate::screen::{Screen, ScreenAction};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::Frame;
+use std::fmt::{Debug, Formatter};
+
+pub type PickCallback = Box<dyn FnOnce(&mut App, Option<Template>) -> AppResult<()>>;
+
+/// Asks which template to start a message from, previewing each for the contact it's for. There
+/// can be a first entry for starting without one.
+pub struct TemplatePicker {
+    pub title: String,
+    /// What the entry for no template is called, e.g. "Blank message", if there is one.
+    pub blank: Option<String>,
+    pub contact: Option<Contact>,
+    pub selected: usize,
+    callback: Option<PickCallback>,
+}
+
+impl Debug for TemplatePicker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TemplatePicker")
+            .field("title", &self.title)
+            .field("blank", &self.blank)
+            .field("contact", &self.contact)
+            .field("selected", &self.selected)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TemplatePicker {
+    pub fn new(title: impl Into<String>, contact: Option<Contact>) -> Self {
+        Self {
+            title: title.into(),
+            blank: None,
+            contact,
+            selected: 0,
+            callback: None,
+        }
+    }
+
+    /// Adds a first entry, called `label`, for starting without a template.
+    pub fn blank(mut self, label: impl Into<String>) -> Self {
+        self.blank = Some(label.into());
+        self
+    }
+
+    /// Runs `callback` with the template picked, or `None` for the blank entry. It isn't run if
+    /// the picker is closed without picking.
+    pub fn on_pick(mut self, callback: impl FnOnce(&mut App, Option<Template>) -> AppResult<()> + 'static) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// The template highlighted, or `None` on the blank entry.
+    pub fn template<'a>(&self, app: &'a App) -> Option<&'a Template> {
+        let offset = self.blank.is_some() as usize;
+        self.selected.checked_sub(offset).and_then(|index| app.state.templates.get(index))
+    }
+
+    fn len(&self, app: &App) -> usize {
+        app.state.templates.len() + self.blank.is_some() as usize
+    }
+}
+
+impl Screen for TemplatePicker {
+    fn render(&mut self, app: &mut App, frame: &mut Frame, _: bool) {
+        Renderer::render_template_picker(self, app, frame);
+    }
+
+    fn handle_key_event(&mut self, app: &mut App, key_event: KeyEvent) -> AppResult<ScreenAction> {
+        match key_event.code {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(self.len(app).saturating_sub(1)),
+            KeyCode::Enter => {
+                let template = self.template(app).cloned();
+                if let Some(callback) = self.callback.take() {
+                    if let Err(err) = callback(app, template) {
+                        app.state.notifications.report(&self.title, &err);
+                    }
+                }
+                return Ok(ScreenAction::Close);
+            }
+            _ => {}
+        }
+        Ok(ScreenAction::None)
+    }
+}
@@ -0,0 +1,349 @@
+/// A value pulled out of pasted text, along with whether the heuristics are sure about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detected {
+    pub value: String,
+    pub confident: bool,
+}
+
+impl Detected {
+    fn new(value: impl Into<String>, confident: bool) -> Self {
+        Self {
+            value: value.into(),
+            confident,
+        }
+    }
+}
+
+/// Contact details extracted from an email signature or similar block of text.
+#[derive(Debug, Default)]
+pub struct ParsedSignature {
+    pub first_name: Option<Detected>,
+    pub last_name: Option<Detected>,
+    pub company: Option<Detected>,
+    pub phone_numbers: Vec<Detected>,
+    pub emails: Vec<String>,
+}
+
+const SIGN_OFFS: &[&str] = &[
+    "regards",
+    "kind regards",
+    "best regards",
+    "warm regards",
+    "thanks",
+    "thank you",
+    "many thanks",
+    "cheers",
+    "best",
+    "sincerely",
+    "yours sincerely",
+    "yours faithfully",
+];
+
+const COMPANY_SUFFIXES: &[&str] = &[
+    "pty ltd", "pty. ltd.", "pty limited", "ltd", "ltd.", "limited", "inc", "inc.", "llc", "gmbh",
+    "corp", "corporation", "co.", "group", "plc", "services", "solutions",
+];
+
+const JOB_TITLE_WORDS: &[&str] = &[
+    "manager", "director", "engineer", "officer", "ceo", "cto", "cfo", "coo", "founder",
+    "owner", "consultant", "assistant", "administrator", "coordinator", "supervisor",
+    "specialist", "technician", "sales", "accounts", "head", "lead", "partner", "president",
+    "executive", "representative", "developer", "analyst", "support",
+];
+
+const MOBILE_LABELS: &[&str] = &["m", "mob", "mobile", "cell", "c"];
+
+const FREE_MAIL_DOMAINS: &[&str] = &[
+    "gmail", "googlemail", "hotmail", "outlook", "live", "yahoo", "icloud", "me", "bigpond",
+    "aol", "protonmail", "proton",
+];
+
+impl ParsedSignature {
+    /// Runs the heuristics over `text`.
+    pub fn parse(text: &str) -> Self {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim().trim_start_matches(['-', '_', '|']).trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let mut parsed = ParsedSignature::default();
+
+        for line in &lines {
+            parsed.emails.extend(find_emails(line));
+            parsed.phone_numbers.extend(find_phone_numbers(line));
+        }
+        parsed.emails.dedup();
+        parsed.phone_numbers.dedup_by(|a, b| digits(&a.value) == digits(&b.value));
+
+        // Put mobile numbers first, since they are the ones we usually ring. Only a
+        // labelled mobile or a lone number is trusted without review.
+        parsed.phone_numbers.sort_by_key(|phone| !phone.confident);
+        let only_one = parsed.phone_numbers.len() == 1;
+        for (index, phone) in parsed.phone_numbers.iter_mut().enumerate() {
+            phone.confident = index == 0 && (phone.confident || only_one);
+        }
+
+        let name_line = lines.iter().position(looks_like_name);
+        if let Some(index) = name_line {
+            let name = lines[index];
+            let confident = name.split_whitespace().count() <= 3;
+            let (first, last) = name.split_once(' ').unwrap_or((name, ""));
+            parsed.first_name = Some(Detected::new(first, confident));
+            if !last.is_empty() {
+                parsed.last_name = Some(Detected::new(last, confident));
+            }
+        } else if let Some(email) = parsed.emails.first() {
+            let (first, last) = name_from_email(email);
+            parsed.first_name = first.map(|first| Detected::new(first, false));
+            parsed.last_name = last.map(|last| Detected::new(last, false));
+        }
+
+        parsed.company = lines
+            .iter()
+            .find(|line| has_company_suffix(line))
+            .map(|line| Detected::new(strip_label(line), true))
+            .or_else(|| {
+                let start = name_line.map_or(0, |index| index + 1);
+                lines
+                    .iter()
+                    .skip(start)
+                    .find(|line| looks_like_company(line))
+                    .map(|line| Detected::new(*line, false))
+            })
+            .or_else(|| {
+                parsed
+                    .emails
+                    .first()
+                    .and_then(|email| company_from_email(email))
+                    .map(|company| Detected::new(company, false))
+            });
+
+        parsed
+    }
+
+    pub fn phone_number(&self) -> Option<&Detected> {
+        self.phone_numbers.first()
+    }
+
+    pub fn email(&self) -> Option<Detected> {
+        self.emails
+            .first()
+            .map(|email| Detected::new(email.clone(), self.emails.len() == 1))
+    }
+}
+
+fn digits(text: &str) -> String {
+    text.chars().filter(char::is_ascii_digit).collect()
+}
+
+fn strip_label(line: &str) -> &str {
+    match line.split_once(':') {
+        Some((label, value)) if label.len() <= 12 && !label.contains('/') => value.trim(),
+        _ => line,
+    }
+}
+
+fn find_emails(line: &str) -> Vec<String> {
+    line.split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '(' | ')' | ',' | ';' | '"'))
+        .map(|word| word.trim_start_matches("mailto:").trim_end_matches('.'))
+        .filter(|word| {
+            word.split_once('@').is_some_and(|(local, domain)| {
+                !local.is_empty() && domain.contains('.') && !domain.starts_with('.')
+            })
+        })
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Finds runs of phone-number characters that contain a plausible number of digits.
+/// Numbers labelled as mobile are marked confident, others are left for review.
+fn find_phone_numbers(line: &str) -> Vec<Detected> {
+    if line.contains('@') || line.contains("://") {
+        return vec![];
+    }
+
+    let mut runs: Vec<(usize, String)> = vec![];
+    let mut current = String::new();
+    let mut run_start = 0;
+    for (index, c) in line.char_indices() {
+        let continues_run = !current.is_empty() && matches!(c, ' ' | '-' | '.' | '(' | ')');
+        let starts_run = current.is_empty() && matches!(c, '+' | '(');
+        if c.is_ascii_digit() || continues_run || starts_run {
+            if current.is_empty() {
+                run_start = index;
+            }
+            current.push(c);
+        } else if !current.is_empty() {
+            runs.push((run_start, std::mem::take(&mut current)));
+        }
+    }
+    if !current.is_empty() {
+        runs.push((run_start, current));
+    }
+
+    runs.into_iter()
+        .filter_map(|(start, run)| {
+            let candidate = run.trim().trim_end_matches(['-', '.', '(']).trim().to_string();
+            if !(8..=15).contains(&digits(&candidate).len()) {
+                return None;
+            }
+
+            let label: String = line[..start]
+                .trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ':' | '.'))
+                .chars()
+                .rev()
+                .take_while(|c| c.is_alphabetic())
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect::<String>()
+                .to_lowercase();
+            Some(Detected::new(candidate, MOBILE_LABELS.contains(&label.as_str())))
+        })
+        .collect()
+}
+
+fn is_sign_off(line: &str) -> bool {
+    let lowercase = line.to_lowercase();
+    let trimmed = lowercase.trim_end_matches([',', '!', '.']);
+    SIGN_OFFS.contains(&trimmed) || lowercase.starts_with("sent from")
+}
+
+fn looks_like_name(line: &&str) -> bool {
+    if is_sign_off(line) {
+        return false;
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    (2..=4).contains(&words.len())
+        && !has_company_suffix(line)
+        && !is_job_title(line)
+        && words.iter().all(|word| {
+            word.chars().next().is_some_and(char::is_uppercase)
+                && word.chars().all(|c| c.is_alphabetic() || matches!(c, '-' | '\'' | '.'))
+        })
+}
+
+fn has_company_suffix(line: &&str) -> bool {
+    let lowercase = line.to_lowercase();
+    COMPANY_SUFFIXES
+        .iter()
+        .any(|suffix| lowercase.ends_with(&format!(" {suffix}")) || lowercase.contains(&format!(" {suffix} ")))
+}
+
+fn is_job_title(line: &str) -> bool {
+    line.to_lowercase()
+        .split(|c: char| !c.is_alphabetic())
+        .any(|word| JOB_TITLE_WORDS.contains(&word))
+}
+
+fn looks_like_company(line: &&str) -> bool {
+    !is_sign_off(line)
+        && !line.contains('@')
+        && !line.contains("://")
+        && !line.to_lowercase().starts_with("www.")
+        && digits(line).len() < 4
+        && !is_job_title(line)
+        && line.chars().next().is_some_and(char::is_uppercase)
+}
+
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn name_from_email(email: &str) -> (Option<String>, Option<String>) {
+    let local = email.split('@').next().unwrap_or("");
+    let mut parts = local
+        .split(['.', '_'])
+        .filter(|part| part.chars().all(char::is_alphabetic) && part.len() > 1);
+    match (parts.next(), parts.next()) {
+        (Some(first), Some(last)) => (Some(capitalise(first)), Some(capitalise(last))),
+        _ => (None, None),
+    }
+}
+
+fn company_from_email(email: &str) -> Option<String> {
+    let domain = email.split('@').nth(1)?;
+    let name = domain.split('.').next()?;
+    if FREE_MAIL_DOMAINS.contains(&name) {
+        None
+    } else {
+        Some(capitalise(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(detected: &Option<Detected>) -> Option<(&str, bool)> {
+        detected.as_ref().map(|detected| (detected.value.as_str(), detected.confident))
+    }
+
+    #[test]
+    fn a_typical_signature_is_parsed() {
+        let parsed = ParsedSignature::parse(
+            "Kind regards,\n\
+             Jane Smith\n\
+             Sales Manager\n\
+             Acme Widgets Pty Ltd\n\
+             M: 0412 345 678 | P: (02) 9876 5432\n\
+             jane.smith@acme.com.au",
+        );
+
+        assert_eq!(value(&parsed.first_name), Some(("Jane", true)));
+        assert_eq!(value(&parsed.last_name), Some(("Smith", true)));
+        assert_eq!(value(&parsed.company), Some(("Acme Widgets Pty Ltd", true)));
+        assert_eq!(parsed.emails, ["jane.smith@acme.com.au"]);
+
+        let numbers: Vec<_> = parsed
+            .phone_numbers
+            .iter()
+            .map(|phone| (phone.value.as_str(), phone.confident))
+            .collect();
+        assert_eq!(numbers, [("0412 345 678", true), ("(02) 9876 5432", false)]);
+    }
+
+    #[test]
+    fn the_name_falls_back_to_the_email_address() {
+        let parsed = ParsedSignature::parse("Thanks!\nmailto:john_doe@example.org.\n+1 555-123-4567");
+
+        assert_eq!(value(&parsed.first_name), Some(("John", false)));
+        assert_eq!(value(&parsed.last_name), Some(("Doe", false)));
+        assert_eq!(value(&parsed.company), Some(("Example", false)));
+        assert_eq!(value(&parsed.phone_number().cloned()), Some(("+1 555-123-4567", true)));
+        assert_eq!(value(&parsed.email()), Some(("john_doe@example.org", true)));
+    }
+
+    #[test]
+    fn free_mail_domains_are_not_companies() {
+        let parsed = ParsedSignature::parse("bob.jones@gmail.com");
+        assert!(parsed.company.is_none());
+    }
+
+    #[test]
+    fn short_and_long_digit_runs_are_not_phone_numbers() {
+        assert!(find_phone_numbers("Suite 1200, 45 Example St").is_empty());
+        assert!(find_phone_numbers("Ref 1234567890123456").is_empty());
+        assert!(find_phone_numbers("https://example.com/0412345678").is_empty());
+    }
+
+    #[test]
+    fn several_unlabelled_numbers_need_review() {
+        let parsed = ParsedSignature::parse("P 02 9876 5432\nF 02 9876 5433");
+        assert_eq!(parsed.phone_numbers.len(), 2);
+        assert!(parsed.phone_numbers.iter().all(|phone| !phone.confident));
+    }
+
+    #[test]
+    fn several_emails_need_review() {
+        let parsed = ParsedSignature::parse("<a@example.com>, b@example.com");
+        assert_eq!(parsed.emails, ["a@example.com", "b@example.com"]);
+        assert_eq!(value(&parsed.email()), Some(("a@example.com", false)));
+    }
+}
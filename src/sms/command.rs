@@ -0,0 +1,68 @@
+use crate::dialler::process::tail;
+use crate::error::AppResult;
+use crate::event::Event;
+use crate::sms::{gateway_error, send, SmsEvent, SmsGateway, SmsRequest};
+use serde::Deserialize;
+use std::process::Stdio;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::info;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CommandConfig {
+    /// The program or script that sends a text, given the number and the message as its arguments.
+    pub program: String,
+}
+
+/// Sends texts by running a local program, such as a modem or provider's command line tool.
+#[derive(Debug)]
+pub struct CommandGateway {
+    config: CommandConfig,
+}
+
+impl CommandGateway {
+    pub fn new(config: CommandConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl SmsGateway for CommandGateway {
+    /// Starts the program and waits for it on the tokio runtime. It has sent the message if it
+    /// exits successfully; otherwise its stderr says why not.
+    fn send(&self, request: SmsRequest, events: Option<UnboundedSender<Event>>) -> AppResult<()> {
+        let program = self.config.program.clone();
+        let SmsRequest { message_id, name, .. } = request.clone();
+        let child = tokio::process::Command::new(&program)
+            .arg(&request.to)
+            .arg(&request.body)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| gateway_error(format!("could not start \"{program}\": {err}")))?;
+        info!("Started SMS command for message {message_id} with pid {:?}", child.id());
+
+        tokio::spawn(async move {
+            let event = match child.wait_with_output().await {
+                Ok(output) if output.status.success() => SmsEvent::Sent { message_id, name },
+                Ok(output) => {
+                    let stderr = tail(String::from_utf8_lossy(&output.stderr).trim());
+                    let status = match output.status.code() {
+                        Some(code) => format!("\"{program}\" exited with code {code}"),
+                        None => format!("\"{program}\" was killed"),
+                    };
+                    let message = if stderr.is_empty() { status } else { format!("{status}: {stderr}") };
+                    SmsEvent::Failed { message_id, name, message }
+                }
+                Err(err) => SmsEvent::Failed {
+                    message_id,
+                    name,
+                    message: format!("could not wait for \"{program}\": {err}"),
+                },
+            };
+            info!("SMS command for message {message_id} finished: {event:?}");
+            send(&events, event);
+        });
+
+        Ok(())
+    }
+}
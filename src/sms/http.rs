@@ -0,0 +1,341 @@
+use crate::error::AppResult;
+use crate::event::Event;
+use crate::sms::{gateway_error, send, SmsEvent, SmsGateway, SmsRequest};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::timeout;
+use tracing::{debug, info};
+
+/// The most of the gateway's response that is read, so a confused server can't make us buffer freely.
+const MAX_RESPONSE: u64 = 64 * 1024;
+
+/// How much of an error response's body is kept for the message log.
+const MAX_ERROR_BODY: usize = 200;
+
+fn default_timeout() -> u64 {
+    10
+}
+
+/// Settings for a gateway that takes texts as JSON `POST`s, such as a local SMS relay or modem daemon.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpConfig {
+    /// An `http://` URL. HTTPS gateways need a local relay in front of them.
+    pub url: String,
+    /// Sent as a bearer token, if set.
+    pub token: Option<String>,
+    /// Seconds to wait for the gateway to answer.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+/// Sends texts by posting `{"to": ..., "message": ..., "reference": ...}` to a URL. Any 2xx
+/// response means the gateway took the message.
+#[derive(Debug)]
+pub struct HttpGateway {
+    config: HttpConfig,
+}
+
+impl HttpGateway {
+    pub fn new(config: HttpConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl SmsGateway for HttpGateway {
+    fn send(&self, request: SmsRequest, events: Option<UnboundedSender<Event>>) -> AppResult<()> {
+        let target = Target::parse(&self.config.url)?;
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            let SmsRequest { message_id, name, .. } = request.clone();
+            let event = match timeout(Duration::from_secs(config.timeout), post(&target, &config, &request)).await {
+                Ok(Ok(())) => SmsEvent::Sent { message_id, name },
+                Ok(Err(err)) => SmsEvent::failed(message_id, name, err),
+                Err(_) => SmsEvent::Failed {
+                    message_id,
+                    name,
+                    message: format!("the gateway didn't answer within {} seconds", config.timeout),
+                },
+            };
+            info!("SMS gateway request for message {message_id} finished: {event:?}");
+            send(&events, event);
+        });
+
+        Ok(())
+    }
+}
+
+/// Where an `http://` URL points.
+#[derive(Debug, PartialEq)]
+struct Target {
+    /// The host and port as written, for the `Host` header.
+    authority: String,
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Target {
+    fn parse(url: &str) -> AppResult<Self> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| gateway_error(format!("{url} isn't an http:// URL")))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.strip_prefix('[') {
+            // An IPv6 address, e.g. `[::1]:8080`.
+            Some(bracketed) => {
+                let (host, rest) = bracketed
+                    .split_once(']')
+                    .ok_or_else(|| gateway_error(format!("{url} has an unclosed [")))?;
+                let port = match rest {
+                    "" => None,
+                    rest => Some(rest.strip_prefix(':').unwrap_or(rest)),
+                };
+                (host, port)
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port
+                .parse()
+                .map_err(|_| gateway_error(format!("{url} has an invalid port")))?,
+            None => 80,
+        };
+        if host.is_empty() {
+            return Err(gateway_error(format!("{url} has no host")));
+        }
+        if host.contains(':') && !authority.starts_with('[') {
+            return Err(gateway_error(format!("{url} needs its IPv6 address in brackets, e.g. http://[::1]:8080/")));
+        }
+
+        Ok(Self {
+            authority: authority.to_string(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+async fn post(target: &Target, config: &HttpConfig, request: &SmsRequest) -> AppResult<()> {
+    let body = serde_json::json!({
+        "to": request.to,
+        "message": request.body,
+        "reference": request.message_id.to_string(),
+    })
+    .to_string();
+    let mut head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        target.path,
+        target.authority,
+        body.len()
+    );
+    if let Some(token) = &config.token {
+        head.push_str(&format!("Authorization: Bearer {token}\r\n"));
+    }
+    head.push_str("\r\n");
+
+    let mut stream = TcpStream::connect((target.host.as_str(), target.port))
+        .await
+        .map_err(|err| gateway_error(format!("could not connect to {}: {err}", target.authority)))?;
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.flush().await?;
+
+    let mut response = Vec::new();
+    stream.take(MAX_RESPONSE).read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    debug!("SMS gateway < {response}");
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status_line = head.lines().next().unwrap_or_default();
+    let (status, reason) = status_line
+        .strip_prefix("HTTP/")
+        .and_then(|line| line.split_once(' '))
+        .map(|(_, status)| status.split_once(' ').unwrap_or((status, "")))
+        .ok_or_else(|| gateway_error("the gateway didn't send an HTTP response"))?;
+    // The body of a success isn't read, so how it's sent doesn't matter.
+    if status.starts_with('2') {
+        return Ok(());
+    }
+
+    let mut message = format!("the gateway answered {status} {reason}").trim_end().to_string();
+    let chunked = head.lines().skip(1).any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("Transfer-Encoding") && value.to_ascii_lowercase().contains("chunked")
+        })
+    });
+    if chunked {
+        message.push_str(" with a chunked body, which can't be read; have it send a Content-Length instead");
+        return Err(gateway_error(message));
+    }
+    let body = body.trim();
+    if !body.is_empty() {
+        let body: String = body.chars().take(MAX_ERROR_BODY).collect();
+        message.push_str(&format!(": {body}"));
+    }
+    Err(gateway_error(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    fn config(port: u16) -> HttpConfig {
+        HttpConfig {
+            url: format!("http://127.0.0.1:{port}/send"),
+            token: Some("s3cret".to_string()),
+            timeout: 5,
+        }
+    }
+
+    fn request() -> SmsRequest {
+        SmsRequest {
+            message_id: 3,
+            to: "+61400000000".to_string(),
+            name: "Jane Smith".to_string(),
+            body: "Running \"late\"".to_string(),
+        }
+    }
+
+    fn failed(message: &str) -> SmsEvent {
+        SmsEvent::Failed {
+            message_id: 3,
+            name: "Jane Smith".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Reads the gateway's request, returning its head and its body.
+    async fn read_request(stream: &mut BufReader<TcpStream>) -> (String, String) {
+        let mut head = String::new();
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            head.push_str(&line);
+        }
+        let length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .and_then(|length| length.parse().ok())
+            .unwrap();
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await.unwrap();
+        (head, String::from_utf8(body).unwrap())
+    }
+
+    /// Sends [`request`] to a stub gateway that answers with `response`, returning how it went
+    /// and what the stub was sent.
+    async fn send_to(config: impl FnOnce(u16) -> HttpConfig, response: &'static str) -> (SmsEvent, String, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let gateway = HttpGateway::new(config(listener.local_addr().unwrap().port()));
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        gateway.send(request(), Some(sender)).unwrap();
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let (head, body) = read_request(&mut stream).await;
+        if response.is_empty() {
+            // Say nothing, and let the clock run on past the timeout.
+            tokio::time::pause();
+        } else {
+            stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+            stream.get_mut().shutdown().await.unwrap();
+        }
+
+        let Some(Event::Sms(event)) = receiver.recv().await else {
+            panic!("the gateway should say how the send went");
+        };
+        (event, head, body)
+    }
+
+    #[tokio::test]
+    async fn the_message_is_posted_as_json_with_the_token() {
+        let (event, head, body) = send_to(config, "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n").await;
+        assert_eq!(
+            event,
+            SmsEvent::Sent {
+                message_id: 3,
+                name: "Jane Smith".to_string(),
+            }
+        );
+
+        let mut lines = head.lines();
+        assert_eq!(lines.next(), Some("POST /send HTTP/1.1"));
+        let headers: Vec<_> = lines.collect();
+        assert!(headers.contains(&"Content-Type: application/json"), "{head}");
+        assert!(headers.contains(&"Authorization: Bearer s3cret"), "{head}");
+        assert!(headers.iter().any(|header| header.starts_with("Host: 127.0.0.1:")), "{head}");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "to": "+61400000000", "message": "Running \"late\"", "reference": "3" })
+        );
+    }
+
+    #[tokio::test]
+    async fn a_refusal_says_what_the_gateway_said() {
+        let response = "HTTP/1.1 400 Bad Request\r\nContent-Length: 15\r\n\r\ninvalid number\n";
+        let (event, _, _) = send_to(config, response).await;
+        assert_eq!(event, failed("the gateway answered 400 Bad Request: invalid number"));
+    }
+
+    #[tokio::test]
+    async fn a_chunked_refusal_is_reported_as_unreadable() {
+        let response = "HTTP/1.1 500 Internal Server Error\r\nTransfer-Encoding: chunked\r\n\r\n4\r\noops\r\n0\r\n\r\n";
+        let (event, _, _) = send_to(config, response).await;
+        assert_eq!(
+            event,
+            failed(
+                "the gateway answered 500 Internal Server Error with a chunked body, which can't be read; \
+                 have it send a Content-Length instead"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn a_gateway_that_does_not_answer_times_out() {
+        let quick = |port| HttpConfig { timeout: 1, ..config(port) };
+        let (event, _, _) = send_to(quick, "").await;
+        assert_eq!(event, failed("the gateway didn't answer within 1 seconds"));
+    }
+
+    #[test]
+    fn urls_are_split_into_where_to_connect_and_the_path() {
+        let target = |authority: &str, host: &str, port, path: &str| Target {
+            authority: authority.to_string(),
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        };
+        assert_eq!(Target::parse("http://sms.local/send").unwrap(), target("sms.local", "sms.local", 80, "/send"));
+        assert_eq!(Target::parse("http://127.0.0.1:8080").unwrap(), target("127.0.0.1:8080", "127.0.0.1", 8080, "/"));
+        assert_eq!(Target::parse("http://[::1]:8080/sms").unwrap(), target("[::1]:8080", "::1", 8080, "/sms"));
+        assert_eq!(Target::parse("http://[::1]/").unwrap(), target("[::1]", "::1", 80, "/"));
+
+        for url in [
+            "https://sms.local/",
+            "http://:8080/",
+            "http://sms.local:http/",
+            "http://::1:8080/",
+            "http://[::1/",
+        ] {
+            assert!(matches!(Target::parse(url), Err(Error::SmsGateway(_))), "{url}");
+        }
+    }
+}
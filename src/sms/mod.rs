@@ -0,0 +1,206 @@
+pub mod command;
+pub mod http;
+
+use crate::error::{AppResult, Error};
+use crate::event::Event;
+use serde::Deserialize;
+use std::fmt::Debug;
+use std::path::Path;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+use command::CommandConfig;
+use http::HttpConfig;
+
+/// The most texts one message may be split into, so a stray paste can't run up a bill.
+pub const MAX_SEGMENTS: usize = 10;
+
+/// The GSM 03.38 characters a plain text can hold in one septet each.
+const GSM_BASIC: &str = "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ !\"#¤%&'()*+,-./0123456789:;<=>?\
+¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿abcdefghijklmnopqrstuvwxyzäöñüà";
+
+/// Characters from the GSM extension table, which take two septets.
+const GSM_EXTENDED: &str = "^{}\\[~]|€\u{c}";
+
+/// How `sms.toml` says texts are sent, chosen by its `gateway` key.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "gateway", rename_all = "lowercase")]
+pub enum GatewayConfig {
+    Command(CommandConfig),
+    Http(HttpConfig),
+}
+
+impl GatewayConfig {
+    /// Reads `path`, returning `None` if it doesn't exist.
+    pub fn load(path: &Path) -> AppResult<Option<Self>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        toml::from_str(&text)
+            .map(Some)
+            .map_err(|err| Error::ConfigError(format!("{}: {}", path.display(), err.message())))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GatewayConfig::Command(_) => "command",
+            GatewayConfig::Http(_) => "http",
+        }
+    }
+}
+
+/// A text for a [`SmsGateway`] to send.
+#[derive(Debug, Clone)]
+pub struct SmsRequest {
+    /// The message's id in the log, which its [`SmsEvent`] carries.
+    pub message_id: u64,
+    pub to: String,
+    pub name: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmsEvent {
+    /// The gateway took the message.
+    Sent { message_id: u64, name: String },
+    Failed {
+        message_id: u64,
+        name: String,
+        message: String,
+    },
+}
+
+impl SmsEvent {
+    /// The [`SmsEvent::Failed`] for `err`, without repeating that it came from the gateway.
+    pub fn failed(message_id: u64, name: String, err: Error) -> Self {
+        let message = match err {
+            Error::SmsGateway(message) => message,
+            err => err.full_message(),
+        };
+        Self::Failed {
+            message_id,
+            name,
+            message,
+        }
+    }
+}
+
+/// Something that can send texts. Like a [`Dialler`](crate::dialler::Dialler), it reports a
+/// gateway that can't even start straight away, and how the send went as an [`SmsEvent`].
+pub trait SmsGateway: Debug {
+    fn send(&self, request: SmsRequest, events: Option<UnboundedSender<Event>>) -> AppResult<()>;
+}
+
+pub fn for_config(config: &GatewayConfig) -> Box<dyn SmsGateway> {
+    match config {
+        GatewayConfig::Command(config) => Box::new(command::CommandGateway::new(config.clone())),
+        GatewayConfig::Http(config) => Box::new(http::HttpGateway::new(config.clone())),
+    }
+}
+
+/// How long a message is and how many texts it takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segments {
+    /// Whether it needs UCS-2, which fits far fewer characters in a text than GSM-7.
+    pub unicode: bool,
+    /// In septets for GSM-7, or UTF-16 code units for UCS-2.
+    pub length: usize,
+    pub count: usize,
+    /// How much more fits before another text is needed.
+    pub remaining: usize,
+}
+
+impl Segments {
+    pub fn count(text: &str) -> Self {
+        let gsm_length = text
+            .chars()
+            .map(|c| match c {
+                c if GSM_BASIC.contains(c) => Some(1),
+                c if GSM_EXTENDED.contains(c) => Some(2),
+                _ => None,
+            })
+            .sum::<Option<usize>>();
+        let (unicode, length, single, multipart) = match gsm_length {
+            Some(length) => (false, length, 160, 153),
+            None => (true, text.encode_utf16().count(), 70, 67),
+        };
+
+        let count = if length <= single { 1 } else { length.div_ceil(multipart) };
+        let capacity = if count == 1 { single } else { count * multipart };
+        Self {
+            unicode,
+            length,
+            count,
+            remaining: capacity - length,
+        }
+    }
+}
+
+impl std::fmt::Display for Segments {
+    /// E.g. `42 characters, 1 SMS, 118 left`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let encoding = if self.unicode { " (Unicode)" } else { "" };
+        write!(
+            f,
+            "{} characters{encoding}, {} SMS, {} left",
+            self.length, self.count, self.remaining
+        )
+    }
+}
+
+/// Sends `event` to the loop, if there is one listening.
+fn send(events: &Option<UnboundedSender<Event>>, event: SmsEvent) {
+    if let Some(events) = events {
+        if events.send(Event::Sms(event)).is_err() {
+            warn!("SMS event arrived after the app closed");
+        }
+    }
+}
+
+fn gateway_error(message: impl Into<String>) -> Error {
+    Error::SmsGateway(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text_fits_160_characters_in_one_text() {
+        let one = Segments::count(&"a".repeat(160));
+        assert_eq!((one.unicode, one.length, one.count, one.remaining), (false, 160, 1, 0));
+        let two = Segments::count(&"a".repeat(161));
+        assert_eq!((two.count, two.remaining), (2, 145));
+        assert_eq!(Segments::count(&"a".repeat(306)).count, 2);
+        assert_eq!(Segments::count(&"a".repeat(307)).count, 3);
+    }
+
+    #[test]
+    fn extension_characters_take_two_septets() {
+        let segments = Segments::count("Costs €5 [approx]");
+        assert!(!segments.unicode);
+        assert_eq!(segments.length, 20);
+        assert_eq!(Segments::count(&"€".repeat(80)).count, 1);
+        assert_eq!(Segments::count(&"€".repeat(81)).count, 2);
+    }
+
+    #[test]
+    fn anything_outside_gsm_needs_ucs2_and_fits_70() {
+        let one = Segments::count(&format!("{}✓", "a".repeat(69)));
+        assert_eq!((one.unicode, one.length, one.count, one.remaining), (true, 70, 1, 0));
+        let two = Segments::count(&format!("{}✓", "a".repeat(70)));
+        assert_eq!((two.count, two.remaining), (2, 63));
+        assert_eq!(Segments::count(&"✓".repeat(134)).count, 2);
+        assert_eq!(Segments::count(&"✓".repeat(135)).count, 3);
+    }
+
+    #[test]
+    fn emoji_count_as_two_ucs2_characters() {
+        let segments = Segments::count("Thanks 👍");
+        assert_eq!((segments.unicode, segments.length), (true, 9));
+        assert_eq!(segments.to_string(), "9 characters (Unicode), 1 SMS, 61 left");
+    }
+}
@@ -0,0 +1,92 @@
+use crate::data_layer::contact::Contact;
+
+/// The placeholders a message can use, filled in from the contact it's for, e.g. `{first_name}`.
+pub const PLACEHOLDERS: &[&str] = &["first_name", "last_name", "name", "company", "phone", "email"];
+
+/// Fills in the placeholders in `text` from `contact`. They're left empty when there's no contact,
+/// and anything else in braces is left as it is.
+pub fn render(text: &str, contact: Option<&Contact>) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| placeholder(&after[..end], contact).map(|value| (value, end)));
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn placeholder(name: &str, contact: Option<&Contact>) -> Option<String> {
+    if !PLACEHOLDERS.contains(&name) {
+        return None;
+    }
+    let Some(contact) = contact else {
+        return Some(String::new());
+    };
+
+    let value = match name {
+        "first_name" => contact.first_name.clone(),
+        "last_name" => contact.last_name.clone(),
+        "name" => Some(contact.display_name()),
+        "company" => contact.company_name.clone(),
+        "phone" => Some(contact.phone_number.clone()),
+        "email" => contact.email.clone(),
+        _ => None,
+    };
+    Some(value.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jane() -> Contact {
+        Contact {
+            id: 1,
+            first_name: Some("Jane".to_string()),
+            last_name: Some("Smith".to_string()),
+            company_name: Some("Acme".to_string()),
+            phone_number: "(02) 9999 1234".to_string(),
+            email: None,
+            favourite: false,
+            speed_dial: None,
+            dtmf: None,
+        }
+    }
+
+    #[test]
+    fn placeholders_are_filled_in_from_the_contact() {
+        let text = "Hi {first_name}, is {name} of {company} still on {phone}?";
+        assert_eq!(render(text, Some(&jane())), "Hi Jane, is Jane Smith of Acme still on (02) 9999 1234?");
+    }
+
+    #[test]
+    fn details_the_contact_lacks_are_left_empty() {
+        assert_eq!(render("Email: {email}.", Some(&jane())), "Email: .");
+    }
+
+    #[test]
+    fn other_braces_are_left_as_they_are() {
+        let text = "{greeting} {first_name}{ {} {first_name";
+        assert_eq!(render(text, Some(&jane())), "{greeting} Jane{ {} {first_name");
+        assert_eq!(render("{{first_name}}", Some(&jane())), "{Jane}");
+    }
+
+    #[test]
+    fn without_a_contact_placeholders_are_left_empty() {
+        assert_eq!(render("Hi {first_name}, from {unknown}", None), "Hi , from {unknown}");
+    }
+}
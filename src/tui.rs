@@ -1,8 +1,11 @@
-use crate::app::{App, AppMode};
-use crate::error::AppResult;
+use crate::app::App;
+use crate::error::{AppResult, Error};
 use crate::event::EventHandler;
 use crate::renderer::Renderer;
-use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use crate::screen::ScreenStack;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::Backend;
 use ratatui::Terminal;
@@ -21,8 +24,14 @@ impl<B: Backend> Tui<B> {
     }
 
     pub fn init(&mut self) -> AppResult<()> {
-        terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)?;
+        terminal::enable_raw_mode().map_err(Error::Terminal)?;
+        crossterm::execute!(
+            io::stderr(),
+            EnterAlternateScreen,
+            EnableMouseCapture,
+            EnableBracketedPaste
+        )
+        .map_err(Error::Terminal)?;
 
         let panic_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic| {
@@ -30,41 +39,37 @@ impl<B: Backend> Tui<B> {
             panic_hook(panic);
         }));
 
-        self.terminal.hide_cursor()?;
-        self.terminal.clear()?;
+        self.terminal.hide_cursor().map_err(Error::Terminal)?;
+        self.terminal.clear().map_err(Error::Terminal)?;
         Ok(())
     }
 
     pub fn draw(&mut self, app: &mut App) -> AppResult<()> {
-        let mode = app.mode.clone();
-        match mode {
-            AppMode::AddingContact => self
-                .terminal
-                .draw(|frame| Renderer::render_add_contact_modal(app, frame))?,
-            AppMode::EditingContact => self
-                .terminal
-                .draw(|frame| Renderer::render_edit_contact_modal(app, frame))?,
-            AppMode::DeletingContact => self
-                .terminal
-                .draw(|frame| Renderer::render_delete_confirmation_modal(app, frame))?,
-            _ => self
-                .terminal
-                .draw(|frame| Renderer::render_main_window(app, frame))?,
-        };
+        self.terminal.draw(|frame| {
+            ScreenStack::render(app, frame);
+            Renderer::render_notifications(app, frame);
+        })
+        .map_err(Error::Terminal)?;
 
-        crossterm::execute!(io::stderr(), app.get_cursor_style())?;
+        crossterm::execute!(io::stderr(), app.get_cursor_style()).map_err(Error::Terminal)?;
         Ok(())
     }
 
     fn reset() -> AppResult<()> {
-        terminal::disable_raw_mode()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)?;
+        terminal::disable_raw_mode().map_err(Error::Terminal)?;
+        crossterm::execute!(
+            io::stderr(),
+            LeaveAlternateScreen,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )
+        .map_err(Error::Terminal)?;
         Ok(())
     }
 
     pub fn exit(&mut self) -> AppResult<()> {
         Self::reset()?;
-        self.terminal.show_cursor()?;
+        self.terminal.show_cursor().map_err(Error::Terminal)?;
         Ok(())
     }
 }
@@ -0,0 +1,355 @@
+use crate::dialler::dtmf;
+use crate::error::{AppResult, Error};
+use serde::Deserialize;
+
+/// Characters people write in numbers to make them readable, which aren't part of the number.
+/// RFC 3966 calls `-`, `.`, `(` and `)` visual separators.
+const VISUAL_SEPARATORS: [char; 5] = [' ', '-', '.', '(', ')'];
+
+/// Which URI a profile's dialler is given.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    Tel,
+    Sip,
+    Sips,
+}
+
+/// A profile's `uri` settings. Profiles without them dial bare digits.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UriConfig {
+    pub scheme: Scheme,
+    /// The SIP domain, or for `tel:` URIs the `phone-context` of numbers that don't start with `+`.
+    pub domain: Option<String>,
+    /// Added as `;transport=`, e.g. `tls`. SIP only.
+    pub transport: Option<String>,
+    /// Adds `;user=phone`, telling the SIP server the user part is a phone number.
+    #[serde(default)]
+    pub user_phone: bool,
+}
+
+impl UriConfig {
+    pub fn build(&self, number: &DialNumber) -> AppResult<String> {
+        match self.scheme {
+            Scheme::Tel => Ok(number.to_tel_uri(self.domain.as_deref())),
+            Scheme::Sip | Scheme::Sips => {
+                let domain = self
+                    .domain
+                    .as_deref()
+                    .ok_or_else(|| Error::ConfigError("sip URIs need a domain in the profile's uri settings".to_string()))?;
+                let scheme = if self.scheme == Scheme::Sips { "sips" } else { "sip" };
+                // With user=phone the user part is a telephone-subscriber, so local numbers need a context.
+                let context = self.user_phone.then_some(domain);
+                let user = escape(&number.telephone_subscriber(context), is_sip_user_char);
+                let mut uri = format!("{scheme}:{user}@{domain}");
+                if let Some(transport) = &self.transport {
+                    uri.push_str(&format!(";transport={}", transport.to_lowercase()));
+                }
+                if self.user_phone {
+                    uri.push_str(";user=phone");
+                }
+                Ok(uri)
+            }
+        }
+    }
+}
+
+/// A number to dial: the number itself, and the extension and post-dial DTMF that go after it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DialNumber {
+    /// Digits, with a leading `+` if the number is in international form.
+    pub number: String,
+    pub extension: Option<String>,
+    /// Tones to send once the call connects, with `,` to pause and `;` to wait. See [`dtmf`].
+    pub post_dial: Option<String>,
+}
+
+impl DialNumber {
+    /// The number as written in a contact, without the visual separators.
+    pub fn new(number: &str) -> Self {
+        Self {
+            number: strip_separators(number),
+            ..Self::default()
+        }
+    }
+
+    /// The number from a contact or call, with the tones stored beside it.
+    pub fn with_tones(number: &str, tones: Option<&str>) -> Self {
+        Self {
+            post_dial: tones.filter(|tones| !tones.is_empty()).map(str::to_string),
+            ..Self::new(number)
+        }
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.number.starts_with('+')
+    }
+
+    /// Everything to send after the number connects: the post-dial tones, or failing those the
+    /// extension after a pause.
+    pub fn tones(&self) -> Option<String> {
+        match (&self.post_dial, &self.extension) {
+            (Some(post_dial), _) => Some(post_dial.clone()),
+            (None, Some(extension)) => Some(format!(",{extension}")),
+            (None, None) => None,
+        }
+    }
+
+    /// The number alone, for backends that send the tones themselves.
+    pub fn without_tones(&self) -> Self {
+        Self::new(&self.number)
+    }
+
+    /// The number then its tones, the way phones take a dial string, e.g. `0299991234,,2`.
+    pub fn dial_string(&self) -> String {
+        format!("{}{}", self.number, self.tones().unwrap_or_default())
+    }
+
+    /// RFC 3966's `telephone-subscriber`: the number then `;ext=`, `;phone-context=` and `;postd=`,
+    /// with the context only added to local numbers.
+    pub fn telephone_subscriber(&self, phone_context: Option<&str>) -> String {
+        let mut subscriber = self.number.clone();
+        if let Some(extension) = &self.extension {
+            subscriber.push_str(&format!(";ext={extension}"));
+        }
+        if let Some(context) = phone_context.filter(|_| !self.is_global()) {
+            subscriber.push_str(&format!(";phone-context={context}"));
+        }
+        if let Some(post_dial) = &self.post_dial {
+            subscriber.push_str(&format!(";postd={}", dtmf::to_postd(post_dial)));
+        }
+        subscriber
+    }
+
+    pub fn to_tel_uri(&self, phone_context: Option<&str>) -> String {
+        format!("tel:{}", escape(&self.telephone_subscriber(phone_context), is_tel_char))
+    }
+
+    /// Reads the number out of a `tel:`, `sip:`, `sips:` or `callto:` URI.
+    pub fn parse_uri(uri: &str) -> AppResult<Self> {
+        let invalid = |reason: &str| Error::InvalidUri {
+            uri: uri.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let (scheme, rest) = uri.trim().split_once(':').ok_or_else(|| invalid("it has no scheme"))?;
+        let subscriber = match scheme.to_ascii_lowercase().as_str() {
+            "tel" => rest,
+            "sip" | "sips" => {
+                let rest = rest.split('?').next().unwrap_or_default();
+                let (user, _host) = rest.rsplit_once('@').ok_or_else(|| invalid("it has no number before the @"))?;
+                // A password, if any, follows the user.
+                user.split_once(':').map_or(user, |(user, _)| user)
+            }
+            "callto" => rest.trim_start_matches('/'),
+            _ => return Err(invalid("only tel:, sip:, sips: and callto: URIs can be dialled")),
+        };
+
+        let mut parts = subscriber.split(';');
+        let number = strip_separators(&unescape(parts.next().unwrap_or_default()));
+        let mut dial = Self {
+            number,
+            ..Self::default()
+        };
+        for param in parts {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            let value = unescape(value);
+            match name.to_ascii_lowercase().as_str() {
+                "ext" => dial.extension = Some(strip_separators(&value)),
+                "postd" => dial.post_dial = Some(dtmf::from_postd(&value)),
+                _ => {}
+            }
+        }
+
+        let digits = dial.number.strip_prefix('+').unwrap_or(&dial.number);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid("it isn't a telephone number"));
+        }
+        if let Some(post_dial) = &dial.post_dial {
+            if dtmf::validate(post_dial).is_err() {
+                return Err(invalid("the post-dial digits can only be 0-9, *, #, A-D, p and w"));
+            }
+        }
+        Ok(dial)
+    }
+}
+
+fn strip_separators(number: &str) -> String {
+    number.trim().chars().filter(|c| !VISUAL_SEPARATORS.contains(c)).collect()
+}
+
+/// Characters a `tel:` URI's subscriber part can hold as they are.
+fn is_tel_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_.!~*'()+;=[]/:&$".contains(c)
+}
+
+/// Characters a SIP URI's user part can hold as they are (RFC 3261 `unreserved` and
+/// `user-unreserved`).
+fn is_sip_user_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-_.!~*'()&=+$,;?/".contains(c)
+}
+
+/// A `mailto:` URI for writing `body` to `address` (RFC 6068).
+pub fn mailto(address: &str, body: &str) -> String {
+    let address = escape(address, |c| is_unreserved(c) || "@+!$'*=".contains(c));
+    if body.is_empty() {
+        return format!("mailto:{address}");
+    }
+    // Mail clients expect CRLF line breaks in the body.
+    let body = body.replace("\r\n", "\n").replace('\n', "\r\n");
+    format!("mailto:{address}?body={}", escape(&body, is_unreserved))
+}
+
+/// RFC 3986 `unreserved` characters, which never need escaping.
+fn is_unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "-._~".contains(c)
+}
+
+/// Percent-encodes the characters `allowed` doesn't allow, such as `#`.
+fn escape(text: &str, allowed: fn(char) -> bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if allowed(c) {
+            escaped.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                escaped.push_str(&format!("%{byte:02X}"));
+            }
+        }
+    }
+    escaped
+}
+
+/// Decodes `%xx` escapes, leaving malformed ones as they are.
+pub(crate) fn unescape(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        match (bytes[i], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_everything() -> DialNumber {
+        DialNumber {
+            number: "0299991234".to_string(),
+            extension: Some("12".to_string()),
+            post_dial: Some(",,2;345#".to_string()),
+        }
+    }
+
+    fn sip(scheme: Scheme, user_phone: bool) -> UriConfig {
+        UriConfig {
+            scheme,
+            domain: Some("pbx.example.com".to_string()),
+            transport: Some("TLS".to_string()),
+            user_phone,
+        }
+    }
+
+    #[test]
+    fn tel_uris_round_trip() {
+        let number = with_everything();
+        let uri = number.to_tel_uri(Some("pbx.example.com"));
+        assert_eq!(uri, "tel:0299991234;ext=12;phone-context=pbx.example.com;postd=pp2w345%23");
+        assert_eq!(DialNumber::parse_uri(&uri).unwrap(), number);
+    }
+
+    #[test]
+    fn global_numbers_get_no_phone_context() {
+        let number = DialNumber::new("+61 2 9999 1234");
+        let uri = number.to_tel_uri(Some("pbx.example.com"));
+        assert_eq!(uri, "tel:+61299991234");
+        assert_eq!(DialNumber::parse_uri(&uri).unwrap(), number);
+    }
+
+    #[test]
+    fn sip_uris_round_trip() {
+        let number = with_everything();
+        let uri = sip(Scheme::Sip, true).build(&number).unwrap();
+        assert_eq!(
+            uri,
+            "sip:0299991234;ext=12;phone-context=pbx.example.com;postd=pp2w345%23\
+             @pbx.example.com;transport=tls;user=phone"
+        );
+        assert_eq!(DialNumber::parse_uri(&uri).unwrap(), number);
+
+        let uri = sip(Scheme::Sips, false).build(&number).unwrap();
+        assert_eq!(uri, "sips:0299991234;ext=12;postd=pp2w345%23@pbx.example.com;transport=tls");
+        assert_eq!(DialNumber::parse_uri(&uri).unwrap(), number);
+    }
+
+    #[test]
+    fn sip_uris_need_a_domain() {
+        let config = UriConfig { domain: None, ..sip(Scheme::Sip, false) };
+        assert!(matches!(config.build(&DialNumber::new("0299991234")), Err(Error::ConfigError(_))));
+    }
+
+    #[test]
+    fn sip_uris_are_read_past_passwords_and_headers() {
+        let number = DialNumber::parse_uri("SIP:02-9999-1234:secret@pbx.example.com;transport=udp?subject=hi").unwrap();
+        assert_eq!(number, DialNumber::new("0299991234"));
+    }
+
+    #[test]
+    fn callto_uris_are_read_with_or_without_slashes() {
+        assert_eq!(DialNumber::parse_uri("callto://+61299991234").unwrap(), DialNumber::new("+61299991234"));
+        assert_eq!(DialNumber::parse_uri("callto:(02) 9999 1234").unwrap(), DialNumber::new("0299991234"));
+    }
+
+    #[test]
+    fn escaped_numbers_are_decoded() {
+        let number = DialNumber::parse_uri("tel:%2B61%20299991234;postd=1%23").unwrap();
+        assert_eq!(number.number, "+61299991234");
+        assert_eq!(number.post_dial.as_deref(), Some("1#"));
+    }
+
+    #[test]
+    fn uris_that_are_not_phone_numbers_are_refused() {
+        for uri in [
+            "0299991234",
+            "mailto:jane@example.com",
+            "sip:pbx.example.com",
+            "sip:jane@example.com",
+            "tel:",
+            "tel:0299991234;postd=1x",
+        ] {
+            assert!(matches!(DialNumber::parse_uri(uri), Err(Error::InvalidUri { .. })), "{uri}");
+        }
+    }
+
+    #[test]
+    fn escape_encodes_what_is_not_allowed_as_utf8_bytes() {
+        assert_eq!(escape("1#2 é", is_unreserved), "1%232%20%C3%A9");
+    }
+
+    #[test]
+    fn unescape_leaves_malformed_escapes_alone() {
+        assert_eq!(unescape("100%25 %zz %4"), "100% %zz %4");
+    }
+
+    #[test]
+    fn mailto_uses_crlf_line_breaks_in_the_body() {
+        assert_eq!(mailto("jane+work@example.com", ""), "mailto:jane+work@example.com");
+        assert_eq!(
+            mailto("jane@example.com", "Hi Jane,\nThanks"),
+            "mailto:jane@example.com?body=Hi%20Jane%2C%0D%0AThanks"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::data_layer::contact::Contact;
+
+/// The longest a vCard line may be, in bytes, before it's folded onto the next line.
+const LINE_LENGTH: usize = 75;
+
+/// The contact as a vCard 3.0 (RFC 2426), which address books and phones can import.
+pub fn format(contact: &Contact) -> String {
+    let field = |value: &Option<String>| value.as_deref().map(str::trim).unwrap_or_default().to_string();
+    let (first, last, company, email) = (
+        field(&contact.first_name),
+        field(&contact.last_name),
+        field(&contact.company_name),
+        field(&contact.email),
+    );
+
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:3.0".to_string(),
+        format!("N:{};{};;;", escape(&last), escape(&first)),
+        format!("FN:{}", escape(&contact.display_name())),
+    ];
+    if !company.is_empty() {
+        lines.push(format!("ORG:{}", escape(&company)));
+    }
+    lines.push(format!("TEL;TYPE=VOICE:{}", escape(contact.phone_number.trim())));
+    if !email.is_empty() {
+        lines.push(format!("EMAIL;TYPE=INTERNET:{}", escape(&email)));
+    }
+    lines.push("END:VCARD".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Escapes the characters that mean something in a vCard value.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ',' | ';' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Ends `line` with CRLF, folding it every [`LINE_LENGTH`] bytes onto lines starting with a space,
+/// without splitting a character.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > LINE_LENGTH {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(escape("Smith, Jones; Partners\\Co"), "Smith\\, Jones\\; Partners\\\\Co");
+        assert_eq!(escape("Level 2\r\nSydney"), "Level 2\\nSydney");
+    }
+
+    #[test]
+    fn short_lines_are_only_ended() {
+        assert_eq!(fold("FN:Jane Smith"), "FN:Jane Smith\r\n");
+        assert_eq!(fold(&"a".repeat(LINE_LENGTH)), format!("{}\r\n", "a".repeat(LINE_LENGTH)));
+    }
+
+    #[test]
+    fn long_lines_are_folded_every_75_bytes() {
+        let folded = fold(&"a".repeat(160));
+        let lines: Vec<&str> = folded.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+        assert_eq!(lines.iter().map(|line| line.len()).collect::<Vec<_>>(), [75, 75, 12]);
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        // Unfolding gives the line back.
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", "a".repeat(160)));
+    }
+
+    #[test]
+    fn folding_does_not_split_characters() {
+        let line = format!("ORG:{}", "é".repeat(40));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|line| line.len() <= LINE_LENGTH), "{folded:?}");
+        assert_eq!(folded.replace("\r\n ", ""), format!("{line}\r\n"));
+    }
+
+    #[test]
+    fn a_contact_becomes_a_vcard() {
+        let contact = Contact {
+            id: 1,
+            first_name: Some("Jane".to_string()),
+            last_name: Some("Smith".to_string()),
+            company_name: Some("Acme, Inc".to_string()),
+            phone_number: "(02) 9999 1234".to_string(),
+            email: Some("jane@example.com".to_string()),
+            favourite: false,
+            speed_dial: None,
+            dtmf: None,
+        };
+        assert_eq!(
+            format(&contact),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Smith;Jane;;;\r\nFN:Jane Smith\r\nORG:Acme\\, Inc\r\n\
+             TEL;TYPE=VOICE:(02) 9999 1234\r\nEMAIL;TYPE=INTERNET:jane@example.com\r\nEND:VCARD\r\n"
+        );
+    }
+}